@@ -0,0 +1,70 @@
+//! Counts the allocations made while parsing a wide, shallow document,
+//! where every element has a few attributes.
+//!
+//! Compare the results with and without the `smallvec` feature:
+//!
+//! ```text
+//! cargo bench --bench attributes
+//! cargo bench --bench attributes --features smallvec
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ELEMENTS: usize = 100_000;
+
+fn main() {
+    let mut input = String::from("<list>\n");
+    for i in 0..ELEMENTS {
+        writeln!(input, "<item id={} class=row><b>{}</b></item>", i, i).unwrap();
+    }
+    input.push_str("</list>\n");
+
+    let parser = sgmlish::Parser::new();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let fragment = parser.parse(&input).unwrap();
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!(
+        "smallvec feature: {}",
+        if cfg!(feature = "smallvec") {
+            "on"
+        } else {
+            "off"
+        }
+    );
+    println!("{} elements, {} events", ELEMENTS * 2 + 1, fragment.len());
+    println!(
+        "{} allocations ({:.2} per element) in {:?}",
+        allocations,
+        allocations as f64 / (ELEMENTS * 2 + 1) as f64,
+        elapsed
+    );
+}
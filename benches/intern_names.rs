@@ -0,0 +1,81 @@
+//! Measures the heap memory held by a parsed document whose element and
+//! attribute names are all owned, before and after interning them.
+//!
+//! The input is written in uppercase and parsed with lowercase normalization,
+//! so that every name is a separate allocation, as happens with case-insensitive
+//! formats like OFX.
+//!
+//! ```text
+//! cargo bench --bench intern_names --features intern
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use sgmlish::transforms::{intern_names, NameTable};
+
+struct CountingAllocator;
+
+/// The number of bytes currently allocated.
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LIVE_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const TRANSACTIONS: usize = 20_000;
+
+fn main() {
+    let mut input = String::from("<BANKTRANLIST>\n");
+    for i in 0..TRANSACTIONS {
+        writeln!(
+            input,
+            "<STMTTRN TYPE=DEBIT><DTPOSTED>20210101</DTPOSTED><TRNAMT>-{}.00</TRNAMT>\
+             <FITID>{}</FITID><NAME>Payee</NAME></STMTTRN>",
+            i, i
+        )
+        .unwrap();
+    }
+    input.push_str("</BANKTRANLIST>\n");
+
+    let parser = sgmlish::Parser::builder().lowercase_names().build();
+    let baseline = LIVE_BYTES.load(Ordering::Relaxed);
+    let fragment = parser.parse(&input).unwrap();
+    let parsed = LIVE_BYTES.load(Ordering::Relaxed) - baseline;
+    let events = fragment.len();
+
+    let start = Instant::now();
+    let names = NameTable::collect(&fragment);
+    let interned = intern_names(fragment, &names);
+    let elapsed = start.elapsed();
+    let after = LIVE_BYTES.load(Ordering::Relaxed) - baseline;
+
+    println!("{} events, {} distinct names", events, names.len());
+    println!("parsed:   {} bytes held", parsed);
+    println!(
+        "interned: {} bytes held, including the name table ({:.1}% of parsed) in {:?}",
+        after,
+        after as f64 * 100.0 / parsed as f64,
+        elapsed
+    );
+    drop(interned);
+}
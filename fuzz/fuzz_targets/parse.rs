@@ -0,0 +1,49 @@
+//! Parses arbitrary UTF-8 input with a few different configurations,
+//! checking that the parser never panics.
+//!
+//! Run with `cargo +nightly fuzz run parse` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sgmlish::parser::{MarkedSectionHandling, WhitespaceHandling};
+use sgmlish::Parser;
+
+fuzz_target!(|data: &[u8]| {
+    let input = match std::str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+
+    let parsers = [
+        Parser::new(),
+        Parser::builder()
+            .marked_section_handling(MarkedSectionHandling::ExpandAll)
+            .expand_entities(|_| Some("&x;<y>"))
+            .expand_parameter_entities(|_| Some("%x; IGNORE"))
+            .resolve_internal_entities(true)
+            .predefined_xml_entities(true)
+            .resolve_function_chars(true)
+            .require_balanced(true)
+            .build(),
+        Parser::builder()
+            .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+            .whitespace_handling(WhitespaceHandling::Separate)
+            .normalize_newlines(true)
+            .split_internal_subset(true)
+            .structured_pis(true)
+            .recognize_xml_declaration(true)
+            .strip_bom(true)
+            .allow_trailing_content(true)
+            .build(),
+    ];
+
+    for parser in &parsers {
+        if let Ok(fragment) = parser.parse(input) {
+            let _ = fragment.to_string();
+            let _ = sgmlish::transforms::normalize_end_tags(fragment);
+        }
+        let _ = parser.parse_one(input);
+        let _ = parser.parse_preserving_layout(input);
+    }
+});
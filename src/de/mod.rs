@@ -1,6 +1,7 @@
 //! Deserialize SGML data to a Rust data structure.
 
 use std::borrow::{BorrowMut, Cow};
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::{fmt, mem};
 
@@ -9,16 +10,33 @@ use serde::de::{self, IntoDeserializer, Unexpected};
 use serde::Deserializer;
 
 use crate::de::buffer::CowBuffer;
+use crate::text::{is_blank, is_sgml_whitespace};
 use crate::{SgmlEvent, SgmlFragment};
 
 mod buffer;
 
+/// The special field name that receives the text content of an element,
+/// or its child elements.
+const VALUE_KEY: &str = "$value";
+/// The special field name that receives all text within an element,
+/// including the text of child elements.
+const TEXT_KEY: &str = "$text";
+/// The special field name that receives the name of the element itself.
+const TAG_KEY: &str = "$tag";
+/// The separator between element names in a struct field naming a nested path.
+const PATH_SEPARATOR: char = '/';
+
 /// Deserializes an instance of type `T` from the given [`SgmlFragment`].
 ///
 /// Before invoking, make sure the content is *tag-valid* and consistently cased.
 /// That means all start tags must have a matching end tag with identical case,
 /// in a consistent hierarchy.
 ///
+/// Errors are reported along with the location where they occurred, as
+/// [`DeserializationError::AtPath`]; use [`inner`](DeserializationError::inner)
+/// to match on the underlying error, and [`path`](DeserializationError::path)
+/// to inspect the location.
+///
 /// # Example
 ///
 /// ```rust
@@ -71,7 +89,55 @@ where
     T: de::Deserialize<'de>,
 {
     let mut reader = SgmlDeserializer::from_fragment(fragment)?;
-    T::deserialize(&mut reader)
+    let root = match reader.peek() {
+        Ok(SgmlEvent::OpenStartTag { name }) => Some(name.to_string()),
+        _ => None,
+    };
+    T::deserialize(&mut reader).map_err(|err| match root {
+        Some(root) => err.at(root),
+        None => err,
+    })
+}
+
+/// Deserializes a value from text, parsing it with [`FromStr`](std::str::FromStr).
+///
+/// This is meant for non-string fields within a `#[serde(flatten)]` struct.
+/// To support flattening, Serde buffers attributes and child elements before knowing
+/// their target types, so text can only be replayed as strings; annotating such fields
+/// with `#[serde(deserialize_with = "sgmlish::de::deserialize_from_str")]` parses them instead.
+///
+/// # Example
+///
+/// ```rust
+/// # use serde::Deserialize;
+/// #[derive(Debug, Deserialize)]
+/// struct Cell {
+///     #[serde(flatten)]
+///     span: Span,
+///     label: String,
+/// }
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Span {
+///     #[serde(deserialize_with = "sgmlish::de::deserialize_from_str")]
+///     colspan: u32,
+/// }
+///
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse(r#"<td colspan="2"><label>Total</label></td>"#)?;
+/// let cell = sgmlish::from_fragment::<Cell>(sgml)?;
+/// assert_eq!(cell.span.colspan, 2);
+/// # Ok(())
+/// # }
+/// ```
+pub fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    T: std::str::FromStr,
+    T::Err: fmt::Display,
+{
+    let text = <String as de::Deserialize>::deserialize(deserializer)?;
+    text.parse().map_err(de::Error::custom)
 }
 
 /// A deserializer for SGML content.
@@ -81,6 +147,8 @@ pub struct SgmlDeserializer<'de> {
     stack: Vec<Cow<'de, str>>,
     map_key: Option<Rc<str>>,
     accumulated_text: Option<Cow<'de, str>>,
+    /// Set while reading a value for `deserialize_any`'s maps, where repeated elements form sequences
+    group_repeated: bool,
 }
 
 /// The error type for deserialization problems.
@@ -98,19 +166,96 @@ pub enum DeserializationError {
     #[error("deserialization of '{0}' is not supported")]
     Unsupported(SgmlEvent<'static>),
 
-    #[error("error parsing integer value: {source}")]
+    #[error("error parsing integer value {value:?} as {ty}: {source}")]
     ParseIntError {
-        #[from]
+        value: String,
+        ty: &'static str,
         source: std::num::ParseIntError,
     },
-    #[error("error parsing float value: {source}")]
+    #[error("error parsing float value {value:?} as {ty}: {source}")]
     ParseFloatError {
-        #[from]
+        value: String,
+        ty: &'static str,
         source: std::num::ParseFloatError,
     },
 
     #[error("{0}")]
     Message(String),
+
+    /// Another error, along with the location where it occurred.
+    ///
+    /// The path lists element names, with attributes prefixed by `@`
+    /// and positions within sequences (starting at 0) in brackets,
+    /// e.g. `root > item[2] > @price`. It is stored starting from the innermost segment,
+    /// as the error propagates outwards; use [`path`](Self::path) to read it from the root.
+    ///
+    /// Errors are wrapped in this variant whenever their location is known,
+    /// which includes nearly all errors returned by [`from_fragment`];
+    /// use [`inner`](Self::inner) to match on the underlying error.
+    #[error("{source} at {}", format_path(.path))]
+    AtPath {
+        path: Vec<String>,
+        source: Box<DeserializationError>,
+    },
+}
+
+impl DeserializationError {
+    /// Returns the underlying error, discarding the path where it occurred, if any.
+    pub fn inner(&self) -> &DeserializationError {
+        match self {
+            DeserializationError::AtPath { source, .. } => source,
+            err => err,
+        }
+    }
+
+    /// Returns the segments of the path where the error occurred, starting from the root,
+    /// or nothing if the location is unknown.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde::Deserialize;
+    /// #[derive(Debug, Deserialize)]
+    /// struct Item {
+    ///     price: u32,
+    /// }
+    ///
+    /// let sgml = sgmlish::parse("<item><price>free</price></item>").unwrap();
+    /// let err = sgmlish::from_fragment::<Item>(sgml).unwrap_err();
+    /// assert_eq!(err.path().collect::<Vec<_>>(), ["item", "price"]);
+    /// ```
+    pub fn path(&self) -> impl DoubleEndedIterator<Item = &str> {
+        let path: &[String] = match self {
+            DeserializationError::AtPath { path, .. } => path,
+            _ => &[],
+        };
+        path.iter().rev().map(String::as_str)
+    }
+
+    /// Adds an outer segment to the path where the error occurred.
+    fn at(self, segment: impl Into<String>) -> Self {
+        match self {
+            DeserializationError::AtPath { mut path, source } => {
+                path.push(segment.into());
+                DeserializationError::AtPath { path, source }
+            }
+            err => DeserializationError::AtPath {
+                path: vec![segment.into()],
+                source: Box::new(err),
+            },
+        }
+    }
+}
+
+fn format_path(path: &[String]) -> String {
+    let mut out = String::new();
+    for segment in path.iter().rev() {
+        if !out.is_empty() && !segment.starts_with('[') {
+            out.push_str(" > ");
+        }
+        out.push_str(segment);
+    }
+    out
 }
 
 impl<'de> SgmlDeserializer<'de> {
@@ -120,6 +265,7 @@ impl<'de> SgmlDeserializer<'de> {
             stack: Vec::new(),
             map_key: None,
             accumulated_text: None,
+            group_repeated: false,
         };
         reader.normalize_at_cursor()?;
         Ok(reader)
@@ -165,7 +311,9 @@ impl<'de> SgmlDeserializer<'de> {
             .find_map(|event| match event {
                 SgmlEvent::OpenStartTag { .. } => Some(true),
                 SgmlEvent::EndTag { .. } => Some(false),
-                SgmlEvent::Attribute { .. } => {
+                SgmlEvent::Attribute { .. }
+                | SgmlEvent::UnquotedAttribute { .. }
+                | SgmlEvent::QuotedAttribute { .. } => {
                     contains_attributes = true;
                     None
                 }
@@ -186,6 +334,105 @@ impl<'de> SgmlDeserializer<'de> {
         Ok(content)
     }
 
+    /// Checks whether the element at the cursor is immediately followed by a sibling element
+    /// with the same name, ignoring whitespace between them.
+    fn peek_repeated_sibling(&self) -> bool {
+        let mut events = self.events.as_slice().iter();
+        let name = match events.next() {
+            Some(SgmlEvent::OpenStartTag { name }) => name,
+            _ => return false,
+        };
+        let mut depth = 1usize;
+        for event in &mut events {
+            match event {
+                SgmlEvent::OpenStartTag { .. } => depth += 1,
+                SgmlEvent::EndTag { .. } | SgmlEvent::XmlCloseEmptyElement => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+        }
+        let next = events.find(|event| match event {
+            SgmlEvent::Character(text) => !text.is_empty(),
+            SgmlEvent::Whitespace(_) => false,
+            _ => true,
+        });
+        matches!(next, Some(SgmlEvent::OpenStartTag { name: next }) if next == name)
+    }
+
+    /// Moves the child elements of the element just pushed so that all children with
+    /// the same name follow the first of them, for [`peek_repeated_sibling`](Self::peek_repeated_sibling)
+    /// to group them. Other children, such as text, keep their relative order.
+    fn group_children_by_name(&mut self) {
+        let events = self.events.as_mut_slice();
+        let start = events
+            .iter()
+            .position(|event| !matches!(event, SgmlEvent::Attribute { .. }))
+            .unwrap_or(events.len());
+        if !matches!(events.get(start), Some(SgmlEvent::CloseStartTag)) {
+            return;
+        }
+
+        // Split the content into children: whole elements, or single other events
+        let mut children = Vec::new();
+        let mut child_start = start + 1;
+        let mut depth = 0usize;
+        let mut end = events.len();
+        for (index, event) in events.iter().enumerate().skip(child_start) {
+            match event {
+                SgmlEvent::OpenStartTag { .. } => depth += 1,
+                SgmlEvent::EndTag { .. } | SgmlEvent::XmlCloseEmptyElement if depth == 0 => {
+                    end = index;
+                    break;
+                }
+                SgmlEvent::EndTag { .. } | SgmlEvent::XmlCloseEmptyElement => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                children.push(child_start..index + 1);
+                child_start = index + 1;
+            }
+        }
+
+        let mut by_name = BTreeMap::<&str, Vec<usize>>::new();
+        for (child, range) in children.iter().enumerate() {
+            if let SgmlEvent::OpenStartTag { name } = &events[range.start] {
+                by_name.entry(name).or_default().push(child);
+            }
+        }
+        if by_name
+            .values()
+            .all(|group| group.windows(2).all(|w| w[1] == w[0] + 1))
+        {
+            return;
+        }
+        let mut order = Vec::with_capacity(children.len());
+        for (child, range) in children.iter().enumerate() {
+            match &events[range.start] {
+                SgmlEvent::OpenStartTag { name } => {
+                    // Only the first element of each name brings the group along
+                    if let Some(group) = by_name.remove(name.as_ref()) {
+                        order.extend(group);
+                    }
+                }
+                _ => order.push(child),
+            }
+        }
+        debug!("grouping repeated child elements: {:?}", order);
+
+        let content = start + 1..end;
+        let mut reordered = Vec::with_capacity(content.len());
+        for child in order {
+            for event in &mut events[children[child].clone()] {
+                reordered.push(mem::replace(event, SgmlEvent::XmlCloseEmptyElement));
+            }
+        }
+        for (slot, event) in events[content].iter_mut().zip(reordered) {
+            *slot = event;
+        }
+    }
+
     /// Rejects unsupported events (like empty start tags), ignores markup declarations and processing instructions,
     /// and ensures any `Data` is expanded
     fn normalize_at_cursor(&mut self) -> Result<(), DeserializationError> {
@@ -194,8 +441,19 @@ impl<'de> SgmlDeserializer<'de> {
             None => return Ok(()),
         };
         match event {
+            SgmlEvent::UnquotedAttribute { name, value }
+            | SgmlEvent::QuotedAttribute { name, value, .. } => {
+                // Quoting makes no difference when deserializing
+                *event = SgmlEvent::Attribute {
+                    name: mem::take(name),
+                    value: Some(mem::take(value)),
+                };
+                return Ok(());
+            }
             SgmlEvent::MarkupDeclaration { .. }
             | SgmlEvent::ProcessingInstruction(_)
+            | SgmlEvent::StructuredProcessingInstruction { .. }
+            | SgmlEvent::XmlDeclaration { .. }
             | SgmlEvent::MarkedSection { .. } => {}
             SgmlEvent::OpenStartTag { name } | SgmlEvent::EndTag { name } if name.is_empty() => {}
             _ => return Ok(()),
@@ -240,7 +498,7 @@ impl<'de> SgmlDeserializer<'de> {
             {
                 SgmlEvent::XmlCloseEmptyElement => {
                     self.stack.pop();
-                    return Ok(());
+                    return self.normalize_at_cursor();
                 }
                 SgmlEvent::EndTag { name } => {
                     self.check_stack_size(stack_size);
@@ -252,7 +510,7 @@ impl<'de> SgmlDeserializer<'de> {
                         });
                     }
                     debug!("popped({}): {:?}", stack_size - 1, name);
-                    return Ok(());
+                    return self.normalize_at_cursor();
                 }
                 SgmlEvent::OpenStartTag { name } => {
                     self.stack.push(name);
@@ -273,6 +531,44 @@ impl<'de> SgmlDeserializer<'de> {
         Ok(())
     }
 
+    /// Takes the events of the element at the cursor, up to its end tag, out of the stream.
+    ///
+    /// The event left at the cursor is not normalized.
+    fn take_element(&mut self) -> Vec<SgmlEvent<'de>> {
+        let mut element = Vec::new();
+        let mut depth = 0usize;
+        for event in &mut self.events {
+            match event {
+                SgmlEvent::OpenStartTag { .. } => depth += 1,
+                SgmlEvent::EndTag { .. } | SgmlEvent::XmlCloseEmptyElement => depth -= 1,
+                _ => {}
+            }
+            element.push(event);
+            if depth == 0 {
+                break;
+            }
+        }
+        element
+    }
+
+    /// Skips content within the current element until reaching a child element
+    /// with the given name, returning whether one was found.
+    fn seek_child(&mut self, name: &str) -> Result<bool, DeserializationError> {
+        loop {
+            match self.peek()? {
+                SgmlEvent::OpenStartTag { name: child } if child == name => return Ok(true),
+                SgmlEvent::OpenStartTag { .. } => {
+                    self.push_elt()?;
+                    self.pop_elt()?;
+                }
+                SgmlEvent::EndTag { .. } | SgmlEvent::XmlCloseEmptyElement => return Ok(false),
+                _ => {
+                    self.advance()?;
+                }
+            }
+        }
+    }
+
     /// Consumes an element and returns all its text.
     ///
     /// Includes text from child elements as well.
@@ -308,7 +604,7 @@ impl<'de> SgmlDeserializer<'de> {
                         break;
                     }
                 }
-                SgmlEvent::Character(t) => {
+                SgmlEvent::Character(t) | SgmlEvent::Whitespace(t) => {
                     text.push_cow(mem::take(t));
                     self.advance()?;
                 }
@@ -322,17 +618,76 @@ impl<'de> SgmlDeserializer<'de> {
         Ok(text.into_cow())
     }
 
+    /// Deserializes an element as a map, whose special entries are decided
+    /// by the given struct fields.
     fn do_map<'r, V>(
         &'r mut self,
         visitor: V,
-        emit_value: bool,
+        fields: &'static [&'static str],
     ) -> Result<V::Value, DeserializationError>
+    where
+        V: de::Visitor<'de>,
+    {
+        let tag_name = self.push_elt()?;
+        let tag_name = fields.contains(&TAG_KEY).then(|| tag_name.to_owned());
+        let stack_size = self.stack.len();
+        let map = MapAccess {
+            tag_name,
+            fields,
+            ..MapAccess::new(self, text_key(fields))
+        };
+        let value = visitor.visit_map(map)?;
+        self.check_stack_size(stack_size);
+        self.pop_elt()?;
+
+        Ok(value)
+    }
+
+    /// Deserializes an element with child elements as a map, for [`deserialize_any`].
+    ///
+    /// Unlike [`do_map`], runs of sibling elements with the same name are grouped
+    /// into sequences, and any non-blank text is kept under `$value`.
+    ///
+    /// [`deserialize_any`]: Deserializer::deserialize_any
+    /// [`do_map`]: Self::do_map
+    fn do_any_map<V>(&mut self, visitor: V) -> Result<V::Value, DeserializationError>
     where
         V: de::Visitor<'de>,
     {
         self.push_elt()?;
+        self.group_children_by_name();
         let stack_size = self.stack.len();
-        let value = visitor.visit_map(MapAccess::new(self, emit_value))?;
+        let value = visitor.visit_map(MapAccess::new_any(self))?;
+        self.check_stack_size(stack_size);
+        self.pop_elt()?;
+
+        Ok(value)
+    }
+
+    /// Deserializes a tuple from the child elements of the current element,
+    /// if it is the one named by the current map key and it has child elements;
+    /// otherwise, the tuple is read as a sequence of sibling elements.
+    fn do_tuple<V>(&mut self, visitor: V) -> Result<V::Value, DeserializationError>
+    where
+        V: de::Visitor<'de>,
+    {
+        let is_container = match (self.peek()?, &self.map_key) {
+            (SgmlEvent::OpenStartTag { name }, Some(map_key)) => {
+                name.as_ref() == map_key.as_ref()
+                    && self.peek_content_type()?.contains_child_elements
+            }
+            _ => false,
+        };
+        if !is_container {
+            return self.deserialize_seq(visitor);
+        }
+
+        debug!("tuple from child elements");
+        self.map_key = None;
+        self.push_elt()?;
+        self.advance_to_content()?;
+        let stack_size = self.stack.len();
+        let value = visitor.visit_seq(SeqAccess::new(self, None))?;
         self.check_stack_size(stack_size);
         self.pop_elt()?;
 
@@ -358,13 +713,21 @@ impl<'de> SgmlDeserializer<'de> {
 }
 
 macro_rules! forward_parse {
-    ($deserialize:ident => $visit:ident) => {
+    ($deserialize:ident => $visit:ident, $ty:ty, $error:ident) => {
         fn $deserialize<V>(self, visitor: V) -> Result<V::Value, DeserializationError>
         where
             V: de::Visitor<'de>,
         {
             trace!(stringify!($deserialize));
-            let value = self.consume_text::<V>()?.parse()?;
+            let text = self.consume_text::<V>()?;
+            let text = text.trim_matches(is_sgml_whitespace);
+            let value = text
+                .parse::<$ty>()
+                .map_err(|source| DeserializationError::$error {
+                    value: text.to_owned(),
+                    ty: stringify!($ty),
+                    source,
+                })?;
             visitor.$visit(value)
         }
     };
@@ -373,16 +736,16 @@ macro_rules! forward_parse {
 impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
     type Error = DeserializationError;
 
-    forward_parse!(deserialize_i8 => visit_i8);
-    forward_parse!(deserialize_i16 => visit_i16);
-    forward_parse!(deserialize_i32 => visit_i32);
-    forward_parse!(deserialize_i64 => visit_i64);
-    forward_parse!(deserialize_u8 => visit_u8);
-    forward_parse!(deserialize_u16 => visit_u16);
-    forward_parse!(deserialize_u32 => visit_u32);
-    forward_parse!(deserialize_u64 => visit_u64);
-    forward_parse!(deserialize_f32 => visit_f32);
-    forward_parse!(deserialize_f64 => visit_f64);
+    forward_parse!(deserialize_i8 => visit_i8, i8, ParseIntError);
+    forward_parse!(deserialize_i16 => visit_i16, i16, ParseIntError);
+    forward_parse!(deserialize_i32 => visit_i32, i32, ParseIntError);
+    forward_parse!(deserialize_i64 => visit_i64, i64, ParseIntError);
+    forward_parse!(deserialize_u8 => visit_u8, u8, ParseIntError);
+    forward_parse!(deserialize_u16 => visit_u16, u16, ParseIntError);
+    forward_parse!(deserialize_u32 => visit_u32, u32, ParseIntError);
+    forward_parse!(deserialize_u64 => visit_u64, u64, ParseIntError);
+    forward_parse!(deserialize_f32 => visit_f32, f32, ParseFloatError);
+    forward_parse!(deserialize_f64 => visit_f64, f64, ParseFloatError);
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
@@ -400,15 +763,14 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
         }
 
         let str = self.consume_text::<V>()?;
-        if str == "1" || str.eq_ignore_ascii_case("true") {
+        let str = str.trim_matches(is_sgml_whitespace);
+        if str == "1" || str.eq_ignore_ascii_case("true") || str.eq_ignore_ascii_case("yes") {
             visitor.visit_bool(true)
-        } else if str == "0" || str.eq_ignore_ascii_case("false") {
+        } else if str == "0" || str.eq_ignore_ascii_case("false") || str.eq_ignore_ascii_case("no")
+        {
             visitor.visit_bool(false)
         } else {
-            Err(de::Error::invalid_value(
-                Unexpected::Str(&str),
-                &"a boolean",
-            ))
+            Err(de::Error::invalid_value(Unexpected::Str(str), &"a boolean"))
         }
     }
 
@@ -435,24 +797,36 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        trace!("deserialize_char -> str");
-        self.deserialize_str(visitor)
+        trace!("deserialize_char");
+        let text = self.consume_text::<V>()?;
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(de::Error::invalid_value(
+                Unexpected::Str(&text),
+                &"a single character",
+            )),
+        }
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        trace!("deserialize_bytes -> str");
-        self.deserialize_str(visitor)
+        trace!("deserialize_bytes");
+        match self.consume_text::<V>()? {
+            Cow::Borrowed(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            Cow::Owned(s) => visitor.visit_byte_buf(s.into_bytes()),
+        }
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        trace!("deserialize_byte_buf -> str");
-        self.deserialize_str(visitor)
+        trace!("deserialize_byte_buf");
+        let text = self.consume_text::<V>()?;
+        visitor.visit_byte_buf(text.into_owned().into_bytes())
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -537,8 +911,8 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        trace!("deserialize_tuple ({} items) -> seq", len);
-        self.deserialize_seq(visitor)
+        trace!("deserialize_tuple ({} items)", len);
+        self.do_tuple(visitor)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -550,8 +924,8 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        trace!("deserialize_tuple_struct({}, {} items) -> seq", name, len);
-        self.deserialize_seq(visitor)
+        trace!("deserialize_tuple_struct({}, {} items)", name, len);
+        self.do_tuple(visitor)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -559,7 +933,7 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         trace!("deserialize_map");
-        self.do_map(visitor, false)
+        self.do_map(visitor, &[])
     }
 
     fn deserialize_struct<V>(
@@ -572,7 +946,7 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
         V: de::Visitor<'de>,
     {
         trace!("deserialize_struct({}) -> map", name);
-        self.do_map(visitor, fields.contains(&"$value"))
+        self.do_map(visitor, fields)
     }
 
     fn deserialize_enum<V>(
@@ -640,11 +1014,22 @@ impl<'de, 'r> Deserializer<'de> for &'r mut SgmlDeserializer<'de> {
         if self.accumulated_text.is_some() {
             return self.deserialize_str(visitor);
         }
+        let group_repeated = mem::take(&mut self.group_repeated);
         match self.peek()? {
-            SgmlEvent::OpenStartTag { .. } => {
+            SgmlEvent::OpenStartTag { name } => {
+                if group_repeated
+                    && self.map_key.as_deref() == Some(name.as_ref())
+                    && self.peek_repeated_sibling()
+                {
+                    debug!("sequence of repeated {:?} elements", name);
+                    let tag_name = self.map_key.take();
+                    return visitor.visit_seq(SeqAccess::new(self, tag_name));
+                }
                 let content = self.peek_content_type()?;
-                if content.contains_child_elements || content.contains_attributes {
-                    self.do_map(visitor, !content.contains_child_elements)
+                if content.contains_child_elements {
+                    self.do_any_map(visitor)
+                } else if content.contains_attributes {
+                    self.do_map(visitor, &[VALUE_KEY])
                 } else if content.contains_text {
                     self.deserialize_str(visitor)
                 } else {
@@ -671,39 +1056,180 @@ impl de::Error for DeserializationError {
     }
 }
 
+/// Finds which special field, if any, should receive the text content of an element.
+fn text_key(fields: &[&str]) -> Option<&'static str> {
+    if fields.contains(&TEXT_KEY) {
+        Some(TEXT_KEY)
+    } else if fields.contains(&VALUE_KEY) {
+        Some(VALUE_KEY)
+    } else {
+        None
+    }
+}
+
 struct MapAccess<'de, 'r> {
     de: &'r mut SgmlDeserializer<'de>,
     stack_size: usize,
     map_key: Option<Rc<str>>,
+    text_key: &'static str,
     content_strategy: ContentStrategy,
     text_content: Option<CowBuffer<'de>>,
     next_entry_is_dollarvalue: bool,
+    /// The element name, while it is yet to be reported as `$tag`
+    tag_name: Option<String>,
+    next_entry_is_tag: bool,
+    /// Set when reading a map for `deserialize_any`; blank text is then not reported as `$value`
+    any: bool,
+    /// The struct fields being read, if any; used to look up nested paths
+    fields: &'static [&'static str],
+    /// The nested path whose elements were entered for the current entry
+    path: Option<&'static str>,
+    /// Paths yet to be read from copies of the element under the cursor, in reverse order
+    pending_paths: Vec<&'static str>,
+    /// The events of the element read by several paths, copied for each of them
+    repeated_element: Vec<SgmlEvent<'de>>,
+    /// The events following the element read by several paths, set aside while reading it
+    after_repeated: Option<std::vec::IntoIter<SgmlEvent<'de>>>,
 }
 
 impl<'de, 'r> MapAccess<'de, 'r> {
-    fn new(de: &'r mut SgmlDeserializer<'de>, emit_value: bool) -> Self {
+    fn new(de: &'r mut SgmlDeserializer<'de>, text_key: Option<&'static str>) -> Self {
         let stack_size = de.stack.len();
-        let content_strategy = if emit_value {
-            if de
-                .peek_content_type()
-                .map(|content| content.contains_child_elements)
-                .unwrap_or(false)
-            {
-                ContentStrategy::ElementsAreDollarValue
-            } else {
-                ContentStrategy::TextOnly
+        let content_strategy = match text_key {
+            Some(TEXT_KEY) => ContentStrategy::AllText,
+            Some(_) => {
+                if de
+                    .peek_content_type()
+                    .map(|content| content.contains_child_elements)
+                    .unwrap_or(false)
+                {
+                    ContentStrategy::ElementsAreDollarValue
+                } else {
+                    ContentStrategy::TextOnly
+                }
             }
-        } else {
-            ContentStrategy::ElementsAreMapEntries
+            None => ContentStrategy::ElementsAreMapEntries,
         };
         Self {
             de,
             stack_size,
             map_key: None,
+            text_key: text_key.unwrap_or(VALUE_KEY),
             content_strategy,
-            text_content: (content_strategy == ContentStrategy::TextOnly).then(CowBuffer::new),
+            text_content: matches!(
+                content_strategy,
+                ContentStrategy::TextOnly | ContentStrategy::AllText
+            )
+            .then(CowBuffer::new),
             next_entry_is_dollarvalue: false,
+            tag_name: None,
+            next_entry_is_tag: false,
+            any: false,
+            fields: &[],
+            path: None,
+            pending_paths: Vec::new(),
+            repeated_element: Vec::new(),
+            after_repeated: None,
+        }
+    }
+
+    fn new_any(de: &'r mut SgmlDeserializer<'de>) -> Self {
+        Self {
+            text_content: Some(CowBuffer::new()),
+            any: true,
+            ..Self::new(de, None)
+        }
+    }
+
+    fn has_text_entry(&self) -> bool {
+        match &self.text_content {
+            Some(text) => !self.any || !is_blank(text.as_str()),
+            None => false,
+        }
+    }
+
+    /// Finds the struct field naming a nested path that starts at the element under the cursor.
+    ///
+    /// A field matching the element name exactly takes precedence over any path.
+    /// When several paths start at the same element, its events are set apart,
+    /// so each path is read from its own copy (see [`next_copy`](Self::next_copy)).
+    fn path_field(&mut self, name: &str) -> Option<&'static str> {
+        if let Some(path) = self.pending_paths.pop() {
+            return Some(path);
+        }
+        if self.fields.contains(&name) {
+            return None;
+        }
+        let mut paths = self
+            .fields
+            .iter()
+            .copied()
+            .filter(|field| {
+                field
+                    .strip_prefix(name)
+                    .and_then(|rest| rest.strip_prefix(PATH_SEPARATOR))
+                    .is_some()
+            })
+            .rev()
+            .collect::<Vec<_>>();
+        let path = paths.pop();
+        if !paths.is_empty() {
+            let element = self.de.take_element();
+            let first_copy = element.clone().into_iter();
+            self.after_repeated = Some(mem::replace(&mut self.de.events, first_copy));
+            self.repeated_element = element;
+        }
+        self.pending_paths = paths;
+        path
+    }
+
+    /// Once a copy of the element read by several paths is consumed, moves on to
+    /// the next copy, or back to the events after the element when no paths are left.
+    fn next_copy(&mut self) -> Result<(), DeserializationError> {
+        if self.after_repeated.is_none() || !self.de.events.as_slice().is_empty() {
+            return Ok(());
         }
+        self.de.events = match self.pending_paths.len() {
+            0 => self.after_repeated.take().unwrap(),
+            1 => mem::take(&mut self.repeated_element).into_iter(),
+            _ => self.repeated_element.clone().into_iter(),
+        };
+        self.de.normalize_at_cursor()
+    }
+
+    /// Descends into the elements along the given path, leaving the cursor
+    /// on its last element; returns `false`, having consumed the first element,
+    /// if any element along the path is missing.
+    fn enter_path(&mut self, path: &'static str) -> Result<bool, DeserializationError> {
+        // The cursor is already on the first element of the path
+        for (depth, segment) in path.split(PATH_SEPARATOR).skip(1).enumerate() {
+            self.de.push_elt()?;
+            self.de.advance_to_content()?;
+            if !self.de.seek_child(segment)? {
+                debug!("path {:?} not found", path);
+                for _ in 0..=depth {
+                    self.de.pop_elt()?;
+                }
+                return Ok(false);
+            }
+        }
+        self.path = Some(path);
+        Ok(true)
+    }
+
+    /// Consumes the rest of the elements entered by [`enter_path`](Self::enter_path).
+    fn leave_path(&mut self) -> Result<(), DeserializationError> {
+        if let Some(path) = self.path.take() {
+            for _ in path.matches(PATH_SEPARATOR) {
+                self.de.pop_elt()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn path_depth(&self) -> usize {
+        self.path
+            .map_or(0, |path| path.matches(PATH_SEPARATOR).count())
     }
 }
 
@@ -715,6 +1241,8 @@ enum ContentStrategy {
     ElementsAreMapEntries,
     /// Treat element content as the value for key `$value`
     ElementsAreDollarValue,
+    /// Collect all text, including from child elements, as the value for key `$text`
+    AllText,
 }
 
 impl<'de, 'r> de::MapAccess<'de> for MapAccess<'de, 'r> {
@@ -727,14 +1255,22 @@ impl<'de, 'r> de::MapAccess<'de> for MapAccess<'de, 'r> {
         trace!("next_key_seed");
         self.de.check_stack_size(self.stack_size);
 
+        if self.tag_name.is_some() && !self.next_entry_is_tag {
+            self.next_entry_is_tag = true;
+            debug!("next key: {}", TAG_KEY);
+            return seed.deserialize(TAG_KEY.into_deserializer()).map(Some);
+        }
+
         loop {
+            self.next_copy()?;
             break match self.de.peek_mut()? {
                 SgmlEvent::EndTag { .. } | SgmlEvent::XmlCloseEmptyElement => {
-                    if self.text_content.is_some() {
+                    if self.has_text_entry() {
                         self.next_entry_is_dollarvalue = true;
-                        debug!("next key: $value");
-                        self.map_key = Some("$value".into());
-                        seed.deserialize("$value".into_deserializer()).map(Some)
+                        debug!("next key: {}", self.text_key);
+                        self.map_key = Some(self.text_key.into());
+                        seed.deserialize(self.text_key.into_deserializer())
+                            .map(Some)
                     } else {
                         Ok(None)
                     }
@@ -750,18 +1286,33 @@ impl<'de, 'r> de::MapAccess<'de> for MapAccess<'de, 'r> {
                 }
                 SgmlEvent::OpenStartTag { name } => match self.content_strategy {
                     ContentStrategy::ElementsAreMapEntries => {
+                        let name = name.clone().into_owned();
+                        if let Some(path) = self.path_field(&name) {
+                            if !self.enter_path(path)? {
+                                continue;
+                            }
+                            debug!("next key: {} (from path)", path);
+                            self.map_key = path.rsplit(PATH_SEPARATOR).next().map(Into::into);
+                            return seed.deserialize(path.into_deserializer()).map(Some);
+                        }
                         debug!("next key: {} (from tag name)", name);
-                        self.map_key = Some(name.clone().into_owned().into());
-                        seed.deserialize(name.as_ref().into_deserializer())
-                            .map(Some)
+                        self.map_key = Some(name.as_str().into());
+                        seed.deserialize(name.into_deserializer()).map(Some)
                     }
                     ContentStrategy::ElementsAreDollarValue => {
                         debug!("next key: $value (for element {:?})", name);
-                        seed.deserialize("$value".into_deserializer()).map(Some)
+                        seed.deserialize(VALUE_KEY.into_deserializer()).map(Some)
+                    }
+                    ContentStrategy::AllText => {
+                        let text = self.de.consume_text::<de::IgnoredAny>()?;
+                        if let Some(value_acc) = &mut self.text_content {
+                            value_acc.push_cow(text);
+                        }
+                        continue;
                     }
                     ContentStrategy::TextOnly => unreachable!(),
                 },
-                SgmlEvent::Character(text) => {
+                SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) => {
                     let text = mem::take(text);
                     self.de.advance()?;
                     if let Some(value_acc) = &mut self.text_content {
@@ -770,6 +1321,10 @@ impl<'de, 'r> de::MapAccess<'de> for MapAccess<'de, 'r> {
                     continue;
                 }
                 SgmlEvent::ProcessingInstruction(_)
+                | SgmlEvent::StructuredProcessingInstruction { .. }
+                | SgmlEvent::XmlDeclaration { .. }
+                | SgmlEvent::UnquotedAttribute { .. }
+                | SgmlEvent::QuotedAttribute { .. }
                 | SgmlEvent::MarkupDeclaration { .. }
                 | SgmlEvent::MarkedSection { .. } => unreachable!(),
             };
@@ -781,19 +1336,41 @@ impl<'de, 'r> de::MapAccess<'de> for MapAccess<'de, 'r> {
         V: de::DeserializeSeed<'de>,
     {
         trace!("next_value_seed (key={:?})", self.map_key);
-        self.de.check_stack_size(self.stack_size);
-
-        if self.next_entry_is_dollarvalue {
+        self.de
+            .check_stack_size(self.stack_size + self.path_depth());
+
+        if self.next_entry_is_tag {
+            self.next_entry_is_tag = false;
+            let tag_name = self.tag_name.take().unwrap();
+            seed.deserialize(tag_name.into_deserializer())
+        } else if self.next_entry_is_dollarvalue {
             self.de.accumulated_text = Some(self.text_content.take().unwrap().into_cow());
-            let value = seed.deserialize(&mut *self.de)?;
+            let value = seed
+                .deserialize(&mut *self.de)
+                .map_err(|err| err.at(self.text_key))?;
             self.de.accumulated_text = None;
             Ok(value)
-        } else if let Ok(SgmlEvent::Attribute { .. }) = self.de.peek() {
+        } else if let Ok(SgmlEvent::Attribute { name, .. }) = self.de.peek() {
+            let segment = format!("@{}", name);
             seed.deserialize(&mut *self.de)
+                .map_err(|err| err.at(segment))
         } else {
+            let segment = self.map_key.as_deref().map(str::to_owned);
+            let path = self.path;
             self.de.map_key = self.map_key.take();
-            let value = seed.deserialize(&mut *self.de)?;
+            self.de.group_repeated = self.any;
+            let value = seed
+                .deserialize(&mut *self.de)
+                .map_err(|err| match (path, segment) {
+                    (Some(path), _) => path
+                        .rsplit(PATH_SEPARATOR)
+                        .fold(err, |err, segment| err.at(segment)),
+                    (None, Some(segment)) => err.at(segment),
+                    (None, None) => err,
+                })?;
             self.de.map_key = None;
+            self.de.group_repeated = false;
+            self.leave_path()?;
             Ok(value)
         }
     }
@@ -803,6 +1380,7 @@ struct SeqAccess<'de, 'r> {
     de: &'r mut SgmlDeserializer<'de>,
     stack_size: usize,
     tag_name: Option<Rc<str>>,
+    index: usize,
 }
 
 impl<'de, 'r> SeqAccess<'de, 'r> {
@@ -812,6 +1390,7 @@ impl<'de, 'r> SeqAccess<'de, 'r> {
             de,
             stack_size,
             tag_name,
+            index: 0,
         }
     }
 }
@@ -832,13 +1411,20 @@ impl<'de, 'r> de::SeqAccess<'de> for SeqAccess<'de, 'r> {
                         return Ok(None)
                     }
                     _ => {
+                        self.de.group_repeated = false;
                         if self.de.map_key != self.tag_name {
                             self.de.map_key = self.tag_name.clone();
                         }
-                        return Ok(Some(seed.deserialize(&mut *self.de)?));
+                        let index = self.index;
+                        self.index += 1;
+                        return seed
+                            .deserialize(&mut *self.de)
+                            .map(Some)
+                            .map_err(|err| err.at(format!("[{}]", index)));
                     }
                 },
                 SgmlEvent::Character(text) if text.is_empty() => self.de.advance()?,
+                SgmlEvent::Whitespace(_) => self.de.advance()?,
                 _ => return Ok(None),
             };
         }
@@ -932,7 +1518,7 @@ impl<'de, 'r> de::VariantAccess<'de> for EnumAccess<'de, 'r> {
         V: de::Visitor<'de>,
     {
         trace!("struct_variant");
-        self.de.do_map(visitor, fields.contains(&"$value"))
+        self.de.do_map(visitor, fields)
     }
 }
 
@@ -0,0 +1,745 @@
+//! Items related to parsing declarations from document type definitions.
+//!
+//! Only a small subset of DTD features is supported; see each item for details.
+
+use alloc::borrow::Cow;
+
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, tag_no_case, take_till};
+use nom::character::complete::{char, multispace0, multispace1, one_of, satisfy};
+use nom::combinator::{map, not, opt, value};
+use nom::error::{make_error, ErrorKind};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+use crate::entities;
+use crate::parser::raw;
+use crate::parser::util::spaces;
+use crate::prelude::*;
+
+/// An entity declared with a literal value, e.g. `<!ENTITY amp "&#38;">`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EntityDeclaration<'a> {
+    /// The name of the entity.
+    pub name: &'a str,
+    /// Whether this is a parameter entity (`<!ENTITY % example "...">`).
+    pub is_parameter: bool,
+    /// How the replacement text is meant to be interpreted.
+    pub kind: EntityKind,
+    /// The replacement text of the entity, with character references expanded.
+    pub text: Cow<'a, str>,
+}
+
+/// The type of an entity declared with a literal value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EntityKind {
+    /// Regular replacement text, e.g. `<!ENTITY example "text">`.
+    Text,
+    /// Character data, never scanned for markup, e.g. `<!ENTITY example CDATA "text">`.
+    CData,
+    /// System-specific data, e.g. `<!ENTITY mdash SDATA "[mdash ]">`.
+    ///
+    /// The text is usually only meaningful to the application,
+    /// which is expected to map it to actual characters.
+    SData,
+}
+
+/// An entity declared with an external identifier, e.g. `<!ENTITY chapter SYSTEM "ch1.sgm">`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExternalEntityDeclaration<'a> {
+    /// The name of the entity.
+    pub name: &'a str,
+    /// Whether this is a parameter entity (`<!ENTITY % example SYSTEM "...">`).
+    pub is_parameter: bool,
+    /// The public identifier, if declared with `PUBLIC`, as written.
+    pub public_id: Option<&'a str>,
+    /// The system identifier, if any; `SYSTEM` alone leaves it up to the application
+    /// to locate the entity.
+    pub system_id: Option<&'a str>,
+}
+
+/// An element type declaration, e.g. `<!ELEMENT p - O (#PCDATA)>`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ElementDeclaration<'a> {
+    /// The names of the declared element types; more than one when
+    /// declared with a name group, e.g. `<!ELEMENT (ul|ol) - - (li)+>`.
+    pub names: Vec<&'a str>,
+    /// Whether the start tag may be omitted (the first `O` in `O O`).
+    pub omit_start: bool,
+    /// Whether the end tag may be omitted (the second `O` in `- O`).
+    pub omit_end: bool,
+    /// The allowed content of the elements.
+    pub content: ContentModel<'a>,
+    /// Elements that may not occur anywhere within these elements, e.g. `-(a)`.
+    pub exclusions: Vec<&'a str>,
+    /// Elements that may occur anywhere within these elements, e.g. `+(ins|del)`.
+    pub inclusions: Vec<&'a str>,
+}
+
+/// The declared content of an element type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ContentModel<'a> {
+    /// Character data, never scanned for markup (`CDATA`).
+    CData,
+    /// Character data where only entity and character references are recognized (`RCDATA`).
+    RcData,
+    /// No content, and no end tag (`EMPTY`).
+    Empty,
+    /// Any mix of text and declared elements (`ANY`).
+    Any,
+    /// A model group, e.g. `(head, body)`.
+    Group(ModelGroup<'a>),
+}
+
+/// A parenthesized group of content tokens, e.g. `(title, (p|list)*)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModelGroup<'a> {
+    /// How the tokens are combined.
+    /// Groups with a single token are reported as [`Connector::Seq`].
+    pub connector: Connector,
+    /// The tokens within the group, in declaration order.
+    pub tokens: Vec<ContentToken<'a>>,
+    /// How many times the whole group may occur.
+    pub occurrence: Occurrence,
+}
+
+/// An item within a [`ModelGroup`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ContentToken<'a> {
+    /// Character data (`#PCDATA`).
+    PcData,
+    /// An element, along with how many times it may occur, e.g. `li+`.
+    Element {
+        name: &'a str,
+        occurrence: Occurrence,
+    },
+    /// A nested group.
+    Group(ModelGroup<'a>),
+}
+
+/// A connector between tokens of a [`ModelGroup`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Connector {
+    /// All tokens must occur, in order (`,`).
+    Seq,
+    /// Exactly one of the tokens must occur (`|`).
+    Or,
+    /// All tokens must occur, in any order (`&`).
+    And,
+}
+
+/// An occurrence indicator for a content token or group.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Occurrence {
+    /// Exactly once (no indicator).
+    Once,
+    /// Zero or one time (`?`).
+    Optional,
+    /// Any number of times, including none (`*`).
+    ZeroOrMore,
+    /// One or more times (`+`).
+    OneOrMore,
+}
+
+/// Extracts the internal subset (the part between `[` and `]`) from the body
+/// of a `DOCTYPE` declaration.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::dtd::internal_subset;
+/// let body = r#"doc SYSTEM "doc.dtd" [ <!ENTITY x "y"> ]"#;
+/// assert_eq!(internal_subset(body), Some(r#" <!ENTITY x "y"> "#));
+/// assert_eq!(internal_subset("HTML"), None);
+/// ```
+pub fn internal_subset(doctype_body: &str) -> Option<&str> {
+    let mut input = doctype_body;
+    loop {
+        let (rest, _) = spaces::<nom::error::Error<_>>(input).ok()?;
+        if let Ok((_, subset)) = raw::declaration_subset::<nom::error::Error<_>>(rest) {
+            return Some(subset);
+        }
+        let (rest, _) = alt((
+            raw::comment::<nom::error::Error<_>>,
+            raw::quoted_attribute_value,
+            is_not("\"'[- \n\r\t"),
+            tag("-"),
+        ))(rest)
+        .ok()?;
+        input = rest;
+    }
+}
+
+/// Returns all entity declarations with literal values found in the given
+/// declaration subset.
+///
+/// Declarations inside marked sections and comments are skipped,
+/// as are external entities (`<!ENTITY example SYSTEM "example.ent">`)
+/// the default entity (`<!ENTITY #DEFAULT "...">`), and entity types other
+/// than `CDATA` and `SDATA` (e.g. `<!ENTITY example PI "...">`).
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::dtd::{entity_declarations, EntityDeclaration, EntityKind};
+/// let subset = r#"
+///     <!ENTITY % draft "IGNORE">
+///     <!ENTITY copy "&#169;">
+///     <!ELEMENT doc - - (#PCDATA)>
+/// "#;
+/// assert_eq!(
+///     entity_declarations(subset),
+///     vec![
+///         EntityDeclaration {
+///             name: "draft",
+///             is_parameter: true,
+///             kind: EntityKind::Text,
+///             text: "IGNORE".into(),
+///         },
+///         EntityDeclaration {
+///             name: "copy",
+///             is_parameter: false,
+///             kind: EntityKind::Text,
+///             text: "©".into(),
+///         },
+///     ]
+/// );
+/// ```
+pub fn entity_declarations(subset: &str) -> Vec<EntityDeclaration<'_>> {
+    declarations(subset, "ENTITY", entity_declaration)
+}
+
+/// Returns all external entity declarations found in the given declaration subset.
+///
+/// Only entities holding text are returned; declarations with a data type,
+/// such as `<!ENTITY logo SYSTEM "logo.gif" NDATA gif>`, are skipped.
+/// As with [`entity_declarations`], declarations inside marked sections
+/// and comments are skipped too.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::dtd::{external_entity_declarations, ExternalEntityDeclaration};
+/// let subset = r#"
+///     <!ENTITY % ISOlat1 PUBLIC "ISO 8879:1986//ENTITIES Added Latin 1//EN">
+///     <!ENTITY chapter1 SYSTEM "ch1.sgm">
+///     <!ENTITY copy "&#169;">
+/// "#;
+/// assert_eq!(
+///     external_entity_declarations(subset),
+///     vec![
+///         ExternalEntityDeclaration {
+///             name: "ISOlat1",
+///             is_parameter: true,
+///             public_id: Some("ISO 8879:1986//ENTITIES Added Latin 1//EN"),
+///             system_id: None,
+///         },
+///         ExternalEntityDeclaration {
+///             name: "chapter1",
+///             is_parameter: false,
+///             public_id: None,
+///             system_id: Some("ch1.sgm"),
+///         },
+///     ]
+/// );
+/// ```
+pub fn external_entity_declarations(subset: &str) -> Vec<ExternalEntityDeclaration<'_>> {
+    declarations(subset, "ENTITY", external_entity_declaration)
+}
+
+/// Returns all element type declarations found in the given declaration subset.
+///
+/// Declarations inside marked sections and comments are skipped, as are
+/// declarations that cannot be parsed without further processing,
+/// such as those containing parameter entity references (`%inline;`)
+/// or ranked elements.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::dtd::*;
+/// let subset = r#"
+///     <!ELEMENT doc - - (title, p*)>
+///     <!ELEMENT (title|p) - O (#PCDATA)>
+/// "#;
+/// let declarations = element_declarations(subset);
+/// assert_eq!(declarations.len(), 2);
+/// assert_eq!(declarations[1].names, ["title", "p"]);
+/// assert!(!declarations[1].omit_start);
+/// assert!(declarations[1].omit_end);
+/// assert_eq!(
+///     declarations[0].content,
+///     ContentModel::Group(ModelGroup {
+///         connector: Connector::Seq,
+///         tokens: vec![
+///             ContentToken::Element { name: "title", occurrence: Occurrence::Once },
+///             ContentToken::Element { name: "p", occurrence: Occurrence::ZeroOrMore },
+///         ],
+///         occurrence: Occurrence::Once,
+///     })
+/// );
+/// ```
+pub fn element_declarations(subset: &str) -> Vec<ElementDeclaration<'_>> {
+    declarations(subset, "ELEMENT", element_declaration)
+}
+
+/// Parses all markup declarations with the given keyword found in the subset.
+fn declarations<'a, T, F>(subset: &'a str, keyword: &str, mut parse: F) -> Vec<T>
+where
+    F: FnMut(&'a str) -> Option<T>,
+{
+    let mut declarations = Vec::new();
+    for_each_declaration(subset, |found, body| {
+        if found.eq_ignore_ascii_case(keyword) {
+            declarations.extend(parse(body));
+        }
+    });
+    declarations
+}
+
+/// Returns the keyword and body of all markup declarations found in the subset,
+/// e.g. `ENTITY` and `example "value"` for `<!ENTITY example "value">`.
+pub(crate) fn markup_declarations(subset: &str) -> Vec<(&str, &str)> {
+    let mut declarations = Vec::new();
+    for_each_declaration(subset, |keyword, body| declarations.push((keyword, body)));
+    declarations
+}
+
+/// Calls `f` with the keyword and body of every markup declaration found in the subset.
+fn for_each_declaration<'a, F>(subset: &'a str, mut f: F)
+where
+    F: FnMut(&'a str, &'a str),
+{
+    type E<'a> = nom::error::Error<&'a str>;
+
+    let mut input = subset;
+    while !input.is_empty() {
+        if let Ok((rest, (keyword, body))) = raw::markup_declaration::<E>(input) {
+            f(keyword, body);
+            input = rest;
+        } else if let Ok((rest, _)) = raw::comment_declaration::<E>(input) {
+            input = rest;
+        } else if let Ok((rest, _)) = raw::marked_section_start_and_keywords::<E>(input)
+            .and_then(|(rest, _)| raw::marked_section_body_ignore::<E>(rest))
+        {
+            input = rest;
+        } else {
+            // Skip anything else (spaces, parameter entity references, malformed declarations)
+            let skip = input.chars().next().map(char::len_utf8).unwrap_or(0);
+            let (rest, _) = take_till::<_, _, E>(|c| c == '<')(&input[skip..]).unwrap();
+            input = rest;
+        }
+    }
+}
+
+/// Parses the body of an `ENTITY` declaration with a literal value,
+/// e.g. `% example "value"` or `example SDATA "value"`.
+fn entity_declaration(body: &str) -> Option<EntityDeclaration<'_>> {
+    let result: IResult<_, _, nom::error::Error<_>> = tuple((
+        opt(terminated(char('%'), multispace1)),
+        raw::name,
+        preceded(multispace1, opt(terminated(entity_kind, multispace1))),
+        raw::quoted_attribute_value,
+    ))(body);
+    let (_, (percent, name, kind, text)) = result.ok()?;
+    Some(EntityDeclaration {
+        name,
+        is_parameter: percent.is_some(),
+        kind: kind.unwrap_or(EntityKind::Text),
+        text: entities::expand_characters(text).unwrap_or_else(|_| text.into()),
+    })
+}
+
+/// Parses the body of an `ENTITY` declaration with an external identifier,
+/// e.g. `% example PUBLIC "public" "system"` or `example SYSTEM`.
+fn external_entity_declaration(body: &str) -> Option<ExternalEntityDeclaration<'_>> {
+    let result: IResult<_, _> = tuple((
+        opt(terminated(char('%'), multispace1)),
+        raw::name,
+        preceded(multispace1, external_identifier),
+        not(preceded(multispace1, entity_data_type)),
+    ))(body);
+    let (_, (percent, name, (public_id, system_id), _)) = result.ok()?;
+    Some(ExternalEntityDeclaration {
+        name,
+        is_parameter: percent.is_some(),
+        public_id,
+        system_id,
+    })
+}
+
+fn external_identifier(input: &str) -> IResult<&str, (Option<&str>, Option<&str>)> {
+    let system_id = || opt(preceded(multispace1, raw::quoted_attribute_value));
+    alt((
+        preceded(
+            keyword("PUBLIC"),
+            pair(
+                map(preceded(multispace1, raw::quoted_attribute_value), Some),
+                system_id(),
+            ),
+        ),
+        map(preceded(keyword("SYSTEM"), system_id()), |system_id| {
+            (None, system_id)
+        }),
+    ))(input)
+}
+
+/// Matches the data type of an external entity, e.g. `NDATA gif`.
+fn entity_data_type(input: &str) -> IResult<&str, &str> {
+    alt((
+        keyword("CDATA"),
+        keyword("SDATA"),
+        keyword("NDATA"),
+        keyword("SUBDOC"),
+    ))(input)
+}
+
+/// Matches the given keyword, case-insensitively, as long as it is not followed by more name characters.
+fn keyword<'a>(keyword: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    terminated(tag_no_case(keyword), not(satisfy(raw::is_name_char)))
+}
+
+fn entity_kind(input: &str) -> IResult<&str, EntityKind> {
+    alt((
+        value(EntityKind::CData, tag_no_case("CDATA")),
+        value(EntityKind::SData, tag_no_case("SDATA")),
+    ))(input)
+}
+
+/// Parses the body of an `ELEMENT` declaration, e.g. `p - O (#PCDATA)`.
+fn element_declaration(body: &str) -> Option<ElementDeclaration<'_>> {
+    let result: IResult<_, _> = tuple((
+        alt((name_group, map(raw::name, |name| vec![name]))),
+        preceded(multispace1, opt(terminated(omission_flags, multispace1))),
+        declared_content,
+        opt(preceded(pair(multispace0, char('-')), name_group)),
+        opt(preceded(pair(multispace0, char('+')), name_group)),
+    ))(body);
+    let (_, (names, omission, content, exclusions, inclusions)) = result.ok()?;
+    let (omit_start, omit_end) = omission.unwrap_or_default();
+    Some(ElementDeclaration {
+        names,
+        omit_start,
+        omit_end,
+        content,
+        exclusions: exclusions.unwrap_or_default(),
+        inclusions: inclusions.unwrap_or_default(),
+    })
+}
+
+/// Parses a group of names joined by any connector, e.g. `(ul|ol)`.
+fn name_group(input: &str) -> IResult<&str, Vec<&str>> {
+    delimited(
+        pair(char('('), multispace0),
+        separated_list1(
+            delimited(multispace0, one_of(",|&"), multispace0),
+            raw::name,
+        ),
+        pair(multispace0, char(')')),
+    )(input)
+}
+
+/// Parses the start and end tag omission flags, e.g. `- O`.
+fn omission_flags(input: &str) -> IResult<&str, (bool, bool)> {
+    let flag = || alt((value(false, char('-')), value(true, one_of("Oo"))));
+    pair(flag(), preceded(multispace1, flag()))(input)
+}
+
+fn declared_content(input: &str) -> IResult<&str, ContentModel<'_>> {
+    alt((
+        value(ContentModel::CData, keyword("CDATA")),
+        value(ContentModel::RcData, keyword("RCDATA")),
+        value(ContentModel::Empty, keyword("EMPTY")),
+        value(ContentModel::Any, keyword("ANY")),
+        map(model_group, ContentModel::Group),
+    ))(input)
+}
+
+fn model_group(input: &str) -> IResult<&str, ModelGroup<'_>> {
+    let (mut input, first) = preceded(pair(char('('), multispace0), content_token)(input)?;
+    let mut tokens = vec![first];
+    let mut connector = None;
+    loop {
+        if let Ok((rest, _)) = pair(multispace0, char::<_, nom::error::Error<_>>(')'))(input) {
+            input = rest;
+            break;
+        }
+        let (rest, (found, token)) = pair(
+            delimited(multispace0, group_connector, multispace0),
+            content_token,
+        )(input)?;
+        // Connectors cannot be mixed within a group
+        if *connector.get_or_insert(found) != found {
+            return Err(nom::Err::Error(make_error(input, ErrorKind::Verify)));
+        }
+        tokens.push(token);
+        input = rest;
+    }
+    let (input, occurrence) = occurrence(input)?;
+    Ok((
+        input,
+        ModelGroup {
+            connector: connector.unwrap_or(Connector::Seq),
+            tokens,
+            occurrence,
+        },
+    ))
+}
+
+fn content_token(input: &str) -> IResult<&str, ContentToken<'_>> {
+    alt((
+        value(ContentToken::PcData, tag_no_case("#PCDATA")),
+        map(model_group, ContentToken::Group),
+        map(pair(raw::name, occurrence), |(name, occurrence)| {
+            ContentToken::Element { name, occurrence }
+        }),
+    ))(input)
+}
+
+fn group_connector(input: &str) -> IResult<&str, Connector> {
+    alt((
+        value(Connector::Seq, char(',')),
+        value(Connector::Or, char('|')),
+        value(Connector::And, char('&')),
+    ))(input)
+}
+
+fn occurrence(input: &str) -> IResult<&str, Occurrence> {
+    map(opt(one_of("?*+")), |indicator| match indicator {
+        Some('?') => Occurrence::Optional,
+        Some('*') => Occurrence::ZeroOrMore,
+        Some('+') => Occurrence::OneOrMore,
+        _ => Occurrence::Once,
+    })(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_subset() {
+        assert_eq!(
+            internal_subset("doc [<!ENTITY x 'y'>]"),
+            Some("<!ENTITY x 'y'>")
+        );
+        assert_eq!(
+            internal_subset(r#"doc PUBLIC "-//X//[not a subset]//EN" -- [nor this] -- [ ]"#),
+            Some(" ")
+        );
+        assert_eq!(
+            internal_subset(r#"HTML PUBLIC "-//W3C//DTD HTML 4.01//EN""#),
+            None
+        );
+        assert_eq!(internal_subset(""), None);
+    }
+
+    #[test]
+    fn test_entity_declarations() {
+        let subset = r##"
+            <!-- <!ENTITY commented "x"> -->
+            <!ENTITY % cond "INCLUDE">
+            %cond;
+            <![ IGNORE [ <!ENTITY ignored "x"> ]]>
+            <!entity plain 'plain &#38; simple'>
+            <!ENTITY external SYSTEM "external.ent">
+            <!ENTITY #DEFAULT "default">
+            <!ENTITY	spaced
+                "spaced"  -- with a comment -->
+            <!ENTITY mdash SDATA "[mdash ]">
+            <!ENTITY raw cdata "<raw>">
+            <!ENTITY pi PI "pi">
+            <!ELEMENT doc - - ANY>
+        "##;
+        assert_eq!(
+            entity_declarations(subset),
+            vec![
+                EntityDeclaration {
+                    name: "cond",
+                    is_parameter: true,
+                    kind: EntityKind::Text,
+                    text: "INCLUDE".into(),
+                },
+                EntityDeclaration {
+                    name: "plain",
+                    is_parameter: false,
+                    kind: EntityKind::Text,
+                    text: "plain & simple".into(),
+                },
+                EntityDeclaration {
+                    name: "spaced",
+                    is_parameter: false,
+                    kind: EntityKind::Text,
+                    text: "spaced".into(),
+                },
+                EntityDeclaration {
+                    name: "mdash",
+                    is_parameter: false,
+                    kind: EntityKind::SData,
+                    text: "[mdash ]".into(),
+                },
+                EntityDeclaration {
+                    name: "raw",
+                    is_parameter: false,
+                    kind: EntityKind::CData,
+                    text: "<raw>".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_external_entity_declarations() {
+        let subset = r##"
+            <!ENTITY literal "x">
+            <!ENTITY % ISOnum PUBLIC "ISO 8879:1986//ENTITIES Numeric and Special Graphic//EN">
+            <!entity chapter1 system 'ch1.sgm'>
+            <!ENTITY local SYSTEM>
+            <!ENTITY both PUBLIC "-//Example//TEXT Both//EN" "both.sgm" -- comment -->
+            <!ENTITY logo SYSTEM "logo.gif" NDATA gif>
+            <!ENTITY data PUBLIC "-//Example//NONSGML Data//EN" CDATA text>
+            <!ENTITY systemic "x">
+            <![ IGNORE [ <!ENTITY ignored SYSTEM "ignored.sgm"> ]]>
+        "##;
+        assert_eq!(
+            external_entity_declarations(subset),
+            vec![
+                ExternalEntityDeclaration {
+                    name: "ISOnum",
+                    is_parameter: true,
+                    public_id: Some("ISO 8879:1986//ENTITIES Numeric and Special Graphic//EN"),
+                    system_id: None,
+                },
+                ExternalEntityDeclaration {
+                    name: "chapter1",
+                    is_parameter: false,
+                    public_id: None,
+                    system_id: Some("ch1.sgm"),
+                },
+                ExternalEntityDeclaration {
+                    name: "local",
+                    is_parameter: false,
+                    public_id: None,
+                    system_id: None,
+                },
+                ExternalEntityDeclaration {
+                    name: "both",
+                    is_parameter: false,
+                    public_id: Some("-//Example//TEXT Both//EN"),
+                    system_id: Some("both.sgm"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_element_declarations() {
+        use ContentToken::*;
+
+        fn element(name: &str, occurrence: Occurrence) -> ContentToken<'_> {
+            Element { name, occurrence }
+        }
+
+        let subset = r#"
+            <!-- <!ELEMENT commented - - ANY> -->
+            <![ IGNORE [ <!ELEMENT ignored - - ANY> ]]>
+            <!ELEMENT doc - - (front?, (sec | app)+, back*) -(doc) +(note|fn)>
+            <!element (b|i) o o RCDATA>
+            <!ELEMENT meta - O EMPTY>
+            <!ELEMENT pre - - CDATA -- raw text -->
+            <!ELEMENT any ANY>
+            <!ELEMENT mixed - - (#PCDATA | em)*>
+            <!ELEMENT list - - (item & label)>
+            <!ELEMENT bad - - (a, b | c)>
+            <!ELEMENT entity - - (%inline;)*>
+        "#;
+        assert_eq!(
+            element_declarations(subset),
+            vec![
+                ElementDeclaration {
+                    names: vec!["doc"],
+                    omit_start: false,
+                    omit_end: false,
+                    content: ContentModel::Group(ModelGroup {
+                        connector: Connector::Seq,
+                        tokens: vec![
+                            element("front", Occurrence::Optional),
+                            Group(ModelGroup {
+                                connector: Connector::Or,
+                                tokens: vec![
+                                    element("sec", Occurrence::Once),
+                                    element("app", Occurrence::Once),
+                                ],
+                                occurrence: Occurrence::OneOrMore,
+                            }),
+                            element("back", Occurrence::ZeroOrMore),
+                        ],
+                        occurrence: Occurrence::Once,
+                    }),
+                    exclusions: vec!["doc"],
+                    inclusions: vec!["note", "fn"],
+                },
+                ElementDeclaration {
+                    names: vec!["b", "i"],
+                    omit_start: true,
+                    omit_end: true,
+                    content: ContentModel::RcData,
+                    exclusions: vec![],
+                    inclusions: vec![],
+                },
+                ElementDeclaration {
+                    names: vec!["meta"],
+                    omit_start: false,
+                    omit_end: true,
+                    content: ContentModel::Empty,
+                    exclusions: vec![],
+                    inclusions: vec![],
+                },
+                ElementDeclaration {
+                    names: vec!["pre"],
+                    omit_start: false,
+                    omit_end: false,
+                    content: ContentModel::CData,
+                    exclusions: vec![],
+                    inclusions: vec![],
+                },
+                ElementDeclaration {
+                    names: vec!["any"],
+                    omit_start: false,
+                    omit_end: false,
+                    content: ContentModel::Any,
+                    exclusions: vec![],
+                    inclusions: vec![],
+                },
+                ElementDeclaration {
+                    names: vec!["mixed"],
+                    omit_start: false,
+                    omit_end: false,
+                    content: ContentModel::Group(ModelGroup {
+                        connector: Connector::Or,
+                        tokens: vec![PcData, element("em", Occurrence::Once)],
+                        occurrence: Occurrence::ZeroOrMore,
+                    }),
+                    exclusions: vec![],
+                    inclusions: vec![],
+                },
+                ElementDeclaration {
+                    names: vec!["list"],
+                    omit_start: false,
+                    omit_end: false,
+                    content: ContentModel::Group(ModelGroup {
+                        connector: Connector::And,
+                        tokens: vec![
+                            element("item", Occurrence::Once),
+                            element("label", Occurrence::Once),
+                        ],
+                        occurrence: Occurrence::Once,
+                    }),
+                    exclusions: vec![],
+                    inclusions: vec![],
+                },
+            ]
+        );
+    }
+}
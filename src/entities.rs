@@ -1,8 +1,9 @@
 //! Utilities for expanding entity and character references.
 
-use std::borrow::Cow;
-use std::char;
-use std::ops::Range;
+use alloc::borrow::Cow;
+use core::char;
+use core::fmt;
+use core::ops::Range;
 
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_while1};
@@ -12,16 +13,16 @@ use nom::sequence::{preceded, terminated};
 use nom::IResult;
 
 use crate::parser::raw::{is_name_char, name};
+use crate::prelude::*;
 
 /// The type returned by expansion operations.
-pub type Result<T = ()> = std::result::Result<T, EntityError>;
+pub type Result<T = ()> = core::result::Result<T, EntityError>;
 
 /// The error type in the event an invalid entity or character reference is found.
 ///
 /// That means the entity expansion closure was called, and it returned `None`.
 /// When invoking [`expand_characters`], any entity reference is considered undefined.
-#[derive(Clone, Debug, PartialEq, thiserror::Error)]
-#[error("entity '{entity}' is not defined")]
+#[derive(Clone, Debug, PartialEq)]
 pub struct EntityError {
     /// The name of the entity that was not found.
     pub entity: String,
@@ -29,6 +30,59 @@ pub struct EntityError {
     pub position: Range<usize>,
 }
 
+impl EntityError {
+    /// Returns a short excerpt of `source` around the offending reference,
+    /// for use in diagnostics.
+    ///
+    /// `source` must be the text that was being expanded when the error occurred.
+    /// The excerpt includes up to 20 characters on each side of the reference,
+    /// without crossing line breaks.
+    /// If [`position`](Self::position) does not fit `source`, an empty string is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::entities::expand_entities;
+    /// let source = "first line\nwith an &undefined; entity\nlast line";
+    /// let err = expand_entities(source, |_| None::<&str>).unwrap_err();
+    /// assert_eq!(err.context(source), "with an &undefined; entity");
+    /// ```
+    pub fn context<'s>(&self, source: &'s str) -> &'s str {
+        const WINDOW: usize = 20;
+
+        let Range { start, end } = self.position;
+        if start > end || source.get(start..end).is_none() {
+            return "";
+        }
+
+        let before = &source[..start];
+        let line_start = before.rfind(['\n', '\r']).map_or(0, |pos| pos + 1);
+        let context_start = before[line_start..]
+            .char_indices()
+            .rev()
+            .nth(WINDOW - 1)
+            .map_or(line_start, |(pos, _)| line_start + pos);
+
+        let after = &source[end..];
+        let line_end = after.find(['\n', '\r']).unwrap_or(after.len());
+        let context_end = after[..line_end]
+            .char_indices()
+            .nth(WINDOW)
+            .map_or(line_end, |(pos, _)| pos);
+
+        &source[context_start..end + context_end]
+    }
+}
+
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entity '{}' is not defined", self.entity)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EntityError {}
+
 /// Expands character references (`&#123;`) in the given text.
 /// Any entity references are treated as errors.
 ///
@@ -71,6 +125,30 @@ where
     expand_entities_with(text, "&", entity_or_char_ref, f)
 }
 
+/// Expands entity references (`&foo;`) in the text using the given closure as lookup,
+/// like [`expand_entities`], but without accepting hexadecimal character references.
+///
+/// Hexadecimal character references (`&#x6f;`) are only valid in XML; here they are
+/// passed to the closure as entity names (`#x6f`), like other invalid character references.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::entities::expand_entities_without_hex;
+/// let expanded = expand_entities_without_hex("&#111; &#x6f;", |entity| match entity {
+///     "#x6f" => Some("hex"),
+///     _ => None,
+/// });
+/// assert_eq!(expanded, Ok("o hex".into()));
+/// ```
+pub fn expand_entities_without_hex<F, T>(text: &str, f: F) -> Result<Cow<'_, str>>
+where
+    F: FnMut(&str) -> Option<T>,
+    T: AsRef<str>,
+{
+    expand_entities_with(text, "&", entity_or_decimal_char_ref, f)
+}
+
 /// Expands parameter entities (`%foo;`) in the text using the given closure as lookup.
 /// Parameter referencies are only used in specific parts of DTDs;
 /// for SGML document content, use [`expand_entities`] instead.
@@ -97,6 +175,92 @@ where
     expand_entities_with(text, "%", entity_ref, f)
 }
 
+/// Returns the replacement text of a standard function character reference
+/// (`&#RE;`, `&#RS;`, `&#SPACE;`, or `&#TAB;`), given its name without the leading `#`.
+///
+/// Record ends (`RE`) become line feeds, and record starts (`RS`) are dropped.
+/// Names are matched case-insensitively.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::entities::{expand_entities, function_char};
+/// assert_eq!(function_char("SPACE"), Some(" "));
+/// assert_eq!(function_char("EXAMPLE"), None);
+///
+/// let expanded = expand_entities("a&#TAB;b&#RS;&#RE;", |entity| {
+///     entity.strip_prefix('#').and_then(function_char)
+/// });
+/// assert_eq!(expanded, Ok("a\tb\n".into()));
+/// ```
+pub fn function_char(name: &str) -> Option<&'static str> {
+    const FUNCTION_CHARS: [(&str, &str); 4] =
+        [("RE", "\n"), ("RS", ""), ("SPACE", " "), ("TAB", "\t")];
+    FUNCTION_CHARS
+        .iter()
+        .find(|(function, _)| function.eq_ignore_ascii_case(name))
+        .map(|(_, replacement)| *replacement)
+}
+
+/// Returns the replacement text of one of the five entities predefined by XML
+/// (`&amp;`, `&lt;`, `&gt;`, `&quot;` and `&apos;`).
+///
+/// As in XML, names are case-sensitive.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::entities::{expand_entities, xml_entity};
+/// assert_eq!(xml_entity("amp"), Some("&"));
+/// assert_eq!(xml_entity("AMP"), None);
+///
+/// let expanded = expand_entities("&lt;a href=&quot;#&quot;&gt;", xml_entity);
+/// assert_eq!(expanded, Ok(r##"<a href="#">"##.into()));
+/// ```
+pub fn xml_entity(name: &str) -> Option<&'static str> {
+    match name {
+        "amp" => Some("&"),
+        "lt" => Some("<"),
+        "gt" => Some(">"),
+        "quot" => Some("\""),
+        "apos" => Some("'"),
+        _ => None,
+    }
+}
+
+/// Combines several entity lookups into one, which tries each of them in order
+/// and returns the first replacement found.
+///
+/// A source is only consulted when all sources before it returned `None`.
+/// The result can be used with [`expand_entities`], as well as with
+/// [`ParserBuilder::expand_entities`](crate::parser::ParserBuilder::expand_entities).
+/// To mix closures of different types, box them first.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use sgmlish::entities::{chain_entities, expand_entities, xml_entity};
+/// let mut custom = HashMap::new();
+/// custom.insert("amp", "and");
+/// custom.insert("eacute", "é");
+///
+/// let entities = chain_entities(vec![
+///     Box::new(move |name: &str| custom.get(name).copied()) as Box<dyn Fn(&str) -> _>,
+///     Box::new(xml_entity),
+/// ]);
+/// let expanded = expand_entities("caf&eacute; &amp; &lt;3", &entities);
+/// assert_eq!(expanded, Ok("café and <3".into()));
+/// ```
+pub fn chain_entities<I, F, T>(sources: I) -> impl Fn(&str) -> Option<T>
+where
+    I: IntoIterator<Item = F>,
+    F: Fn(&str) -> Option<T>,
+{
+    let sources = sources.into_iter().collect::<Vec<_>>();
+    move |name| sources.iter().find_map(|source| source(name))
+}
+
 fn expand_entities_with<'a, M, F, T>(
     text: &'a str,
     prefix: &str,
@@ -113,6 +277,7 @@ where
 
     let mut remainder = text;
     let mut out = String::new();
+    let mut replaced = false;
 
     while let Some(position) = remainder.find(prefix) {
         let (mid, candidate) = remainder.split_at(position);
@@ -128,10 +293,12 @@ where
                         .as_ref(),
                 );
                 remainder = after;
+                replaced = true;
             }
             Ok((after, EntityRef::Char(c))) => {
                 out.push(c);
                 remainder = after;
+                replaced = true;
             }
             Err(_) => {
                 out.push_str(prefix);
@@ -140,7 +307,8 @@ where
         }
     }
 
-    if remainder.len() == text.len() {
+    // A bare prefix with no reference after it is kept as is
+    if !replaced {
         return Ok(text.into());
     }
 
@@ -152,27 +320,43 @@ fn entity_or_char_ref(input: &str) -> IResult<&str, EntityRef> {
     alt((char_ref, entity_ref))(input)
 }
 
+fn entity_or_decimal_char_ref(input: &str) -> IResult<&str, EntityRef<'_>> {
+    alt((decimal_char_ref, entity_ref))(input)
+}
+
 fn char_ref(input: &str) -> IResult<&str, EntityRef> {
-    map(
-        consumed(preceded(
-            tag("#"),
-            alt((
-                map(digit1, |code: &str| code.parse().ok()),
-                // Hex escape codes are actually only valid in XML, but welp
-                preceded(
-                    tag("x"),
-                    map(take_while1(is_name_char), |code| {
-                        u32::from_str_radix(code, 16).ok()
-                    }),
-                ),
-            )),
+    char_ref_with(
+        input,
+        alt((
+            decimal_char_code,
+            // Hex escape codes are actually only valid in XML, but welp
+            preceded(
+                tag("x"),
+                map(take_while1(is_name_char), |code| {
+                    u32::from_str_radix(code, 16).ok()
+                }),
+            ),
         )),
-        |(raw, code)| {
-            code.and_then(char::from_u32)
-                .map(EntityRef::Char)
-                .unwrap_or_else(|| EntityRef::Entity(raw))
-        },
-    )(input)
+    )
+}
+
+fn decimal_char_ref(input: &str) -> IResult<&str, EntityRef<'_>> {
+    char_ref_with(input, decimal_char_code)
+}
+
+fn decimal_char_code(input: &str) -> IResult<&str, Option<u32>> {
+    map(digit1, |code: &str| code.parse().ok())(input)
+}
+
+fn char_ref_with<'a, P>(input: &'a str, code: P) -> IResult<&'a str, EntityRef<'a>>
+where
+    P: FnMut(&'a str) -> IResult<&'a str, Option<u32>>,
+{
+    map(consumed(preceded(tag("#"), code)), |(raw, code)| {
+        code.and_then(char::from_u32)
+            .map(EntityRef::Char)
+            .unwrap_or_else(|| EntityRef::Entity(raw))
+    })(input)
 }
 
 fn entity_ref(input: &str) -> IResult<&str, EntityRef> {
@@ -188,11 +372,67 @@ enum EntityRef<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_function_char() {
+        assert_eq!(function_char("RE"), Some("\n"));
+        assert_eq!(function_char("RS"), Some(""));
+        assert_eq!(function_char("SPACE"), Some(" "));
+        assert_eq!(function_char("TAB"), Some("\t"));
+        assert_eq!(function_char("space"), Some(" "));
+        assert_eq!(function_char("#SPACE"), None);
+        assert_eq!(function_char("SPACES"), None);
+    }
+
+    #[test]
+    fn test_xml_entity() {
+        assert_eq!(xml_entity("amp"), Some("&"));
+        assert_eq!(xml_entity("lt"), Some("<"));
+        assert_eq!(xml_entity("gt"), Some(">"));
+        assert_eq!(xml_entity("quot"), Some("\""));
+        assert_eq!(xml_entity("apos"), Some("'"));
+        assert_eq!(xml_entity("Amp"), None);
+        assert_eq!(xml_entity("nbsp"), None);
+    }
+
+    #[test]
+    fn test_chain_entities() {
+        use core::cell::RefCell;
+
+        let calls = RefCell::new(Vec::new());
+        let source = |id: &'static str, entities: &'static [(&'static str, &'static str)]| {
+            let calls = &calls;
+            move |name: &str| {
+                calls.borrow_mut().push(id);
+                entities
+                    .iter()
+                    .find(|(entity, _)| *entity == name)
+                    .map(|(_, text)| *text)
+            }
+        };
+        let entities = chain_entities(vec![
+            source("first", &[("a", "1")]),
+            source("second", &[("a", "2"), ("b", "2")]),
+            source("third", &[("b", "3"), ("c", "3")]),
+        ]);
+
+        assert_eq!(entities("a"), Some("1"));
+        assert_eq!(calls.take(), ["first"]);
+        assert_eq!(entities("b"), Some("2"));
+        assert_eq!(calls.take(), ["first", "second"]);
+        assert_eq!(entities("c"), Some("3"));
+        assert_eq!(calls.take(), ["first", "second", "third"]);
+        assert_eq!(entities("d"), None);
+        assert_eq!(calls.take(), ["first", "second", "third"]);
+
+        let none = chain_entities(Vec::<fn(&str) -> Option<&'static str>>::new());
+        assert_eq!(none("amp"), None);
+    }
+
     #[test]
     fn test_invalid_refs() {
         fn assert_noop(s: &str) {
             let result = expand_characters(s);
-            assert_eq!(result, Ok(s.into()));
+            assert!(matches!(result, Ok(Cow::Borrowed(text)) if text == s));
         }
 
         assert_noop("foo&");
@@ -229,6 +469,25 @@ mod tests {
         assert_eq!(result, Ok("foo bar \u{feff}".into()));
     }
 
+    #[test]
+    fn test_expand_entities_without_hex() {
+        let result = expand_entities_without_hex("fo&#111; &#x6f;&#X6F", |entity| match entity {
+            "#x6f" => Some("[x]"),
+            "#X6F" => Some("[X]"),
+            _ => None,
+        });
+        assert_eq!(result, Ok("foo [x][X]".into()));
+
+        let result = expand_entities_without_hex("&#x6f;", |_| None::<&str>);
+        assert_eq!(
+            result,
+            Err(EntityError {
+                entity: "#x6f".to_owned(),
+                position: 0..6,
+            })
+        );
+    }
+
     #[test]
     fn test_expand_characters_missing_semicolon() {
         let result = expand_characters("fo&#x6f bar &#xFeFf");
@@ -301,6 +560,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_entity_error_context() {
+        let source = "<p>\nSome text with an &undefined; reference, and then some\n</p>";
+        let err = expand_entities(source, |_| None::<&str>).unwrap_err();
+        assert_eq!(
+            err.context(source),
+            "Some text with an &undefined; reference, and then"
+        );
+
+        let source = "&x;";
+        let err = expand_entities(source, |_| None::<&str>).unwrap_err();
+        assert_eq!(err.context(source), "&x;");
+
+        let source = "ção &x; ção";
+        let err = expand_entities(source, |_| None::<&str>).unwrap_err();
+        assert_eq!(err.context(source), "ção &x; ção");
+
+        let err = EntityError {
+            entity: "x".into(),
+            position: 5..8,
+        };
+        assert_eq!(err.context("&x;"), "");
+    }
+
     #[test]
     fn test_expand_parameter_entities() {
         let result = expand_parameter_entities("CDATA %bar.baz ", |name| {
@@ -1,10 +1,22 @@
 //! When things don't go as planned.
 
 /// Alias for a `Result` with the error type [`sgmlish::Error`](Error)
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
-/// The error type for all parsing and deserialization errors.
-#[derive(Debug, thiserror::Error)]
+use core::fmt;
+
+use crate::prelude::*;
+
+/// The error type for all parsing, serialization and deserialization errors.
+///
+/// With the `miette` feature, this type implements `miette::Diagnostic`,
+/// but only to report its message: it does not keep the input nor where in it
+/// the error occurred, so no source code or labels are shown.
+/// To report a parse error with the offending input highlighted, parse with
+/// [`Parser::parse_with_detailed_errors`](crate::parser::Parser::parse_with_detailed_errors)
+/// into a [`ContextualizedError`](crate::parser::ContextualizedError)
+/// and call its `to_diagnostic` method.
+#[derive(Debug)]
 pub enum Error {
     /// An error occurred when parsing SGML data.
     ///
@@ -12,21 +24,170 @@ pub enum Error {
     /// has no dependencies on transient state.
     /// If you wish to capture more details from the parser, see
     /// [`Parser::parse_with_detailed_errors`](crate::parser::Parser::parse_with_detailed_errors).
-    #[error("{0}")]
     ParseError(String),
     /// An error occurred when deseralizing.
     #[cfg(feature = "serde")]
-    #[error(transparent)]
-    DeserializationError(#[from] crate::de::DeserializationError),
+    DeserializationError(crate::de::DeserializationError),
+    /// An error occurred when serializing.
+    #[cfg(feature = "serde")]
+    SerializationError(crate::ser::SerializationError),
     /// An error occurred when normalizing end tags.
-    #[error(transparent)]
-    NormalizationError(#[from] crate::transforms::NormalizationError),
+    NormalizationError(crate::transforms::NormalizationError),
+    /// An error occurred when applying attribute defaults.
+    #[cfg(feature = "std")]
+    AttributeDefaultError(crate::transforms::AttributeDefaultError),
     /// An error occurred when decoding an entity reference.
-    #[error(transparent)]
-    EntityError(#[from] crate::entities::EntityError),
+    EntityError(crate::entities::EntityError),
+    /// Start and end tags were not balanced.
+    UnbalancedError(crate::UnbalancedError),
     /// An error ocurred when processing a marked section.
-    #[error("invalid marked section keyword: {0}")]
     InvalidMarkedSectionKeyword(String),
+    /// An attribute was written without a value (e.g. `<input disabled>`)
+    /// while [`ParserConfig::allow_minimized_attributes`](crate::ParserConfig::allow_minimized_attributes)
+    /// was disabled.
+    MinimizedAttribute(String),
+    /// A start tag declared more attributes than allowed by
+    /// [`ParserConfig::max_attributes_per_element`](crate::ParserConfig::max_attributes_per_element).
+    TooManyAttributes { element: String, limit: usize },
+    /// The input was longer, in bytes, than allowed by
+    /// [`ParserConfig::max_input_len`](crate::ParserConfig::max_input_len).
+    InputTooLong { len: usize, limit: usize },
+    /// The input produced more events than allowed by
+    /// [`ParserConfig::max_events`](crate::ParserConfig::max_events).
+    TooManyEvents { limit: usize },
+    /// A start tag declared the same attribute more than once while
+    /// [`ParserConfig::reject_duplicate_attributes`](crate::ParserConfig::reject_duplicate_attributes)
+    /// was enabled.
+    DuplicateAttribute { element: String, attribute: String },
+    /// The input ended while these elements, outermost first, were still open,
+    /// while [`ParserConfig::require_balanced`](crate::ParserConfig::require_balanced)
+    /// was enabled.
+    UnclosedElements(Vec<String>),
+    /// A markup declaration (`<!EXAMPLE>`) had a keyword other than the standard ones
+    /// while [`ParserConfig::strict_markup_declarations`](crate::ParserConfig::strict_markup_declarations)
+    /// was enabled.
+    UnknownMarkupDeclaration(String),
+    /// A tag name was refused by the closure set with
+    /// [`ParserBuilder::validate_names`](crate::parser::ParserBuilder::validate_names).
+    InvalidTagName(String),
+    /// An error occurred when reading input.
+    #[cfg(feature = "std")]
+    IoError(std::io::Error),
+    /// The input declared a character encoding that is not supported by
+    /// [`Parser::parse_bytes`](crate::parser::Parser::parse_bytes).
+    #[cfg(feature = "encoding")]
+    UnknownEncoding(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ParseError(message) => f.write_str(message),
+            #[cfg(feature = "serde")]
+            Error::DeserializationError(err) => fmt::Display::fmt(err, f),
+            #[cfg(feature = "serde")]
+            Error::SerializationError(err) => fmt::Display::fmt(err, f),
+            Error::NormalizationError(err) => fmt::Display::fmt(err, f),
+            #[cfg(feature = "std")]
+            Error::AttributeDefaultError(err) => fmt::Display::fmt(err, f),
+            Error::EntityError(err) => fmt::Display::fmt(err, f),
+            Error::UnbalancedError(err) => fmt::Display::fmt(err, f),
+            Error::InvalidMarkedSectionKeyword(keyword) => {
+                write!(f, "invalid marked section keyword: {}", keyword)
+            }
+            Error::MinimizedAttribute(name) => {
+                write!(f, "attribute without value: {}", name)
+            }
+            Error::TooManyAttributes { element, limit } => write!(
+                f,
+                "too many attributes in start tag for {}: limit is {}",
+                element, limit
+            ),
+            Error::InputTooLong { len, limit } => {
+                write!(f, "input too long: {} bytes, limit is {}", len, limit)
+            }
+            Error::TooManyEvents { limit } => write!(f, "too many events: limit is {}", limit),
+            Error::DuplicateAttribute { element, attribute } => write!(
+                f,
+                "duplicate attribute in start tag for {}: {}",
+                element, attribute
+            ),
+            Error::UnknownMarkupDeclaration(keyword) => {
+                write!(f, "unrecognized markup declaration: {}", keyword)
+            }
+            Error::InvalidTagName(name) => write!(f, "invalid tag name: {}", name),
+            Error::UnclosedElements(names) => {
+                write!(f, "unclosed elements at end of input: {}", names.join(", "))
+            }
+            #[cfg(feature = "std")]
+            Error::IoError(err) => write!(f, "error reading input: {}", err),
+            #[cfg(feature = "encoding")]
+            Error::UnknownEncoding(label) => write!(f, "unknown character encoding: {}", label),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "serde")]
+            Error::DeserializationError(err) => std::error::Error::source(err),
+            Error::IoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Reports only the error message, without source code or labels;
+/// see the [`Error`] documentation.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {}
+
+#[cfg(feature = "serde")]
+impl From<crate::de::DeserializationError> for Error {
+    fn from(err: crate::de::DeserializationError) -> Self {
+        Error::DeserializationError(err)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<crate::ser::SerializationError> for Error {
+    fn from(err: crate::ser::SerializationError) -> Self {
+        Error::SerializationError(err)
+    }
+}
+
+impl From<crate::transforms::NormalizationError> for Error {
+    fn from(err: crate::transforms::NormalizationError) -> Self {
+        Error::NormalizationError(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<crate::transforms::AttributeDefaultError> for Error {
+    fn from(err: crate::transforms::AttributeDefaultError) -> Self {
+        Error::AttributeDefaultError(err)
+    }
+}
+
+impl From<crate::UnbalancedError> for Error {
+    fn from(err: crate::UnbalancedError) -> Self {
+        Error::UnbalancedError(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::IoError(err)
+    }
+}
+
+impl From<crate::entities::EntityError> for Error {
+    fn from(err: crate::entities::EntityError) -> Self {
+        Error::EntityError(err)
+    }
 }
 
 #[cfg(test)]
@@ -40,6 +201,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     /// Ensure all the necessary bounds are met for downcasting errors
     fn test_error_dyn_cast() {
         let err: Box<dyn std::error::Error> = Box::new(Error::ParseError("".to_owned()));
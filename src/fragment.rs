@@ -1,6 +1,10 @@
-use std::fmt;
+use alloc::borrow::Cow;
+use core::iter::FromIterator;
+use core::str::FromStr;
+use core::{fmt, mem};
 
-use crate::SgmlEvent;
+use crate::prelude::*;
+use crate::{text, SgmlEvent, Visitor};
 
 /// A list of events from a parsed SGML document.
 ///
@@ -9,12 +13,62 @@ use crate::SgmlEvent;
 ///
 /// Working directly with events is not very practical; they are mainly meant
 /// for applying transforms before being used for deserialization.
+///
+/// Fragments usually come from the [`Parser`](crate::Parser), but can also be
+/// built from events produced by other means, with [`from_events`](SgmlFragment::from_events).
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SgmlFragment<'a> {
     events: Vec<SgmlEvent<'a>>,
 }
 
 impl<'a> SgmlFragment<'a> {
+    /// Creates a fragment from events produced elsewhere, such as by a custom tokenizer,
+    /// so they can be transformed, serialized or deserialized like parsed ones.
+    ///
+    /// Events must be laid out as the parser would produce them:
+    ///
+    /// * Each [`OpenStartTag`](SgmlEvent::OpenStartTag) is followed by the attributes of
+    ///   the tag, if any, then by a [`CloseStartTag`](SgmlEvent::CloseStartTag) or an
+    ///   [`XmlCloseEmptyElement`](SgmlEvent::XmlCloseEmptyElement);
+    /// * Attribute events only appear within start tags, as above;
+    /// * `CloseStartTag` and `XmlCloseEmptyElement` only appear at the end of start tags.
+    ///
+    /// Elements do not need to be balanced, nor have end tags, as with parsed input;
+    /// see [`validate_balanced`](SgmlFragment::validate_balanced) and
+    /// [`normalize_end_tags`](crate::transforms::normalize_end_tags).
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the events break any of the rules above.
+    /// Release builds perform no checks, and the result of operating
+    /// on such a fragment is unspecified.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::{SgmlEvent, SgmlFragment};
+    /// let fragment = SgmlFragment::from_events(vec![
+    ///     SgmlEvent::OpenStartTag { name: "p".into() },
+    ///     SgmlEvent::Attribute { name: "class".into(), value: Some("note".into()) },
+    ///     SgmlEvent::CloseStartTag,
+    ///     SgmlEvent::Character("Hello".into()),
+    ///     SgmlEvent::EndTag { name: "p".into() },
+    /// ]);
+    /// assert_eq!(fragment.to_string(), r#"<p class="note">Hello</p>"#);
+    /// ```
+    pub fn from_events<I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = SgmlEvent<'a>>,
+    {
+        let events = events.into_iter().collect::<Vec<_>>();
+        if cfg!(debug_assertions) {
+            if let Err((index, message)) = check_layout(&events) {
+                panic!("invalid event at index {}: {}", index, message);
+            }
+        }
+        SgmlFragment { events }
+    }
+
     /// Returns the number of events in the list.
     // `is_empty()` makes no sense here, since we don't expect empty fragments
     #[allow(clippy::len_without_is_empty)]
@@ -33,16 +87,32 @@ impl<'a> SgmlFragment<'a> {
     }
 
     /// Returns an iterator over references to events.
-    pub fn iter(&self) -> std::slice::Iter<SgmlEvent<'a>> {
+    pub fn iter(&self) -> core::slice::Iter<SgmlEvent<'a>> {
         self.events.iter()
     }
 
     /// Returns an iterator over mutable references to events.
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<SgmlEvent<'a>> {
+    pub fn iter_mut(&mut self) -> core::slice::IterMut<SgmlEvent<'a>> {
         self.events.iter_mut()
     }
 
     /// Detaches the fragment from the source string, taking ownership of all substrings.
+    ///
+    /// Every string still borrowed from the source is copied into its own allocation,
+    /// so this costs roughly one allocation per tag name, attribute and text;
+    /// strings that were already owned (such as text with expanded entities) are moved as is.
+    /// This makes it possible to keep a fragment after the input is gone:
+    ///
+    /// ```rust
+    /// # use sgmlish::SgmlFragment;
+    /// fn load() -> sgmlish::Result<SgmlFragment<'static>> {
+    ///     let input = String::from("<greeting>hello</greeting>");
+    ///     Ok(sgmlish::parse(&input)?.into_owned())
+    /// }
+    ///
+    /// assert_eq!(load()?.to_string(), "<greeting>hello</greeting>");
+    /// # Ok::<(), sgmlish::Error>(())
+    /// ```
     pub fn into_owned(self) -> SgmlFragment<'static> {
         self.into_iter()
             .map(|event| event.into_owned())
@@ -50,6 +120,639 @@ impl<'a> SgmlFragment<'a> {
             .into()
     }
 
+    /// Applies the given function to the text of every [`Character`](SgmlEvent::Character) event.
+    ///
+    /// Events are updated in place, and text is handed over as is, so returning
+    /// the given `Cow` unchanged (or any other `Cow::Borrowed`) allocates nothing.
+    /// Whitespace-only text reported as [`Whitespace`](SgmlEvent::Whitespace) events
+    /// is left untouched.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::parse("<p>my password is hunter2</p>")?;
+    /// let sgml = sgml.map_text(|text| {
+    ///     if text.contains("hunter2") {
+    ///         text.replace("hunter2", "*******").into()
+    ///     } else {
+    ///         text
+    ///     }
+    /// });
+    /// assert_eq!(sgml.to_string(), "<p>my password is *******</p>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map_text<F>(mut self, mut f: F) -> SgmlFragment<'a>
+    where
+        F: FnMut(Cow<'a, str>) -> Cow<'a, str>,
+    {
+        for event in self.iter_mut() {
+            if let SgmlEvent::Character(text) = event {
+                *text = f(mem::take(text));
+            }
+        }
+        self
+    }
+
+    /// Concatenates all text in the fragment, in document order, discarding markup.
+    ///
+    /// Text from both [`Character`](SgmlEvent::Character) and
+    /// [`Whitespace`](SgmlEvent::Whitespace) events is included as is;
+    /// see [`text_content_with`](SgmlFragment::text_content_with) for other options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::parse("<p>caf&#233; <b>au</b> lait</p>")?;
+    /// assert_eq!(sgml.text_content(), "caféaulait");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn text_content(&self) -> String {
+        self.text_content_with(&TextContentOptions::default())
+    }
+
+    /// Concatenates all text in the fragment, as in [`text_content`](SgmlFragment::text_content),
+    /// with the given options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::TextContentOptions;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::parse(
+    ///     "<div><style>p {}</style><h1>Title</h1><p>First<br>line</p></div>",
+    /// )?;
+    /// let options = TextContentOptions {
+    ///     separate_elements: true,
+    ///     exclude: &["style", "script"],
+    /// };
+    /// assert_eq!(sgml.text_content_with(&options), "Title First line");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn text_content_with(&self, options: &TextContentOptions) -> String {
+        struct Collector<'o> {
+            options: &'o TextContentOptions<'o>,
+            out: String,
+            at_boundary: bool,
+            excluded_depth: Option<usize>,
+        }
+
+        impl Visitor for Collector<'_> {
+            fn enter_element(&mut self, name: &str, _: &[(&str, Option<&str>)], depth: usize) {
+                if self.excluded_depth.is_none() && self.options.exclude.contains(&name) {
+                    self.excluded_depth = Some(depth);
+                }
+                self.at_boundary = true;
+            }
+
+            fn leave_element(&mut self, _: &str, depth: usize) {
+                if self.excluded_depth == Some(depth) {
+                    self.excluded_depth = None;
+                }
+                self.at_boundary = true;
+            }
+
+            fn text(&mut self, text: &str, _: usize) {
+                if self.excluded_depth.is_some() || text.is_empty() {
+                    return;
+                }
+                if self.options.separate_elements
+                    && self.at_boundary
+                    && !self.out.ends_with(text::is_sgml_whitespace)
+                    && !text.starts_with(text::is_sgml_whitespace)
+                    && !self.out.is_empty()
+                {
+                    self.out.push(' ');
+                }
+                self.at_boundary = false;
+                self.out.push_str(text);
+            }
+        }
+
+        let mut collector = Collector {
+            options,
+            out: String::new(),
+            at_boundary: false,
+            excluded_depth: None,
+        };
+        self.walk(&mut collector);
+        collector.out
+    }
+
+    /// Counts the elements with the given name, including those nested within one another.
+    ///
+    /// Every [`OpenStartTag`](SgmlEvent::OpenStartTag) event is counted, whether
+    /// its element is closed or not. Names are compared exactly as they appear in
+    /// the events, that is, after any normalization performed by the parser (see
+    /// [`ParserBuilder::lowercase_names`](crate::parser::ParserBuilder::lowercase_names)).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse("<table><tr><td>1<tr><td>2<td>3</table>")?;
+    /// assert_eq!(fragment.count_elements("tr"), 2);
+    /// assert_eq!(fragment.count_elements("td"), 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn count_elements(&self, name: &str) -> usize {
+        self.iter()
+            .filter(|event| event.is_start_tag() && event.tag_name() == Some(name))
+            .count()
+    }
+
+    /// Extracts the first element with the given name, along with all its descendants,
+    /// as a new fragment.
+    ///
+    /// The result spans from the element's [`OpenStartTag`](SgmlEvent::OpenStartTag)
+    /// through its matching [`EndTag`](SgmlEvent::EndTag), inclusive, or through
+    /// [`XmlCloseEmptyElement`](SgmlEvent::XmlCloseEmptyElement) for XML-style empty elements.
+    /// End tags are matched by nesting depth, so elements nested within another
+    /// with the same name are handled correctly. Tag names are compared in a
+    /// case-sensitive manner.
+    ///
+    /// Returns `None` if no element has the given name, or if the first one is never closed;
+    /// if end tags may have been omitted, apply
+    /// [`normalize_end_tags`](crate::transforms::normalize_end_tags) first.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse("<doc><head><title>Hi</title></head><body>...</body></doc>")?;
+    /// let head = fragment.subtree("head").unwrap();
+    /// assert_eq!(head.to_string(), "<head><title>Hi</title></head>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn subtree(&self, name: &str) -> Option<SgmlFragment<'a>> {
+        let events = self.as_slice();
+        let start = events
+            .iter()
+            .position(|event| event.is_start_tag() && event.tag_name() == Some(name))?;
+        let end = self.matching_end(start)?;
+        Some(events[start..=end].to_vec().into())
+    }
+
+    /// Finds the end of the element whose start tag is at the given index.
+    ///
+    /// Returns the index of the element's matching [`EndTag`](SgmlEvent::EndTag),
+    /// or of its [`XmlCloseEmptyElement`](SgmlEvent::XmlCloseEmptyElement) for
+    /// XML-style empty elements. End tags are matched by nesting depth, as in
+    /// [`subtree`](SgmlFragment::subtree), and empty end tags (`</>`) close the element
+    /// at their depth.
+    ///
+    /// Returns `None` if the event at the given index is not an
+    /// [`OpenStartTag`](SgmlEvent::OpenStartTag), or if the element is never closed,
+    /// including when an end tag for another element is found at its depth,
+    /// as happens with omitted end tags.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse("<ul><li>One</li><li>Two<br/></li></ul>")?;
+    /// assert_eq!(fragment.matching_end(0), Some(12));
+    /// assert_eq!(fragment.matching_end(2), Some(5));
+    /// assert_eq!(fragment.matching_end(9), Some(10));
+    /// assert_eq!(fragment.matching_end(1), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn matching_end(&self, start: usize) -> Option<usize> {
+        if !self.events.get(start)?.is_start_tag() {
+            return None;
+        }
+        match element_end(&self.events, start) {
+            ElementEnd::Empty(end) | ElementEnd::EndTag(end) => Some(end),
+            ElementEnd::Unclosed => None,
+        }
+    }
+
+    /// Returns a view of the attributes of the start tag at the given index,
+    /// or `None` if the event at that index is not an [`OpenStartTag`](SgmlEvent::OpenStartTag).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse(r#"<a href="/" hidden>Home</a>"#)?;
+    /// let attributes = fragment.attributes(0).unwrap();
+    /// assert_eq!(attributes.get("href"), Some(Some("/")));
+    /// assert_eq!(attributes.get("hidden"), Some(None));
+    /// assert_eq!(attributes.get("title"), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attributes(&self, start: usize) -> Option<Attributes<'_, 'a>> {
+        if !self.events.get(start)?.is_start_tag() {
+            return None;
+        }
+        let end = start_tag_end(&self.events, start);
+        Some(Attributes {
+            events: &self.events[start + 1..end],
+        })
+    }
+
+    /// Checks that every end tag closes the innermost open element,
+    /// and that all elements are closed by the end of the fragment.
+    ///
+    /// Empty end tags (`</>`) close whichever element is innermost,
+    /// and XML-style empty elements (`<br/>`) need no end tag.
+    /// Tag names are compared in a case-sensitive manner.
+    ///
+    /// This does not attempt to infer omitted end tags; see
+    /// [`normalize_end_tags`](crate::transforms::normalize_end_tags) for that.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::UnbalancedError;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse("<a><b>text</a>")?;
+    /// assert_eq!(
+    ///     fragment.validate_balanced(),
+    ///     Err(UnbalancedError::MismatchedEndTag {
+    ///         index: 5,
+    ///         expected: "b".to_owned(),
+    ///         found: "a".to_owned(),
+    ///     })
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn validate_balanced(&self) -> Result<(), UnbalancedError> {
+        let mut stack = vec![];
+        for (index, event) in self.iter().enumerate() {
+            match event {
+                SgmlEvent::OpenStartTag { name } => stack.push((index, name)),
+                SgmlEvent::XmlCloseEmptyElement => {
+                    stack.pop();
+                }
+                SgmlEvent::EndTag { name } => match stack.pop() {
+                    Some((_, open_name)) if name.is_empty() || open_name == name => {}
+                    Some((_, open_name)) => {
+                        return Err(UnbalancedError::MismatchedEndTag {
+                            index,
+                            expected: open_name.to_string(),
+                            found: name.to_string(),
+                        })
+                    }
+                    None => {
+                        return Err(UnbalancedError::UnexpectedEndTag {
+                            index,
+                            name: name.to_string(),
+                        })
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        match stack.pop() {
+            Some((index, name)) => Err(UnbalancedError::UnclosedElement {
+                index,
+                name: name.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Converts the fragment into a generic JSON tree. This method requires the `json` feature.
+    ///
+    /// The result is an array of the top-level nodes. Elements are represented as objects
+    /// with `name`, `attributes` and `children` keys; attributes without a value
+    /// map to `null`. Text is represented as plain strings.
+    ///
+    /// Markup declarations, processing instructions and marked sections are omitted.
+    /// Tags must be properly balanced, as in [`validate_balanced`](SgmlFragment::validate_balanced).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use serde_json::json;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse(r#"<a href="/">Home <b>page</b></a>"#)?;
+    /// assert_eq!(
+    ///     fragment.to_json()?,
+    ///     json!([{
+    ///         "name": "a",
+    ///         "attributes": { "href": "/" },
+    ///         "children": ["Home", { "name": "b", "attributes": {}, "children": ["page"] }],
+    ///     }])
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<serde_json::Value, UnbalancedError> {
+        use serde_json::{Map, Value};
+
+        struct OpenElement<'e> {
+            index: usize,
+            name: &'e str,
+            attributes: Map<String, Value>,
+            children: Vec<Value>,
+        }
+
+        impl OpenElement<'_> {
+            fn into_json(self) -> Value {
+                let mut object = Map::new();
+                object.insert("name".to_owned(), self.name.into());
+                object.insert("attributes".to_owned(), self.attributes.into());
+                object.insert("children".to_owned(), self.children.into());
+                object.into()
+            }
+        }
+
+        let mut roots = vec![];
+        let mut stack: Vec<OpenElement> = vec![];
+        for (index, event) in self.iter().enumerate() {
+            let node = match event {
+                SgmlEvent::OpenStartTag { name } => {
+                    stack.push(OpenElement {
+                        index,
+                        name,
+                        attributes: Map::new(),
+                        children: vec![],
+                    });
+                    continue;
+                }
+                SgmlEvent::Attribute { .. }
+                | SgmlEvent::UnquotedAttribute { .. }
+                | SgmlEvent::QuotedAttribute { .. } => {
+                    if let (Some(element), Some((name, value))) =
+                        (stack.last_mut(), event.as_attribute())
+                    {
+                        let value = value.map_or(Value::Null, Value::from);
+                        element.attributes.insert(name.to_owned(), value);
+                    }
+                    continue;
+                }
+                SgmlEvent::XmlCloseEmptyElement => match stack.pop() {
+                    Some(element) => element.into_json(),
+                    None => continue,
+                },
+                SgmlEvent::EndTag { name } => match stack.pop() {
+                    Some(element) if name.is_empty() || element.name == name => element.into_json(),
+                    Some(element) => {
+                        return Err(UnbalancedError::MismatchedEndTag {
+                            index,
+                            expected: element.name.to_string(),
+                            found: name.to_string(),
+                        })
+                    }
+                    None => {
+                        return Err(UnbalancedError::UnexpectedEndTag {
+                            index,
+                            name: name.to_string(),
+                        })
+                    }
+                },
+                SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) => text.as_ref().into(),
+                _ => continue,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => roots.push(node),
+            }
+        }
+
+        match stack.pop() {
+            Some(element) => Err(UnbalancedError::UnclosedElement {
+                index: element.index,
+                name: element.name.to_string(),
+            }),
+            None => Ok(roots.into()),
+        }
+    }
+
+    /// Converts the fragment into [`quick_xml`] events, so it can be handed to
+    /// existing XML tooling. This method requires the `quick-xml` feature.
+    ///
+    /// Each start tag, along with its attributes, becomes a single [`Event::Start`],
+    /// or an [`Event::Empty`] for XML-style empty elements (`<br/>`); end tags become
+    /// [`Event::End`], with empty end tags (`</>`) given the name of the element they close;
+    /// text becomes [`Event::Text`], escaped as needed when written.
+    ///
+    /// The conversion is lossy where SGML has no XML equivalent:
+    ///
+    /// * attributes without a value (`<input disabled>`) are given their own name
+    ///   as value (`disabled="disabled"`);
+    /// * `DOCTYPE` declarations become [`Event::DocType`], and all other markup
+    ///   declarations are dropped;
+    /// * `CDATA` marked sections become [`Event::CData`], and all others are dropped,
+    ///   so other marked sections should be expanded beforehand
+    ///   (see [`expand_marked_sections`](crate::transforms::expand_marked_sections));
+    /// * processing instructions become [`Event::PI`], with their content kept as is.
+    ///
+    /// Fails if an empty end tag is found while no element is open.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use quick_xml::events::Event;
+    /// use quick_xml::Writer;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let fragment = sgmlish::parse("<p class=intro>Fish & chips<br/></>")?;
+    /// let mut writer = Writer::new(Vec::new());
+    /// for event in fragment.to_quick_xml_events()? {
+    ///     writer.write_event(event)?;
+    /// }
+    /// assert_eq!(
+    ///     String::from_utf8(writer.into_inner())?,
+    ///     r#"<p class="intro">Fish &amp; chips<br/></p>"#
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Event::Start`]: quick_xml::events::Event::Start
+    /// [`Event::Empty`]: quick_xml::events::Event::Empty
+    /// [`Event::End`]: quick_xml::events::Event::End
+    /// [`Event::Text`]: quick_xml::events::Event::Text
+    /// [`Event::DocType`]: quick_xml::events::Event::DocType
+    /// [`Event::CData`]: quick_xml::events::Event::CData
+    /// [`Event::PI`]: quick_xml::events::Event::PI
+    #[cfg(feature = "quick-xml")]
+    pub fn to_quick_xml_events(
+        &self,
+    ) -> Result<Vec<quick_xml::events::Event<'_>>, UnbalancedError> {
+        use quick_xml::events::{BytesCData, BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+
+        let mut events = Vec::with_capacity(self.len());
+        let mut open_elements: Vec<&str> = vec![];
+        let mut start: Option<(&str, BytesStart)> = None;
+        for (index, event) in self.iter().enumerate() {
+            let converted = match event {
+                SgmlEvent::OpenStartTag { name } => {
+                    start = Some((name, BytesStart::new(name.as_ref())));
+                    continue;
+                }
+                SgmlEvent::Attribute { .. }
+                | SgmlEvent::UnquotedAttribute { .. }
+                | SgmlEvent::QuotedAttribute { .. } => {
+                    if let (Some((_, start)), Some((name, value))) =
+                        (&mut start, event.as_attribute())
+                    {
+                        start.push_attribute((name, value.unwrap_or(name)));
+                    }
+                    continue;
+                }
+                SgmlEvent::CloseStartTag => match start.take() {
+                    Some((name, start)) => {
+                        open_elements.push(name);
+                        Event::Start(start)
+                    }
+                    None => continue,
+                },
+                SgmlEvent::XmlCloseEmptyElement => match start.take() {
+                    Some((_, start)) => Event::Empty(start),
+                    None => continue,
+                },
+                SgmlEvent::EndTag { name } => {
+                    let open = open_elements.pop();
+                    match (name.as_ref(), open) {
+                        ("", None) => {
+                            return Err(UnbalancedError::UnexpectedEndTag {
+                                index,
+                                name: String::new(),
+                            })
+                        }
+                        ("", Some(open)) => Event::End(BytesEnd::new(open)),
+                        (name, _) => Event::End(BytesEnd::new(name)),
+                    }
+                }
+                SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) => {
+                    Event::Text(BytesText::new(text))
+                }
+                SgmlEvent::XmlDeclaration {
+                    version,
+                    encoding,
+                    standalone,
+                } => Event::Decl(BytesDecl::new(
+                    version,
+                    encoding.as_deref(),
+                    standalone.map(|standalone| if standalone { "yes" } else { "no" }),
+                )),
+                SgmlEvent::ProcessingInstruction(pi) => {
+                    let content = pi.strip_prefix("<?").unwrap_or(pi);
+                    let content = content.strip_suffix('>').unwrap_or(content);
+                    let content = content.strip_suffix('?').unwrap_or(content);
+                    Event::PI(BytesText::from_escaped(content))
+                }
+                SgmlEvent::StructuredProcessingInstruction { target, data } => {
+                    let content = if data.is_empty() {
+                        Cow::Borrowed(target.as_ref())
+                    } else {
+                        Cow::Owned([target.as_ref(), data.as_ref()].join(" "))
+                    };
+                    Event::PI(BytesText::from_escaped(content))
+                }
+                SgmlEvent::MarkupDeclaration { keyword, body }
+                    if keyword.eq_ignore_ascii_case("DOCTYPE") =>
+                {
+                    Event::DocType(BytesText::from_escaped(body.as_ref()))
+                }
+                SgmlEvent::MarkedSection {
+                    status_keywords,
+                    section,
+                } if status_keywords.trim().eq_ignore_ascii_case("CDATA") => {
+                    Event::CData(BytesCData::new(section.as_ref()))
+                }
+                SgmlEvent::MarkupDeclaration { .. } | SgmlEvent::MarkedSection { .. } => continue,
+            };
+            events.push(converted);
+        }
+        Ok(events)
+    }
+
+    /// Formats the fragment with one element per line, indented by nesting depth.
+    ///
+    /// Elements whose direct content includes any non-whitespace text are kept on a single
+    /// line, along with everything inside them, so that mixed content is never reflowed;
+    /// the same goes for elements without child elements. Other elements have each child
+    /// placed on its own line, dropping whitespace-only text between them.
+    /// XML-style empty elements (`<br/>`), markup declarations, processing instructions
+    /// and marked sections are written verbatim on a line of their own.
+    ///
+    /// End tags are matched by nesting depth, so this works best on fragments
+    /// where omitted end tags have been inserted (see
+    /// [`normalize_end_tags`](crate::transforms::normalize_end_tags)).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let fragment = sgmlish::parse("<ul><li>One</li><li>Two <b>2</b></li><br/></ul>")?;
+    /// assert_eq!(
+    ///     fragment.to_pretty_string("  "),
+    ///     "<ul>\n  <li>One</li>\n  <li>Two<b>2</b></li>\n  <br/>\n</ul>"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_pretty_string(&self, indent: &str) -> String {
+        fn new_line(out: &mut String, indent: &str, depth: usize) {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            for _ in 0..depth {
+                out.push_str(indent);
+            }
+        }
+
+        let events = self.as_slice();
+        let mut out = String::new();
+        let mut depth = 0;
+        let mut i = 0;
+        while i < events.len() {
+            match &events[i] {
+                SgmlEvent::OpenStartTag { .. } => {
+                    new_line(&mut out, indent, depth);
+                    let end = element_end(events, i);
+                    let inline = match end {
+                        ElementEnd::Empty(_) => true,
+                        ElementEnd::EndTag(end) => keep_inline(&events[i..end]),
+                        ElementEnd::Unclosed => false,
+                    };
+                    let (until, is_open) = match end {
+                        ElementEnd::Empty(end) | ElementEnd::EndTag(end) if inline => (end, false),
+                        _ => (start_tag_end(events, i), true),
+                    };
+                    write_events(&mut out, &events[i..=until]).unwrap();
+                    if is_open {
+                        depth += 1;
+                    }
+                    i = until + 1;
+                    continue;
+                }
+                SgmlEvent::EndTag { .. } => {
+                    depth = depth.saturating_sub(1);
+                    new_line(&mut out, indent, depth);
+                }
+                SgmlEvent::Whitespace(_) => {
+                    i += 1;
+                    continue;
+                }
+                SgmlEvent::Character(text) if crate::text::is_blank(text) => {
+                    i += 1;
+                    continue;
+                }
+                _ => new_line(&mut out, indent, depth),
+            }
+            write_events(&mut out, &events[i..=i]).unwrap();
+            i += 1;
+        }
+        out
+    }
+
     /// Deserializes using [`serde`]. This method requires the `serde` feature.
     ///
     /// This is a convenience method for [`from_fragment`](crate::de::from_fragment).
@@ -62,16 +765,263 @@ impl<'a> SgmlFragment<'a> {
     }
 }
 
+/// Options for [`SgmlFragment::text_content_with`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TextContentOptions<'a> {
+    /// Insert a space between pieces of text separated by a start or end tag,
+    /// unless there is whitespace between them already, so that text from adjacent
+    /// elements, like `<td>1</td><td>2</td>`, is not joined into a single word.
+    ///
+    /// Note that this applies to inline elements as well: `<b>bo</b>ld` becomes `bo ld`.
+    pub separate_elements: bool,
+    /// Names of elements whose content should be skipped, such as `script` and `style`.
+    ///
+    /// Names are compared exactly as they appear in the events, that is,
+    /// after any normalization performed by the parser.
+    pub exclude: &'a [&'a str],
+}
+
+/// The attributes of a start tag, as returned by [`SgmlFragment::attributes`].
+///
+/// Attributes without a value (like `HIDDEN`) have `None` as their value.
+/// Names are compared exactly as they appear in the events, that is,
+/// after any normalization performed by the parser (see
+/// [`ParserBuilder::lowercase_names`](crate::parser::ParserBuilder::lowercase_names)).
+#[derive(Clone, Copy, Debug)]
+pub struct Attributes<'f, 'a> {
+    events: &'f [SgmlEvent<'a>],
+}
+
+impl<'f> Attributes<'f, '_> {
+    /// Returns the value of the attribute with the given name, if present.
+    ///
+    /// If the attribute is declared more than once, the first occurrence wins.
+    pub fn get(&self, name: &str) -> Option<Option<&'f str>> {
+        self.iter()
+            .find(|(attribute, _)| *attribute == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Tests whether an attribute with the given name is present.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns an iterator over the names and values of the attributes, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'f str, Option<&'f str>)> {
+        self.events.iter().filter_map(SgmlEvent::as_attribute)
+    }
+
+    /// Returns the number of attributes.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns `true` if the start tag has no attributes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Splits a start tag off the beginning of the given events, returning its name,
+/// its attributes, and the events after it.
+///
+/// Returns `None` if the first event is not an [`OpenStartTag`](SgmlEvent::OpenStartTag).
+/// The remaining events start right after the [`CloseStartTag`](SgmlEvent::CloseStartTag)
+/// or [`XmlCloseEmptyElement`](SgmlEvent::XmlCloseEmptyElement) event ending the tag;
+/// if there is none, they are empty.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::{split_start_tag, SgmlEvent};
+/// # fn main() -> sgmlish::Result<()> {
+/// let fragment = sgmlish::parse(r#"<ul><li id=a>One</li><li id=b class=x>Two</li></ul>"#)?;
+/// let mut events = fragment.as_slice();
+/// let mut ids = vec![];
+/// while !events.is_empty() {
+///     if let Some((name, attributes, rest)) = split_start_tag(events) {
+///         if name == "li" {
+///             ids.push(attributes.get("id").flatten().unwrap());
+///         }
+///         events = rest;
+///     } else {
+///         events = &events[1..];
+///     }
+/// }
+/// assert_eq!(ids, ["a", "b"]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn split_start_tag<'f, 'a>(
+    events: &'f [SgmlEvent<'a>],
+) -> Option<(&'f str, Attributes<'f, 'a>, &'f [SgmlEvent<'a>])> {
+    let (first, rest) = events.split_first()?;
+    let name = match first {
+        SgmlEvent::OpenStartTag { name } => name,
+        _ => return None,
+    };
+    let end = rest.iter().position(|event| {
+        matches!(
+            event,
+            SgmlEvent::CloseStartTag | SgmlEvent::XmlCloseEmptyElement
+        )
+    });
+    let (attributes, rest) = match end {
+        Some(end) => (&rest[..end], &rest[end + 1..]),
+        None => (rest, &[][..]),
+    };
+    Some((name, Attributes { events: attributes }, rest))
+}
+
+/// Checks the layout of start tags, as described in [`SgmlFragment::from_events`].
+fn check_layout(events: &[SgmlEvent]) -> Result<(), (usize, &'static str)> {
+    let mut in_start_tag = false;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            SgmlEvent::OpenStartTag { .. } if in_start_tag => {
+                return Err((i, "start tag within another start tag"));
+            }
+            SgmlEvent::OpenStartTag { .. } => in_start_tag = true,
+            SgmlEvent::Attribute { .. }
+            | SgmlEvent::UnquotedAttribute { .. }
+            | SgmlEvent::QuotedAttribute { .. }
+                if !in_start_tag =>
+            {
+                return Err((i, "attribute outside of a start tag"));
+            }
+            SgmlEvent::Attribute { .. }
+            | SgmlEvent::UnquotedAttribute { .. }
+            | SgmlEvent::QuotedAttribute { .. } => {}
+            SgmlEvent::CloseStartTag | SgmlEvent::XmlCloseEmptyElement if !in_start_tag => {
+                return Err((i, "end of start tag outside of a start tag"));
+            }
+            SgmlEvent::CloseStartTag | SgmlEvent::XmlCloseEmptyElement => in_start_tag = false,
+            _ if in_start_tag => return Err((i, "unclosed start tag")),
+            _ => {}
+        }
+    }
+    if in_start_tag {
+        return Err((events.len(), "unclosed start tag"));
+    }
+    Ok(())
+}
+
+/// The error type returned by [`SgmlFragment::validate_balanced`].
+///
+/// Each variant includes the index of the offending event within the fragment.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UnbalancedError {
+    /// An end tag did not match the innermost open element.
+    MismatchedEndTag {
+        index: usize,
+        expected: String,
+        found: String,
+    },
+    /// An end tag was found while no element was open.
+    UnexpectedEndTag { index: usize, name: String },
+    /// An element was never closed; the index points to its start tag.
+    UnclosedElement { index: usize, name: String },
+}
+
+impl fmt::Display for UnbalancedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnbalancedError::MismatchedEndTag {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "mismatched end tag at event {}: expected </{}>, found </{}>",
+                index, expected, found
+            ),
+            UnbalancedError::UnexpectedEndTag { index, name } => {
+                write!(f, "unexpected end tag at event {}: </{}>", index, name)
+            }
+            UnbalancedError::UnclosedElement { index, name } => {
+                write!(f, "element <{}> at event {} is never closed", name, index)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnbalancedError {}
+
 impl<'a> From<Vec<SgmlEvent<'a>>> for SgmlFragment<'a> {
     fn from(events: Vec<SgmlEvent<'a>>) -> Self {
         SgmlFragment { events }
     }
 }
 
+impl<'a> From<SgmlFragment<'a>> for Vec<SgmlEvent<'a>> {
+    fn from(fragment: SgmlFragment<'a>) -> Self {
+        fragment.events
+    }
+}
+
+impl<'a> AsRef<[SgmlEvent<'a>]> for SgmlFragment<'a> {
+    fn as_ref(&self) -> &[SgmlEvent<'a>] {
+        &self.events
+    }
+}
+
+/// Builds a fragment from events, e.g. ones constructed by hand or produced
+/// by filtering another fragment.
+///
+/// ```rust
+/// # use sgmlish::{SgmlEvent, SgmlFragment};
+/// let fragment: SgmlFragment = vec![
+///     SgmlEvent::OpenStartTag { name: "p".into() },
+///     SgmlEvent::CloseStartTag,
+///     SgmlEvent::Character("Hello".into()),
+///     SgmlEvent::EndTag { name: "p".into() },
+/// ]
+/// .into_iter()
+/// .collect();
+/// assert_eq!(fragment.to_string(), "<p>Hello</p>");
+/// ```
+impl<'a> FromIterator<SgmlEvent<'a>> for SgmlFragment<'a> {
+    fn from_iter<I: IntoIterator<Item = SgmlEvent<'a>>>(iter: I) -> Self {
+        SgmlFragment {
+            events: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Parses a fragment with the default [`Parser`](crate::Parser) settings,
+/// as in [`parse`](crate::parse).
+///
+/// Since the parsed fragment cannot borrow from the string, all of its
+/// contents are copied into owned data; use [`parse`](crate::parse) to avoid this.
+///
+/// ```rust
+/// # use sgmlish::SgmlFragment;
+/// # fn main() -> sgmlish::Result<()> {
+/// let fragment = "<a href=/>Home</a>".parse::<SgmlFragment>()?;
+/// assert_eq!(fragment.to_string(), r#"<a href="/">Home</a>"#);
+/// # Ok(())
+/// # }
+/// ```
+impl FromStr for SgmlFragment<'static> {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Ok(crate::parse(s)?.into_owned())
+    }
+}
+
+impl<'a> Extend<SgmlEvent<'a>> for SgmlFragment<'a> {
+    fn extend<I: IntoIterator<Item = SgmlEvent<'a>>>(&mut self, iter: I) {
+        self.events.extend(iter)
+    }
+}
+
 impl<'a> IntoIterator for SgmlFragment<'a> {
     type Item = SgmlEvent<'a>;
 
-    type IntoIter = std::vec::IntoIter<SgmlEvent<'a>>;
+    type IntoIter = alloc::vec::IntoIter<SgmlEvent<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.events.into_iter()
@@ -81,7 +1031,7 @@ impl<'a> IntoIterator for SgmlFragment<'a> {
 impl<'a, 'b> IntoIterator for &'b SgmlFragment<'a> {
     type Item = &'b SgmlEvent<'a>;
 
-    type IntoIter = std::slice::Iter<'b, SgmlEvent<'a>>;
+    type IntoIter = core::slice::Iter<'b, SgmlEvent<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
@@ -91,7 +1041,7 @@ impl<'a, 'b> IntoIterator for &'b SgmlFragment<'a> {
 impl<'a, 'b> IntoIterator for &'b mut SgmlFragment<'a> {
     type Item = &'b mut SgmlEvent<'a>;
 
-    type IntoIter = std::slice::IterMut<'b, SgmlEvent<'a>>;
+    type IntoIter = core::slice::IterMut<'b, SgmlEvent<'a>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter_mut()
@@ -100,11 +1050,537 @@ impl<'a, 'b> IntoIterator for &'b mut SgmlFragment<'a> {
 
 impl fmt::Display for SgmlFragment<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.events.iter().try_for_each(|event| {
-            if let SgmlEvent::Attribute { .. } = event {
-                f.write_str(" ")?;
+        write_events(f, &self.events)
+    }
+}
+
+fn write_events<W: fmt::Write>(mut f: W, events: &[SgmlEvent]) -> fmt::Result {
+    events.iter().try_for_each(|event| {
+        if let SgmlEvent::Attribute { .. }
+        | SgmlEvent::UnquotedAttribute { .. }
+        | SgmlEvent::QuotedAttribute { .. } = event
+        {
+            f.write_str(" ")?;
+        }
+        write!(f, "{}", event)
+    })
+}
+
+/// Where the element started at a given index ends.
+enum ElementEnd {
+    /// An XML-style empty element, ending with `/>` at the given index.
+    Empty(usize),
+    /// An element closed by the end tag at the given index.
+    EndTag(usize),
+    /// An element that is never closed.
+    Unclosed,
+}
+
+/// Finds the end of the element whose start tag begins at `start`,
+/// matching end tags by nesting depth.
+///
+/// An end tag with a different name at the same depth means the element
+/// is never closed, as happens with omitted end tags.
+fn element_end(events: &[SgmlEvent], start: usize) -> ElementEnd {
+    let name = events[start].tag_name();
+    let close = start_tag_end(events, start);
+    if let Some(SgmlEvent::XmlCloseEmptyElement) = events.get(close) {
+        return ElementEnd::Empty(close);
+    }
+    let mut nesting = 0;
+    for (i, event) in events.iter().enumerate().skip(close + 1) {
+        match event {
+            SgmlEvent::OpenStartTag { .. } => nesting += 1,
+            SgmlEvent::XmlCloseEmptyElement => nesting -= 1,
+            SgmlEvent::EndTag { name: end_name } if nesting == 0 => {
+                return if end_name.is_empty() || Some(end_name.as_ref()) == name {
+                    ElementEnd::EndTag(i)
+                } else {
+                    ElementEnd::Unclosed
+                };
             }
-            fmt::Display::fmt(event, f)
+            SgmlEvent::EndTag { .. } => nesting -= 1,
+            _ => {}
+        }
+    }
+    ElementEnd::Unclosed
+}
+
+/// Finds the index of the event closing the start tag that begins at `start`.
+fn start_tag_end(events: &[SgmlEvent], start: usize) -> usize {
+    events[start..]
+        .iter()
+        .position(|event| {
+            matches!(
+                event,
+                SgmlEvent::CloseStartTag | SgmlEvent::XmlCloseEmptyElement
+            )
         })
+        .map_or(events.len() - 1, |offset| start + offset)
+}
+
+/// Tests whether the given element (from its start tag up to, but excluding,
+/// its end tag) should be written on a single line: that is, if it directly
+/// contains any non-whitespace text, or if it contains no child elements.
+fn keep_inline(element: &[SgmlEvent]) -> bool {
+    let mut nesting = 0;
+    let mut has_children = false;
+    for event in &element[start_tag_end(element, 0) + 1..] {
+        match event {
+            SgmlEvent::OpenStartTag { .. } => {
+                has_children = true;
+                nesting += 1;
+            }
+            SgmlEvent::XmlCloseEmptyElement | SgmlEvent::EndTag { .. } => nesting -= 1,
+            SgmlEvent::Character(text) if nesting == 0 && !crate::text::is_blank(text) => {
+                return true
+            }
+            _ => {}
+        }
+    }
+    !has_children
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::borrow::Cow;
+
+    use crate::{parse, Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let fragment = "<a/><b x=1>text</b>".parse::<SgmlFragment>().unwrap();
+        assert_eq!(fragment, parse("<a/><b x=1>text</b>").unwrap());
+        assert_eq!(fragment.to_string(), r#"<a/><b x="1">text</b>"#);
+
+        let err = "<a b='></a>".parse::<SgmlFragment>().unwrap_err();
+        assert!(matches!(err, crate::Error::ParseError(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let input = "<a href='x'>caf&#233;</a>";
+        let owned_input = String::from(input);
+        let owned = parse(&owned_input).unwrap().into_owned();
+        assert_eq!(owned, parse(input).unwrap());
+        assert!(owned.iter().all(|event| match event {
+            SgmlEvent::OpenStartTag { name } | SgmlEvent::EndTag { name } => {
+                matches!(name, Cow::Owned(_))
+            }
+            SgmlEvent::Attribute { name, value } => {
+                matches!(name, Cow::Owned(_)) && matches!(value, Some(Cow::Owned(_)))
+            }
+            SgmlEvent::Character(text) => matches!(text, Cow::Owned(_)),
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn test_map_text() {
+        let input = "<p>one</p>\n<p>two &#38; three</p>";
+        let fragment = parse(input).unwrap().map_text(|text| text);
+        assert_eq!(fragment, parse(input).unwrap());
+        match &fragment.as_slice()[2] {
+            SgmlEvent::Character(Cow::Borrowed(text)) => {
+                assert_eq!(text.as_ptr(), input[3..].as_ptr());
+            }
+            event => panic!("expected borrowed text, got {:?}", event),
+        }
+
+        let fragment = fragment.map_text(|text| text.to_uppercase().into());
+        assert_eq!(fragment.to_string(), "<p>ONE</p><p>TWO &#38; THREE</p>");
+    }
+
+    #[test]
+    fn test_text_content() {
+        let input = concat!(
+            "<article><h1>Title</h1>",
+            "<p>Hello, <b>bold</b><i>world</i>!<br>Bye</p>",
+            "<script>var x = 1;</script>",
+            "<ul><li>one<li>two <script>nested</script>three</ul></article>",
+        );
+        let fragment = Parser::builder()
+            .trim_whitespace(false)
+            .parse(input)
+            .unwrap();
+        assert_eq!(
+            fragment.text_content(),
+            "TitleHello, boldworld!Byevar x = 1;onetwo nestedthree"
+        );
+
+        let options = TextContentOptions {
+            separate_elements: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fragment.text_content_with(&options),
+            "Title Hello, bold world ! Bye var x = 1; one two nested three"
+        );
+
+        let options = TextContentOptions {
+            separate_elements: true,
+            exclude: &["script"],
+        };
+        assert_eq!(
+            fragment.text_content_with(&options),
+            "Title Hello, bold world ! Bye one two three"
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let fragment = parse("<a>x</a><b></b>").unwrap();
+        let events = fragment.clone().into_vec();
+
+        assert_eq!(SgmlFragment::from(events.clone()), fragment);
+        assert_eq!(Vec::from(fragment.clone()), events);
+        assert_eq!(fragment.as_ref(), &events[..]);
+        assert_eq!(fragment.iter().cloned().collect::<SgmlFragment>(), fragment);
+        assert_eq!((&fragment).into_iter().count(), events.len());
+
+        let mut combined = fragment
+            .clone()
+            .into_iter()
+            .take_while(|event| !matches!(event, SgmlEvent::EndTag { .. }))
+            .collect::<SgmlFragment>();
+        combined.extend(events.into_iter().skip(3));
+        assert_eq!(combined, fragment);
+    }
+
+    #[test]
+    fn test_attributes() {
+        let fragment = crate::Parser::builder()
+            .lowercase_names()
+            .unquoted_attribute_events(true)
+            .parse(r#"<TD COLSPAN=2 Class="a" NOWRAP class=b>x</TD><BR/>"#)
+            .unwrap();
+
+        let attributes = fragment.attributes(0).unwrap();
+        assert_eq!(attributes.len(), 4);
+        assert_eq!(attributes.get("colspan"), Some(Some("2")));
+        assert_eq!(attributes.get("class"), Some(Some("a")));
+        assert_eq!(attributes.get("nowrap"), Some(None));
+        assert_eq!(attributes.get("COLSPAN"), None);
+        assert!(attributes.contains("nowrap"));
+        assert!(!attributes.contains("rowspan"));
+        assert_eq!(
+            attributes.iter().collect::<Vec<_>>(),
+            [
+                ("colspan", Some("2")),
+                ("class", Some("a")),
+                ("nowrap", None),
+                ("class", Some("b")),
+            ]
+        );
+
+        let br = fragment.attributes(8).unwrap();
+        assert!(br.is_empty());
+        assert_eq!(br.get("class"), None);
+
+        assert!(fragment.attributes(1).is_none());
+        assert!(fragment.attributes(100).is_none());
+    }
+
+    #[test]
+    fn test_split_start_tag() {
+        let fragment = parse(r#"<td colspan=2 nowrap>x</td>"#).unwrap();
+        let (name, attributes, rest) = split_start_tag(fragment.as_slice()).unwrap();
+        assert_eq!(name, "td");
+        assert_eq!(
+            attributes.iter().collect::<Vec<_>>(),
+            [("colspan", Some("2")), ("nowrap", None)]
+        );
+        assert_eq!(
+            rest,
+            [
+                SgmlEvent::Character("x".into()),
+                SgmlEvent::EndTag { name: "td".into() },
+            ]
+        );
+
+        assert!(split_start_tag(rest).is_none());
+        assert!(split_start_tag(&[]).is_none());
+    }
+
+    #[test]
+    fn test_from_events() {
+        let events = parse("<a x=1><b/>text</a>").unwrap().into_vec();
+        let fragment = SgmlFragment::from_events(events.clone());
+        assert_eq!(fragment.into_vec(), events);
+
+        let invalid: &[&[SgmlEvent]] = &[
+            &[SgmlEvent::OpenStartTag { name: "a".into() }],
+            &[
+                SgmlEvent::OpenStartTag { name: "a".into() },
+                SgmlEvent::Character("x".into()),
+                SgmlEvent::CloseStartTag,
+            ],
+            &[
+                SgmlEvent::OpenStartTag { name: "a".into() },
+                SgmlEvent::OpenStartTag { name: "b".into() },
+            ],
+            &[SgmlEvent::Attribute {
+                name: "x".into(),
+                value: None,
+            }],
+            &[SgmlEvent::XmlCloseEmptyElement],
+        ];
+        for events in invalid {
+            assert!(check_layout(events).is_err(), "{:?}", events);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalid event at index 1: attribute outside of a start tag")]
+    fn test_from_events_invalid() {
+        SgmlFragment::from_events(vec![
+            SgmlEvent::Character("x".into()),
+            SgmlEvent::Attribute {
+                name: "y".into(),
+                value: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_count_elements() {
+        let fragment = parse("<a><b><b><b/></b></b><c>b</c><B></B></a>").unwrap();
+        assert_eq!(fragment.count_elements("b"), 3);
+        assert_eq!(fragment.count_elements("a"), 1);
+        assert_eq!(fragment.count_elements("B"), 1);
+        assert_eq!(fragment.count_elements("d"), 0);
+
+        let fragment = Parser::builder()
+            .lowercase_names()
+            .parse("<UL><LI>one<li>two</UL>")
+            .unwrap();
+        assert_eq!(fragment.count_elements("li"), 2);
+        assert_eq!(fragment.count_elements("LI"), 0);
+    }
+
+    #[test]
+    fn test_subtree() {
+        let fragment = parse("<a><b x=1><b>inner</b><c/></b><b>second</b><d></d></a>").unwrap();
+        assert_eq!(
+            fragment.subtree("b").unwrap().to_string(),
+            r#"<b x="1"><b>inner</b><c/></b>"#
+        );
+        assert_eq!(fragment.subtree("c").unwrap().to_string(), "<c/>");
+        assert_eq!(fragment.subtree("d").unwrap().to_string(), "<d></d>");
+        assert_eq!(fragment.subtree("a"), Some(fragment.clone()));
+        assert_eq!(fragment.subtree("B"), None);
+        assert_eq!(fragment.subtree("e"), None);
+
+        let fragment = parse("<ul><li>one<li>two</ul>").unwrap();
+        assert_eq!(fragment.subtree("li"), None);
+        assert_eq!(fragment.subtree("ul"), None);
+        let fragment = crate::transforms::normalize_end_tags(fragment).unwrap();
+        assert_eq!(fragment.subtree("li").unwrap().to_string(), "<li>one</li>");
+        assert_eq!(
+            fragment.subtree("ul").unwrap().to_string(),
+            "<ul><li>one</li><li>two</li></ul>"
+        );
+    }
+
+    #[test]
+    fn test_matching_end() {
+        let fragment = parse("<a><a x=1><a/></a><b></></a><c>").unwrap();
+        assert_eq!(fragment.matching_end(0), Some(11));
+        assert_eq!(fragment.matching_end(2), Some(7));
+        assert_eq!(fragment.matching_end(5), Some(6));
+        assert_eq!(fragment.matching_end(8), Some(10));
+        assert_eq!(fragment.matching_end(12), None);
+        assert_eq!(fragment.matching_end(1), None);
+        assert_eq!(fragment.matching_end(100), None);
+
+        let fragment = parse("<ul><li>one<li>two</ul>").unwrap();
+        assert_eq!(fragment.matching_end(0), None);
+        assert_eq!(fragment.matching_end(2), None);
+    }
+
+    #[test]
+    fn test_validate_balanced() {
+        let fragment = parse("<a><b x=1>text</b><br/><c></></a>").unwrap();
+        assert_eq!(fragment.validate_balanced(), Ok(()));
+
+        let fragment = parse("<?pi><!DOCTYPE a><a>text</a>").unwrap();
+        assert_eq!(fragment.validate_balanced(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_balanced_errors() {
+        let fragment = parse("<a><b></c></a>").unwrap();
+        assert_eq!(
+            fragment.validate_balanced(),
+            Err(UnbalancedError::MismatchedEndTag {
+                index: 4,
+                expected: "b".to_owned(),
+                found: "c".to_owned(),
+            })
+        );
+
+        let fragment = parse("<a></a></a>").unwrap();
+        assert_eq!(
+            fragment.validate_balanced(),
+            Err(UnbalancedError::UnexpectedEndTag {
+                index: 3,
+                name: "a".to_owned(),
+            })
+        );
+
+        let fragment = parse("<a><b></b>").unwrap();
+        assert_eq!(
+            fragment.validate_balanced(),
+            Err(UnbalancedError::UnclosedElement {
+                index: 0,
+                name: "a".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_to_pretty_string() {
+        let fragment = parse(
+            r#"
+            <!DOCTYPE doc>
+            <doc lang=en>
+                <title>A <em>small</em> document</title>
+                <section><p>One</p><hr/>
+                    <list><item>a</item><item></item></list>
+                </section>
+                <?pi>
+            </doc>
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            fragment.to_pretty_string("\t"),
+            concat!(
+                "<!DOCTYPE doc>\n",
+                "<doc lang=\"en\">\n",
+                "\t<title>A<em>small</em>document</title>\n",
+                "\t<section>\n",
+                "\t\t<p>One</p>\n",
+                "\t\t<hr/>\n",
+                "\t\t<list>\n",
+                "\t\t\t<item>a</item>\n",
+                "\t\t\t<item></item>\n",
+                "\t\t</list>\n",
+                "\t</section>\n",
+                "\t<?pi>\n",
+                "</doc>",
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_pretty_string_unclosed() {
+        let fragment = parse("<a><b>text<c></a>").unwrap();
+        assert_eq!(
+            fragment.to_pretty_string("  "),
+            "<a>\n  <b>\n    text\n    <c>\n    </a>"
+        );
+    }
+
+    #[test]
+    fn test_display_preserves_attribute_quotes() {
+        let input = r#"<p><a href='/x?a=1&#38;b=2' title="it's" class=link>x</a><img alt='"'></p>"#;
+        let fragment = crate::Parser::builder()
+            .quoted_attribute_events(true)
+            .unquoted_attribute_events(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(fragment.to_string(), input);
+
+        let fragment = parse(input).unwrap();
+        assert_eq!(
+            fragment.to_string(),
+            r#"<p><a href="/x?a=1&#38;b=2" title="it's" class="link">x</a><img alt='"'></p>"#
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "quick-xml")]
+    fn test_to_quick_xml_events() {
+        use quick_xml::events::{BytesCData, BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+
+        let fragment = crate::Parser::builder()
+            .recognize_xml_declaration(true)
+            .unquoted_attribute_events(true)
+            .marked_section_handling(crate::parser::MarkedSectionHandling::KeepUnmodified)
+            .parse(concat!(
+                r#"<?xml version="1.0"?><!DOCTYPE list SYSTEM "list.dtd"><?pi data?>"#,
+                r#"<list compact type=a><item/><item>1 &#60; 2</><![CDATA[<raw>]]></list>"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            fragment.to_quick_xml_events().unwrap(),
+            vec![
+                Event::Decl(BytesDecl::new("1.0", None, None)),
+                Event::DocType(BytesText::from_escaped(r#"list SYSTEM "list.dtd""#)),
+                Event::PI(BytesText::from_escaped("pi data")),
+                Event::Start(
+                    BytesStart::new("list")
+                        .with_attributes([("compact", "compact"), ("type", "a")])
+                ),
+                Event::Empty(BytesStart::new("item")),
+                Event::Start(BytesStart::new("item")),
+                Event::Text(BytesText::new("1 < 2")),
+                Event::End(BytesEnd::new("item")),
+                Event::CData(BytesCData::new("<raw>")),
+                Event::End(BytesEnd::new("list")),
+            ]
+        );
+
+        let fragment = parse("<a></a></>").unwrap();
+        assert_eq!(
+            fragment.to_quick_xml_events(),
+            Err(UnbalancedError::UnexpectedEndTag {
+                index: 3,
+                name: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_to_json() {
+        use serde_json::json;
+
+        let fragment = crate::Parser::builder()
+            .unquoted_attribute_events(true)
+            .parse(
+                r#"<?pi><list compact type=a><item/><item>one</><item>two <b>!</b></item></list>"#,
+            )
+            .unwrap();
+        assert_eq!(
+            fragment.to_json().unwrap(),
+            json!([{
+                "name": "list",
+                "attributes": { "compact": null, "type": "a" },
+                "children": [
+                    { "name": "item", "attributes": {}, "children": [] },
+                    { "name": "item", "attributes": {}, "children": ["one"] },
+                    {
+                        "name": "item",
+                        "attributes": {},
+                        "children": ["two", { "name": "b", "attributes": {}, "children": ["!"] }],
+                    },
+                ],
+            }])
+        );
+
+        let fragment = parse("<a><b></a>").unwrap();
+        assert_eq!(
+            fragment.to_json(),
+            Err(UnbalancedError::MismatchedEndTag {
+                index: 4,
+                expected: "b".to_owned(),
+                found: "a".to_owned(),
+            })
+        );
     }
 }
@@ -2,6 +2,17 @@
 //!
 //! For a quick example of deserialization, see [`from_fragment`].
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(all(feature = "serde", not(feature = "std")))]
+compile_error!("the `serde` feature requires the `std` feature");
+
+#[cfg(all(feature = "miette", not(feature = "std")))]
+compile_error!("the `miette` feature requires the `std` feature");
+
+pub mod dtd;
 pub mod entities;
 pub mod error;
 mod fragment;
@@ -9,19 +20,31 @@ pub mod marked_sections;
 pub mod parser;
 pub mod text;
 pub mod transforms;
+mod visitor;
 
-use std::borrow::Cow;
-use std::fmt::{self, Write};
+mod prelude;
+
+use alloc::borrow::Cow;
+use core::fmt;
+
+use crate::marked_sections::MarkedSectionStatus;
+use crate::prelude::*;
 
 pub use error::{Error, Result};
 pub use fragment::*;
 pub use parser::{parse, Parser, ParserConfig};
+pub use visitor::Visitor;
 
 #[cfg(feature = "serde")]
 pub mod de;
 
+#[cfg(feature = "serde")]
+pub mod ser;
+
 #[cfg(feature = "serde")]
 pub use de::from_fragment;
+#[cfg(feature = "serde")]
+pub use ser::{to_fragment, to_string};
 
 /// Represents a relevant occurrence in an SGML document.
 ///
@@ -44,6 +67,28 @@ pub enum SgmlEvent<'a> {
     },
     /// A processing instruction, e.g. `<?EXAMPLE>`
     ProcessingInstruction(Cow<'a, str>),
+    /// A processing instruction split into its target and data,
+    /// e.g. `<?xml version="1.0"?>` becomes target `xml` and data `version="1.0"`.
+    ///
+    /// This is only emitted when [`ParserConfig::structured_processing_instructions`]
+    /// is enabled. The target is everything up to the first whitespace; the data
+    /// is everything after that whitespace run, with the XML-style trailing `?`
+    /// removed. Consequently, an XML-style instruction is displayed as `<?xml version="1.0">`.
+    StructuredProcessingInstruction {
+        target: Cow<'a, str>,
+        data: Cow<'a, str>,
+    },
+    /// An XML declaration, e.g. `<?xml version="1.0" encoding="UTF-8"?>`.
+    ///
+    /// This is only emitted when [`ParserConfig::recognize_xml_declaration`]
+    /// is enabled, and only for a declaration at the start of the document;
+    /// anywhere else, it is treated as any other processing instruction.
+    /// `standalone` is `true` for `standalone="yes"`, and `false` for `standalone="no"`.
+    XmlDeclaration {
+        version: Cow<'a, str>,
+        encoding: Option<Cow<'a, str>>,
+        standalone: Option<bool>,
+    },
     /// A marked section, like `<![IGNORE[...]]>`.
     MarkedSection {
         status_keywords: Cow<'a, str>,
@@ -52,13 +97,41 @@ pub enum SgmlEvent<'a> {
     /// The beginning of a start-element tag, e.g. `<EXAMPLE`.
     ///
     /// Empty start-elements (`<>`) are also represented by this event,
-    /// with an empty slice.
+    /// with an empty slice; see [`transforms::resolve_empty_tags`] for naming them.
     OpenStartTag { name: Cow<'a, str> },
     /// An attribute inside a start-element tag, e.g. `FOO="bar"`.
+    ///
+    /// Like with [`Character`](SgmlEvent::Character), the value is final:
+    /// entity and character references have already been expanded, so any
+    /// undefined entity is reported as a parse error. The value is `None`
+    /// for attributes written without a value, such as `CHECKED`.
     Attribute {
         name: Cow<'a, str>,
         value: Option<Cow<'a, str>>,
     },
+    /// An attribute whose value was written without quotes, e.g. `COLSPAN=2`.
+    ///
+    /// This is only emitted when [`ParserConfig::unquoted_attribute_events`]
+    /// is enabled; otherwise, such attributes are represented by
+    /// [`Attribute`](SgmlEvent::Attribute) like any other.
+    /// Unlike quoted values, unquoted values never contain entity or character references.
+    UnquotedAttribute {
+        name: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+    /// An attribute whose value was written with quotes, along with the
+    /// quote character used (`'` or `"`), e.g. `FOO='bar'`.
+    ///
+    /// This is only emitted when [`ParserConfig::quoted_attribute_events`]
+    /// is enabled; otherwise, such attributes are represented by
+    /// [`Attribute`](SgmlEvent::Attribute) like any other.
+    /// When displayed, the same quote character is used, and occurrences
+    /// of it within the value are escaped as character references.
+    QuotedAttribute {
+        name: Cow<'a, str>,
+        value: Cow<'a, str>,
+        quote: char,
+    },
     /// Closing of a start-element tag, e.g. `>`.
     CloseStartTag,
     /// XML-specific closing of empty elements, e.g. `/>`
@@ -66,13 +139,104 @@ pub enum SgmlEvent<'a> {
     /// An end-element tag, e.g. `</EXAMPLE>`.
     ///
     /// Empty end-element tags (`</>`) are also represented by this event,
-    /// with an empty slice.
+    /// with an empty slice; see [`transforms::resolve_empty_tags`] for naming them.
     EndTag { name: Cow<'a, str> },
     /// Any string of characters that is not part of a tag.
+    ///
+    /// The text is final: entity and character references have already been
+    /// expanded by the parser, and `CDATA`/`RCDATA` marked sections (when expanded)
+    /// produce plain text too. Use [`Cow::into_owned`] to get a `String`;
+    /// this only allocates if the text was borrowed from the input.
     Character(Cow<'a, str>),
+    /// A run of text consisting only of whitespace, e.g. indentation between tags.
+    ///
+    /// This is only emitted when [`ParserConfig::whitespace_handling`] is
+    /// [`Separate`](parser::WhitespaceHandling::Separate); otherwise, such text
+    /// is either dropped or represented by [`Character`](SgmlEvent::Character).
+    Whitespace(Cow<'a, str>),
 }
 
 impl<'a> SgmlEvent<'a> {
+    /// Returns `true` if this is the beginning of a start-element tag
+    /// ([`OpenStartTag`](SgmlEvent::OpenStartTag)).
+    pub fn is_start_tag(&self) -> bool {
+        matches!(self, SgmlEvent::OpenStartTag { .. })
+    }
+
+    /// Returns `true` if this is an end-element tag ([`EndTag`](SgmlEvent::EndTag)).
+    pub fn is_end_tag(&self) -> bool {
+        matches!(self, SgmlEvent::EndTag { .. })
+    }
+
+    /// Returns `true` if this is character data ([`Character`](SgmlEvent::Character)).
+    pub fn is_text(&self) -> bool {
+        matches!(self, SgmlEvent::Character(_))
+    }
+
+    /// Returns the element name of an [`OpenStartTag`](SgmlEvent::OpenStartTag)
+    /// or [`EndTag`](SgmlEvent::EndTag) event, and `None` for any other event.
+    ///
+    /// Empty tags (`<>` and `</>`) yield an empty name.
+    pub fn tag_name(&self) -> Option<&str> {
+        match self {
+            SgmlEvent::OpenStartTag { name } | SgmlEvent::EndTag { name } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Returns the name and value of an attribute event ([`Attribute`](SgmlEvent::Attribute),
+    /// [`UnquotedAttribute`](SgmlEvent::UnquotedAttribute) or
+    /// [`QuotedAttribute`](SgmlEvent::QuotedAttribute)), and `None` for any other event.
+    ///
+    /// The value is `None` for attributes written without one (`<ul compact>`).
+    pub fn as_attribute(&self) -> Option<(&str, Option<&str>)> {
+        match self {
+            SgmlEvent::Attribute { name, value } => Some((name, value.as_deref())),
+            SgmlEvent::UnquotedAttribute { name, value }
+            | SgmlEvent::QuotedAttribute { name, value, .. } => Some((name, Some(value))),
+            _ => None,
+        }
+    }
+
+    /// Returns the text of a [`Character`](SgmlEvent::Character) event,
+    /// and `None` for any other event.
+    pub fn as_character(&self) -> Option<&str> {
+        match self {
+            SgmlEvent::Character(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Parses the status keywords of a [`MarkedSection`](SgmlEvent::MarkedSection) event,
+    /// and returns `None` for any other event.
+    ///
+    /// The keywords are combined as described in
+    /// [`MarkedSectionStatus::from_keywords`]; an unknown keyword is reported as
+    /// [`Error::InvalidMarkedSectionKeyword`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::marked_sections::MarkedSectionStatus;
+    /// # use sgmlish::SgmlEvent;
+    /// let event = SgmlEvent::MarkedSection {
+    ///     status_keywords: "TEMP RCDATA".into(),
+    ///     section: "a & b".into(),
+    /// };
+    /// assert_eq!(event.parsed_status().unwrap().unwrap(), MarkedSectionStatus::RcData);
+    /// ```
+    pub fn parsed_status(&self) -> Option<Result<MarkedSectionStatus>> {
+        match self {
+            SgmlEvent::MarkedSection {
+                status_keywords, ..
+            } => Some(
+                MarkedSectionStatus::from_keywords(status_keywords)
+                    .map_err(|keyword| Error::InvalidMarkedSectionKeyword(keyword.to_owned())),
+            ),
+            _ => None,
+        }
+    }
+
     pub fn into_owned(self) -> SgmlEvent<'static> {
         match self {
             SgmlEvent::MarkupDeclaration { keyword, body } => SgmlEvent::MarkupDeclaration {
@@ -80,6 +244,21 @@ impl<'a> SgmlEvent<'a> {
                 body: make_owned(body),
             },
             SgmlEvent::ProcessingInstruction(s) => SgmlEvent::ProcessingInstruction(make_owned(s)),
+            SgmlEvent::StructuredProcessingInstruction { target, data } => {
+                SgmlEvent::StructuredProcessingInstruction {
+                    target: make_owned(target),
+                    data: make_owned(data),
+                }
+            }
+            SgmlEvent::XmlDeclaration {
+                version,
+                encoding,
+                standalone,
+            } => SgmlEvent::XmlDeclaration {
+                version: make_owned(version),
+                encoding: encoding.map(make_owned),
+                standalone,
+            },
             Self::MarkedSection {
                 status_keywords,
                 section,
@@ -94,12 +273,22 @@ impl<'a> SgmlEvent<'a> {
                 name: make_owned(name),
                 value: value.map(make_owned),
             },
+            SgmlEvent::UnquotedAttribute { name, value } => SgmlEvent::UnquotedAttribute {
+                name: make_owned(name),
+                value: make_owned(value),
+            },
+            SgmlEvent::QuotedAttribute { name, value, quote } => SgmlEvent::QuotedAttribute {
+                name: make_owned(name),
+                value: make_owned(value),
+                quote,
+            },
             SgmlEvent::CloseStartTag => SgmlEvent::CloseStartTag,
             SgmlEvent::XmlCloseEmptyElement => SgmlEvent::XmlCloseEmptyElement,
             SgmlEvent::EndTag { name } => SgmlEvent::EndTag {
                 name: make_owned(name),
             },
             SgmlEvent::Character(text) => SgmlEvent::Character(make_owned(text)),
+            SgmlEvent::Whitespace(text) => SgmlEvent::Whitespace(make_owned(text)),
         }
     }
 }
@@ -122,6 +311,29 @@ impl fmt::Display for SgmlEvent<'_> {
                 f.write_str(">")
             }
             SgmlEvent::ProcessingInstruction(decl) => f.write_str(decl),
+            SgmlEvent::StructuredProcessingInstruction { target, data } => {
+                write!(f, "<?{}", target)?;
+                if !data.is_empty() {
+                    write!(f, " {}", data)?;
+                }
+                f.write_str(">")
+            }
+            SgmlEvent::XmlDeclaration {
+                version,
+                encoding,
+                standalone,
+            } => {
+                write!(f, "<?xml version=\"{}\"", version)?;
+                if let Some(encoding) = encoding {
+                    write!(f, " encoding=\"{}\"", encoding)?;
+                }
+                match standalone {
+                    Some(true) => f.write_str(" standalone=\"yes\"")?,
+                    Some(false) => f.write_str(" standalone=\"no\"")?,
+                    None => {}
+                }
+                f.write_str("?>")
+            }
             SgmlEvent::MarkedSection {
                 status_keywords,
                 section,
@@ -141,19 +353,19 @@ impl fmt::Display for SgmlEvent<'_> {
                 } else if !escape_ampersand && !value.contains('\'') {
                     write!(f, "='{}'", value)
                 } else {
-                    f.write_str("=\"")?;
-                    value.chars().try_for_each(|c| match c {
-                        '"' => f.write_str("&#34;"),
-                        '&' => f.write_str("&#38;"),
-                        c => f.write_char(c),
-                    })?;
-                    f.write_str("\"")
+                    write!(f, "=\"{}\"", text::escape_for_attribute(value, '"'))
                 }
             }
+            SgmlEvent::UnquotedAttribute { name, value } => write!(f, "{}={}", name, value),
+            SgmlEvent::QuotedAttribute { name, value, quote } => {
+                let value = text::escape_for_attribute(value, *quote);
+                write!(f, "{}={}{}{}", name, quote, value, quote)
+            }
             SgmlEvent::CloseStartTag => f.write_str(">"),
             SgmlEvent::XmlCloseEmptyElement => f.write_str("/>"),
             SgmlEvent::EndTag { name } => write!(f, "</{}>", name),
-            SgmlEvent::Character(value) => fmt::Display::fmt(&text::escape(value), f),
+            SgmlEvent::Character(value) => f.write_str(&text::escape_for_text(value)),
+            SgmlEvent::Whitespace(value) => f.write_str(value),
         }
     }
 }
@@ -189,6 +401,48 @@ mod tests {
             format!("{}", ProcessingInstruction("<?IS10744 FSIDR myurl>".into())),
             "<?IS10744 FSIDR myurl>"
         );
+        assert_eq!(
+            format!(
+                "{}",
+                StructuredProcessingInstruction {
+                    target: "IS10744".into(),
+                    data: "FSIDR myurl".into(),
+                }
+            ),
+            "<?IS10744 FSIDR myurl>"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                StructuredProcessingInstruction {
+                    target: "page".into(),
+                    data: "".into(),
+                }
+            ),
+            "<?page>"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                XmlDeclaration {
+                    version: "1.0".into(),
+                    encoding: Some("UTF-8".into()),
+                    standalone: Some(true),
+                }
+            ),
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                XmlDeclaration {
+                    version: "1.0".into(),
+                    encoding: None,
+                    standalone: None,
+                }
+            ),
+            r#"<?xml version="1.0"?>"#
+        );
 
         assert_eq!(format!("{}", OpenStartTag { name: "foo".into() }), "<foo");
         assert_eq!(
@@ -219,6 +473,72 @@ mod tests {
         assert_eq!(format!("{}", Character("hello".into())), "hello");
     }
 
+    #[test]
+    fn test_predicates() {
+        use super::SgmlEvent::*;
+        let start = OpenStartTag { name: "a".into() };
+        let end = EndTag { name: "a".into() };
+        let text = Character("text".into());
+        let attribute = Attribute {
+            name: "a".into(),
+            value: None,
+        };
+
+        assert!(start.is_start_tag() && !start.is_end_tag() && !start.is_text());
+        assert!(!end.is_start_tag() && end.is_end_tag() && !end.is_text());
+        assert!(!text.is_start_tag() && !text.is_end_tag() && text.is_text());
+
+        assert_eq!(start.tag_name(), Some("a"));
+        assert_eq!(end.tag_name(), Some("a"));
+        assert_eq!(text.tag_name(), None);
+        assert_eq!(attribute.tag_name(), None);
+        assert_eq!(CloseStartTag.tag_name(), None);
+
+        assert_eq!(text.as_character(), Some("text"));
+        assert_eq!(start.as_character(), None);
+
+        assert_eq!(attribute.as_attribute(), Some(("a", None)));
+        let unquoted = UnquotedAttribute {
+            name: "b".into(),
+            value: "1".into(),
+        };
+        assert_eq!(unquoted.as_attribute(), Some(("b", Some("1"))));
+        let quoted = QuotedAttribute {
+            name: "c".into(),
+            value: "".into(),
+            quote: '"',
+        };
+        assert_eq!(quoted.as_attribute(), Some(("c", Some(""))));
+        assert_eq!(start.as_attribute(), None);
+    }
+
+    #[test]
+    fn test_parsed_status() {
+        let status = |keywords: &'static str| {
+            let event = SgmlEvent::MarkedSection {
+                status_keywords: keywords.into(),
+                section: "".into(),
+            };
+            event.parsed_status().unwrap()
+        };
+
+        assert_eq!(status("").unwrap(), MarkedSectionStatus::Include);
+        assert_eq!(status("temp").unwrap(), MarkedSectionStatus::Include);
+        assert_eq!(status("CDATA RCDATA").unwrap(), MarkedSectionStatus::CData);
+        assert_eq!(
+            status(" rcdata\nIGNORE ").unwrap(),
+            MarkedSectionStatus::Ignore
+        );
+        assert!(matches!(
+            status("CDATA BOGUS"),
+            Err(Error::InvalidMarkedSectionKeyword(keyword)) if keyword == "BOGUS"
+        ));
+
+        assert!(SgmlEvent::Character("CDATA".into())
+            .parsed_status()
+            .is_none());
+    }
+
     #[test]
     fn test_display_attribute() {
         assert_eq!(
@@ -285,5 +605,31 @@ mod tests {
             .to_string(),
             "key=\"a&#38;o'\""
         );
+        assert_eq!(
+            SgmlEvent::UnquotedAttribute {
+                name: "key".into(),
+                value: "2".into(),
+            }
+            .to_string(),
+            "key=2"
+        );
+        assert_eq!(
+            SgmlEvent::QuotedAttribute {
+                name: "key".into(),
+                value: "it's \"a&o\"".into(),
+                quote: '\'',
+            }
+            .to_string(),
+            "key='it&#39;s \"a&#38;o\"'"
+        );
+        assert_eq!(
+            SgmlEvent::QuotedAttribute {
+                name: "key".into(),
+                value: "it's \"a&o\"".into(),
+                quote: '"',
+            }
+            .to_string(),
+            "key=\"it's &#34;a&#38;o&#34;\""
+        );
     }
 }
@@ -1,6 +1,6 @@
 //! Items related to parsing marked sections.
 
-use std::str::FromStr;
+use core::str::FromStr;
 
 const KEYWORDS: &[(&str, MarkedSectionStatus)] = &[
     ("CDATA", MarkedSectionStatus::CData),
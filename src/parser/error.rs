@@ -1,5 +1,7 @@
-use std::fmt;
-use std::ops::Deref;
+use core::fmt;
+use core::ops::Deref;
+
+use crate::prelude::*;
 
 /// A [`nom`]-compatible error type that captures relevant information
 /// for the SGML parser.
@@ -40,18 +42,7 @@ impl<I: Deref<Target = str>> ContextualizedError<I> {
             write!(f, "in {}, ", ctx)?;
         }
         write!(f, "at line {}:", location.line_number)?;
-        if let Some(err) = &self.error {
-            write!(f, " {}", err)?;
-        }
-        if let Some(c) = self.char {
-            write!(f, " expected '{}', got ", c)?;
-            match self.input.chars().next() {
-                Some(' ' | '\t') => write!(f, "whitespace")?,
-                Some('\r' | '\n') => write!(f, "end of line")?,
-                Some(c) => write!(f, "'{}'", c.escape_default())?,
-                None => write!(f, "end of input")?,
-            }
-        }
+        self.write_reason(&mut f)?;
         writeln!(f, "\n{}", location)?;
 
         let mut last_loc = location;
@@ -77,6 +68,140 @@ impl<I: Deref<Target = str>> ContextualizedError<I> {
 
         Ok(())
     }
+
+    /// Builds a [`ParseDiagnostic`] for this error, so that [`miette`] can show it
+    /// with the offending part of the input highlighted.
+    ///
+    /// `input` must be the complete input given to the parser.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::parser::{ContextualizedError, ParseDiagnostic};
+    /// # use sgmlish::Parser;
+    /// # fn main() {
+    /// let input = "<memo>\n<to name='&who;'>Joe</to>\n</memo>";
+    /// let err: ParseDiagnostic = Parser::new()
+    ///     .parse_with_detailed_errors::<ContextualizedError<_>>(input)
+    ///     .map_err(|err| err.to_diagnostic(&input))
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "parse error in entity: entity 'who' is not defined");
+    /// assert_eq!(err.offset(), 17);
+    /// # }
+    /// ```
+    #[cfg(feature = "miette")]
+    pub fn to_diagnostic(&self, input: &I) -> ParseDiagnostic {
+        use miette::LabeledSpan;
+        use nom::Offset;
+
+        let mut message = String::from("parse error");
+        if input.is_empty() {
+            message.push_str(": input is empty");
+            return ParseDiagnostic {
+                message,
+                source_code: String::new(),
+                labels: vec![LabeledSpan::new(None, 0, 0)],
+            };
+        }
+
+        let span = |substring: &str| {
+            let len = substring.chars().next().map_or(0, char::len_utf8);
+            (input.offset(substring), len)
+        };
+        let location = span(&self.input);
+        let mut context = self
+            .context
+            .iter()
+            .map(|(substring, ctx)| (ctx, span(substring)))
+            .peekable();
+        if let Some((ctx, _)) = context.next_if(|(_, ctxloc)| *ctxloc == location) {
+            message.push_str(" in ");
+            message.push_str(ctx);
+        }
+        let mut reason = String::new();
+        self.write_reason(&mut reason).unwrap();
+        if !reason.is_empty() {
+            message.push(':');
+            message.push_str(&reason);
+        }
+
+        let mut labels = vec![LabeledSpan::new(None, location.0, location.1)];
+        let mut last_loc = location;
+        for (ctx, ctxloc) in context {
+            if ctxloc == last_loc {
+                // Avoid pointing multiple times to the same location
+                continue;
+            }
+            let label = Some([*ctx, " started here"].concat());
+            labels.push(LabeledSpan::new(label, ctxloc.0, ctxloc.1));
+            last_loc = ctxloc;
+        }
+
+        ParseDiagnostic {
+            message,
+            source_code: input.to_string(),
+            labels,
+        }
+    }
+
+    /// Writes what went wrong, if known, starting with a space.
+    fn write_reason<W: fmt::Write>(&self, f: &mut W) -> fmt::Result {
+        if let Some(err) = &self.error {
+            write!(f, " {}", err)?;
+        }
+        if let Some(c) = self.char {
+            write!(f, " expected '{}', got ", c)?;
+            match self.input.chars().next() {
+                Some(' ' | '\t') => write!(f, "whitespace")?,
+                Some('\r' | '\n') => write!(f, "end of line")?,
+                Some(c) => write!(f, "'{}'", c.escape_default())?,
+                None => write!(f, "end of input")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A parse error along with the input it was found in, which implements
+/// [`miette::Diagnostic`] to report the error with the input highlighted.
+///
+/// This is created by [`ContextualizedError::to_diagnostic`].
+#[cfg(feature = "miette")]
+#[derive(Clone, Debug)]
+pub struct ParseDiagnostic {
+    message: String,
+    source_code: String,
+    /// The location of the error, followed by where each enclosing construct started.
+    labels: Vec<miette::LabeledSpan>,
+}
+
+#[cfg(feature = "miette")]
+impl ParseDiagnostic {
+    /// The byte offset in the input where the error was found.
+    pub fn offset(&self) -> usize {
+        self.labels[0].offset()
+    }
+}
+
+#[cfg(feature = "miette")]
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "miette")]
+impl std::error::Error for ParseDiagnostic {}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for ParseDiagnostic {
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.source_code)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        Some(Box::new(self.labels.iter().cloned()))
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -190,6 +315,45 @@ impl<I> nom::error::FromExternalError<I, crate::Error> for ContextualizedError<I
 mod tests {
     use super::*;
 
+    #[test]
+    #[cfg(feature = "miette")]
+    fn test_to_diagnostic() {
+        use miette::Diagnostic;
+
+        let input = "<memo>\n<to name='Joe'\n</memo>";
+        let err = crate::Parser::new()
+            .parse_with_detailed_errors::<ContextualizedError<_>>(input)
+            .unwrap_err()
+            .to_diagnostic(&input);
+        let labels = err
+            .labels()
+            .unwrap()
+            .map(|label| (label.label().map(String::from), label.offset(), label.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(err.to_string(), "parse error: expected '>', got '<'");
+        assert_eq!(err.offset(), 22);
+        assert_eq!(
+            labels,
+            [
+                (None, 22, 1),
+                (Some("start tag started here".into()), 7, 1),
+                (Some("document content started here".into()), 0, 1),
+            ]
+        );
+
+        let mut report = String::new();
+        miette::NarratableReportHandler::new()
+            .render_report(&mut report, &err)
+            .unwrap();
+        assert!(report.contains("<to name='Joe'"), "{}", report);
+
+        let err = crate::Parser::new()
+            .parse_with_detailed_errors::<ContextualizedError<_>>("")
+            .unwrap_err()
+            .to_diagnostic(&"");
+        assert_eq!(err.to_string(), "parse error: input is empty");
+    }
+
     #[test]
     fn test_locate() {
         let input = "hello\nworld\n";
@@ -1,23 +1,36 @@
 //! Higher-level parser combinators that produce [`SgmlEvent`]s.
 
-use std::borrow::Cow;
-use std::iter::{FromIterator, FusedIterator};
-use std::{fmt, mem};
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use core::iter::{FromIterator, FusedIterator};
+use core::marker::PhantomData;
+use core::{fmt, mem};
 
 use nom::branch::alt;
-use nom::combinator::{all_consuming, cut, map, recognize, value};
+use nom::bytes::complete::take_while;
+use nom::combinator::{cut, map, recognize, value};
 use nom::error::{context, ContextError, ErrorKind, FromExternalError, ParseError};
-use nom::multi::{many0, many0_count, many1};
+use nom::multi::{fold_many0, many0, many0_count, many1};
 use nom::sequence::{terminated, tuple};
-use nom::IResult;
+use nom::{Finish, IResult, Offset, Parser as _};
 
 use crate::marked_sections::MarkedSectionStatus;
-use crate::{Error, SgmlEvent};
+use crate::prelude::*;
+use crate::text::is_sgml_whitespace;
+use crate::{dtd, Error, SgmlEvent};
 
 use super::raw::{self, comment_declaration, MarkedSectionEndHandling};
 use super::util::{comments_and_spaces, strip_comments_and_spaces_after, strip_spaces_after};
-use super::{MarkedSectionHandling, ParserConfig};
+use super::{
+    ContentMode, DeclaredEntities, MarkedSectionHandling, ParserConfig, Scope, WhitespaceHandling,
+};
 
+/// Matches an entire document entity, producing all of its events.
+///
+/// This collects the events of [`DocumentEvents`], as used by [`Parser`](super::Parser),
+/// so both accept the same documents and honor the same configuration. Unless
+/// [`allow_trailing_content`](ParserConfig::allow_trailing_content) is set,
+/// the whole input is consumed.
 pub fn document_entity<'a, E>(
     input: &'a str,
     config: &ParserConfig,
@@ -25,25 +38,381 @@ pub fn document_entity<'a, E>(
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
-    all_consuming(map(
-        tuple((
-            comments_and_spaces,
-            |input| prolog(input, config),
-            context(
-                "document content",
-                cut(|input| content(input, config, MarkedSectionEndHandling::TreatAsText)),
-            ),
-            many0(strip_comments_and_spaces_after(|input| {
-                processing_instruction(input, config)
-            })),
-        )),
-        |(_, declarations, content, epilogue)| {
-            declarations
-                .into_iter()
-                .chain(content)
-                .chain(epilogue.into_iter().flatten())
-        },
-    ))(input)
+    let mut events = DocumentEvents::new(input, config);
+    let collected = events
+        .by_ref()
+        .collect::<Result<Vec<_>, E>>()
+        .map_err(nom::Err::Failure)?;
+    Ok((events.remaining_input(), collected.into_iter()))
+}
+
+/// An iterator that parses a document entity lazily, one unit of content at a time.
+///
+/// This is how [`Parser`](super::Parser) reads documents; [`document_entity`]
+/// collects all of its events at once.
+/// Once an error is returned, the iterator is exhausted.
+pub struct DocumentEvents<'a, 'c, E> {
+    input: &'a str,
+    config: &'c ParserConfig,
+    /// The entities declared in the document, once its `DOCTYPE` declaration is read.
+    entities: DeclaredEntities<'a>,
+    state: DocumentState<'a>,
+    pending: EventIter<'a>,
+    /// Elements not yet closed, tracked only when the configuration requires balance.
+    open_elements: OpenElements<'a>,
+    /// The number of events yielded so far.
+    events: usize,
+    /// The number of events yielded before the first one in `pending`.
+    pending_start: usize,
+    /// The number of entity references replaced in the events parsed so far.
+    entity_expansions: usize,
+    /// Whether references were expanded in the last event yielded.
+    last_expanded: bool,
+    error: PhantomData<fn() -> E>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum DocumentState<'a> {
+    Start,
+    Prolog { start: &'a str },
+    Content { start: &'a str, first: bool },
+    Epilogue,
+    Done,
+}
+
+impl<'a, 'c, E> DocumentEvents<'a, 'c, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    pub fn new(input: &'a str, config: &'c ParserConfig) -> Self {
+        let input = if config.strip_bom {
+            input.strip_prefix('\u{feff}').unwrap_or(input)
+        } else {
+            input
+        };
+        DocumentEvents {
+            input,
+            config,
+            entities: DeclaredEntities::default(),
+            state: DocumentState::Start,
+            pending: EventIter::empty(),
+            open_elements: OpenElements::default(),
+            events: 0,
+            pending_start: 0,
+            entity_expansions: 0,
+            last_expanded: false,
+            error: PhantomData,
+        }
+    }
+
+    /// Returns the number of entity references replaced in the events parsed so far.
+    pub(crate) fn entity_expansions(&self) -> usize {
+        self.entity_expansions
+    }
+
+    /// Returns `true` if the last event yielded is text where entity or character
+    /// references (or short references) were expanded.
+    pub(crate) fn last_event_expanded(&self) -> bool {
+        self.last_expanded
+    }
+
+    /// Returns the input that has not been parsed yet.
+    ///
+    /// Once all pending events for the last parsed item have been yielded,
+    /// this is exactly what follows that item, minus any trailing comments.
+    pub(crate) fn remaining_input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Advances the parser by one step, possibly filling `pending` with new events.
+    ///
+    /// Returns `false` once the end of the document is reached.
+    fn step(&mut self) -> Result<bool, E> {
+        match self.state {
+            DocumentState::Start => {
+                if let Some(limit) = self.config.max_input_len {
+                    if self.input.len() > limit {
+                        return Err(E::from_external_error(
+                            self.input,
+                            ErrorKind::TooLarge,
+                            Error::InputTooLong {
+                                len: self.input.len(),
+                                limit,
+                            },
+                        ));
+                    }
+                }
+                if self.config.declares_entities() {
+                    if let Some(subset) = doctype_internal_subset(self.input) {
+                        self.entities.declare(self.config, subset);
+                    }
+                }
+                let (mut rest, _) = comments_and_spaces(self.input).finish()?;
+                if self.config.recognize_xml_declaration {
+                    let config = self.config;
+                    let mut declaration = strip_comments_and_spaces_after(|input| {
+                        xml_declaration::<E>(input, config)
+                    });
+                    if let Ok((after, event)) = declaration.parse(rest) {
+                        rest = after;
+                        self.pending = EventIter::once(event);
+                    }
+                }
+                self.input = rest;
+                self.state = DocumentState::Prolog { start: rest };
+            }
+            DocumentState::Prolog { start } => {
+                let config = &Scope::with_entities(self.config, &self.entities);
+                let mut declaration = strip_comments_and_spaces_after(alt((
+                    |input| markup_declaration(input, config),
+                    |input| marked_section_declaration_in(input, config),
+                    |input| processing_instruction(input, config),
+                )));
+                match declaration.parse(self.input) {
+                    Ok((rest, events)) => {
+                        self.input = rest;
+                        self.pending = events;
+                    }
+                    Err(nom::Err::Error(_)) => {
+                        self.state = DocumentState::Content {
+                            start: self.input,
+                            first: true,
+                        };
+                    }
+                    Err(err) => return Err(E::add_context(start, "prolog", into_inner(err))),
+                }
+            }
+            DocumentState::Content { start, first } => {
+                let config = &Scope::with_entities(self.config, &self.entities);
+                let result = match terminated(
+                    |input| content_item_in(input, config, MarkedSectionEndHandling::TreatAsText),
+                    many0_count(comment_declaration),
+                )(self.input)
+                {
+                    Ok((rest, _)) if rest.len() == self.input.len() => {
+                        Err(nom::Err::Error(E::from_error_kind(rest, ErrorKind::Many1)))
+                    }
+                    result => result,
+                };
+                if let Ok(("", _)) = result {
+                    if config.require_balanced {
+                        if let Some(err) = truncated_tag_error(self.input) {
+                            return Err(E::add_context(start, "document content", err));
+                        }
+                    }
+                }
+                match result {
+                    Ok((rest, events)) => {
+                        self.input = rest;
+                        self.pending = events;
+                        self.state = DocumentState::Content {
+                            start,
+                            first: false,
+                        };
+                    }
+                    Err(nom::Err::Error(_)) if !first => self.state = DocumentState::Epilogue,
+                    Err(nom::Err::Error(err)) => {
+                        let err = E::append(self.input, ErrorKind::Many1, err);
+                        return Err(E::add_context(start, "document content", err));
+                    }
+                    Err(err) => {
+                        return Err(E::add_context(start, "document content", into_inner(err)))
+                    }
+                }
+            }
+            DocumentState::Epilogue => {
+                let config = self.config;
+                let mut instruction =
+                    strip_comments_and_spaces_after(|input| processing_instruction(input, config));
+                match instruction.parse(self.input) {
+                    Ok((rest, events)) => {
+                        self.input = rest;
+                        self.pending = events;
+                    }
+                    Err(nom::Err::Error(_))
+                        if self.input.is_empty() || config.allow_trailing_content =>
+                    {
+                        self.state = DocumentState::Done;
+                    }
+                    Err(nom::Err::Error(_)) => {
+                        return Err(E::from_error_kind(self.input, ErrorKind::Eof));
+                    }
+                    Err(err) => return Err(into_inner(err)),
+                }
+            }
+            DocumentState::Done => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Updates the open elements with the given event, if tracking them.
+    fn track(&mut self, event: &SgmlEvent<'a>) {
+        if self.config.require_balanced {
+            self.open_elements.track(event);
+        }
+    }
+}
+
+/// The names of the elements open at some point of a document, updated event by event.
+///
+/// An end tag closes the innermost open element with its name, along with any
+/// elements still open inside it; an empty end tag (`</>`) closes the innermost
+/// element. End tags for elements that are not open are ignored.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OpenElements<'a>(Vec<Cow<'a, str>>);
+
+impl<'a> OpenElements<'a> {
+    /// Updates the open elements with the given event.
+    pub(crate) fn track(&mut self, event: &SgmlEvent<'a>) {
+        match event {
+            SgmlEvent::OpenStartTag { name } => self.0.push(name.clone()),
+            SgmlEvent::XmlCloseEmptyElement => {
+                self.0.pop();
+            }
+            SgmlEvent::EndTag { name } => {
+                let position = self
+                    .0
+                    .iter()
+                    .rposition(|open| name.is_empty() || open == name);
+                if let Some(position) = position {
+                    self.0.truncate(position);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the number of open elements.
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no elements are open.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Forgets all open elements.
+    pub(crate) fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Returns the names of the open elements, outermost first, and forgets them.
+    fn take_names(&mut self) -> Vec<String> {
+        mem::take(&mut self.0)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// Reports input ending right after a tag open delimiter (`<` or `</`),
+/// which would otherwise be read as text.
+fn truncated_tag_error<'a, E>(input: &'a str) -> Option<E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str>,
+{
+    let end = &input[input.len()..];
+    if input.ends_with("</") {
+        let start = &input[input.len() - 2..];
+        Some(E::add_context(start, "end tag", E::from_char(end, '>')))
+    } else if input.ends_with('<') {
+        let start = &input[input.len() - 1..];
+        Some(E::add_context(start, "start tag", E::from_char(end, '>')))
+    } else {
+        None
+    }
+}
+
+impl<'a, 'c, E> Iterator for DocumentEvents<'a, 'c, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    type Item = Result<SgmlEvent<'a>, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.next() {
+                if self.config.max_events == Some(self.events) {
+                    self.state = DocumentState::Done;
+                    self.pending = EventIter::empty();
+                    self.open_elements.clear();
+                    return Some(Err(E::from_external_error(
+                        self.input,
+                        ErrorKind::TooLarge,
+                        Error::TooManyEvents { limit: self.events },
+                    )));
+                }
+                self.last_expanded = self.pending.is_expanded(self.events - self.pending_start);
+                self.events += 1;
+                self.track(&event);
+                return Some(Ok(event));
+            }
+            match self.step() {
+                Ok(true) => {
+                    self.pending_start = self.events;
+                    self.entity_expansions += mem::take(&mut self.pending.expansions);
+                }
+                Ok(false) if self.open_elements.is_empty() => return None,
+                Ok(false) => {
+                    let names = self.open_elements.take_names();
+                    return Some(Err(E::from_external_error(
+                        self.input,
+                        ErrorKind::Eof,
+                        Error::UnclosedElements(names),
+                    )));
+                }
+                Err(err) => {
+                    self.state = DocumentState::Done;
+                    self.open_elements.clear();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 'c, E> FusedIterator for DocumentEvents<'a, 'c, E> where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>
+{
+}
+
+impl<E> fmt::Debug for DocumentEvents<'_, '_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DocumentEvents")
+            .field("input", &self.input)
+            .field("state", &self.state)
+            .field("pending", &self.pending)
+            .field("open_elements", &self.open_elements)
+            .finish()
+    }
+}
+
+/// Unwraps the error from a complete parser.
+fn into_inner<E>(err: nom::Err<E>) -> E {
+    match err {
+        nom::Err::Error(err) | nom::Err::Failure(err) => err,
+        nom::Err::Incomplete(_) => unreachable!("complete parsers do not return Incomplete"),
+    }
+}
+
+/// Looks for a `DOCTYPE` declaration at the start of the document, skipping
+/// other markup declarations, comments and processing instructions,
+/// and outputs its internal subset, if any.
+fn doctype_internal_subset(input: &str) -> Option<&str> {
+    type E<'a> = nom::error::Error<&'a str>;
+    let (mut input, _) = comments_and_spaces::<E>(input).ok()?;
+    loop {
+        if let Ok((rest, (keyword, body))) = raw::markup_declaration::<E>(input) {
+            if keyword.eq_ignore_ascii_case("DOCTYPE") {
+                return dtd::internal_subset(body);
+            }
+            input = rest;
+        } else {
+            input = raw::processing_instruction::<E>(input).ok()?.0;
+        }
+        input = comments_and_spaces::<E>(input).ok()?.0;
+    }
 }
 
 pub fn prolog<'a, E>(
@@ -71,16 +440,91 @@ pub fn markup_declaration<'a, E>(
     config: &ParserConfig,
 ) -> IResult<&'a str, EventIter<'a>, E>
 where
-    E: ParseError<&'a str> + ContextError<&'a str>,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
-    map(raw::markup_declaration, |(keyword, body)| {
-        EventIter::cond(!config.ignore_markup_declarations, || {
-            SgmlEvent::MarkupDeclaration {
-                keyword: keyword.into(),
-                body: body.into(),
-            }
-        })
-    })(input)
+    let (rest, (keyword, body)) = raw::markup_declaration(input)?;
+    if config.strict_markup_declarations {
+        check_markup_declaration_keywords(keyword, body)?;
+    }
+    if config.ignore_markup_declarations {
+        return Ok((rest, EventIter::empty()));
+    }
+    let subset = if config.split_internal_subset && keyword.eq_ignore_ascii_case("DOCTYPE") {
+        dtd::internal_subset(body)
+    } else {
+        None
+    };
+    let events = match subset {
+        Some(subset) => split_internal_subset(keyword, body, subset),
+        None => EventIter::once(SgmlEvent::MarkupDeclaration {
+            keyword: keyword.into(),
+            body: body.into(),
+        }),
+    };
+    Ok((rest, events))
+}
+
+/// Produces an event for a `DOCTYPE` declaration without its internal subset,
+/// followed by events for the markup declarations in the subset.
+fn split_internal_subset<'a>(keyword: &'a str, body: &'a str, subset: &'a str) -> EventIter<'a> {
+    // Skip the brackets surrounding the subset
+    let start = body.offset(subset) - 1;
+    let end = start + subset.len() + 2;
+    let before = body[..start].trim_end_matches(is_sgml_whitespace);
+    let after = body[end..].trim_matches(is_sgml_whitespace);
+    let body = if after.is_empty() {
+        Cow::Borrowed(before)
+    } else {
+        let mut body = before.to_owned();
+        body.push(' ');
+        body.push_str(after);
+        Cow::Owned(body)
+    };
+
+    let doctype = SgmlEvent::MarkupDeclaration {
+        keyword: keyword.into(),
+        body,
+    };
+    let declarations = dtd::markup_declarations(subset)
+        .into_iter()
+        .map(|(keyword, body)| SgmlEvent::MarkupDeclaration {
+            keyword: keyword.into(),
+            body: body.into(),
+        });
+    Some(doctype).into_iter().chain(declarations).collect()
+}
+
+/// Rejects markup declarations, and declarations in the internal subset
+/// of a `DOCTYPE` declaration, with unrecognized keywords.
+fn check_markup_declaration_keywords<'a, E>(
+    keyword: &'a str,
+    body: &'a str,
+) -> Result<(), nom::Err<E>>
+where
+    E: FromExternalError<&'a str, Error>,
+{
+    const KEYWORDS: &[&str] = &[
+        "DOCTYPE", "ENTITY", "ELEMENT", "ATTLIST", "NOTATION", "SGML",
+    ];
+
+    let mut keywords = vec![keyword];
+    if keyword.eq_ignore_ascii_case("DOCTYPE") {
+        let subset = dtd::internal_subset(body).map_or_else(Vec::new, dtd::markup_declarations);
+        keywords.extend(subset.into_iter().map(|(keyword, _)| keyword));
+    }
+    let unknown = keywords.into_iter().find(|keyword| {
+        !KEYWORDS
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(keyword))
+    });
+    match unknown {
+        Some(keyword) => Err(nom::Err::Failure(E::from_external_error(
+            keyword,
+            ErrorKind::Tag,
+            Error::UnknownMarkupDeclaration(keyword.to_owned()),
+        ))),
+        None => Ok(()),
+    }
 }
 
 /// Matches an entire marked section declaration and
@@ -89,11 +533,26 @@ pub fn marked_section_declaration<'a, E>(
     input: &'a str,
     config: &ParserConfig,
 ) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    marked_section_declaration_in(input, &Scope::new(config))
+}
+
+/// Matches a marked section declaration like [`marked_section_declaration`],
+/// expanding the entities declared in the document being parsed.
+fn marked_section_declaration_in<'a, E>(
+    input: &'a str,
+    config: &Scope<'a, '_>,
+) -> IResult<&'a str, EventIter<'a>, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
     context("marked section declaration", |input| {
         let (rest, raw_status_keywords) = raw::marked_section_start_and_keywords(input)?;
+        if config.xml_marked_sections {
+            return xml_marked_section(input, rest, raw_status_keywords, config);
+        }
         let status_keywords = config.parse_markup_declaration_text(raw_status_keywords)?;
 
         let status = match config
@@ -116,10 +575,33 @@ where
             }
         };
 
-        marked_section_body(rest, status_keywords, status, config)
+        marked_section_body_in(rest, status_keywords, status, config)
     })(input)
 }
 
+/// Accepts only the literal `<![CDATA[` start, per [`ParserConfig::xml_marked_sections`].
+fn xml_marked_section<'a, E>(
+    input: &'a str,
+    rest: &'a str,
+    raw_status_keywords: &'a str,
+    config: &Scope<'a, '_>,
+) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    const XML_CDATA_START: &str = "<![CDATA[";
+    if !input.starts_with(XML_CDATA_START) {
+        // Report the keywords as written, including any whitespace
+        let keywords = &input[raw::MARKED_SECTION_START.len()..input.len() - rest.len() - 1];
+        return Err(nom::Err::Failure(E::from_external_error(
+            raw_status_keywords,
+            ErrorKind::Tag,
+            Error::InvalidMarkedSectionKeyword(keywords.to_owned()),
+        )));
+    }
+    marked_section_body_in(rest, "CDATA".into(), MarkedSectionStatus::CData, config)
+}
+
 /// Matches the marked section + end inside a marked section declaration and
 /// produces events according to [`ParserConfig::marked_section_handling`].
 pub fn marked_section_body<'a, E>(
@@ -128,6 +610,20 @@ pub fn marked_section_body<'a, E>(
     status: MarkedSectionStatus,
     config: &ParserConfig,
 ) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    marked_section_body_in(input, status_keywords, status, &Scope::new(config))
+}
+
+/// Matches the rest of a marked section like [`marked_section_body`],
+/// expanding the entities declared in the document being parsed.
+fn marked_section_body_in<'a, E>(
+    input: &'a str,
+    status_keywords: Cow<'a, str>,
+    status: MarkedSectionStatus,
+    config: &Scope<'a, '_>,
+) -> IResult<&'a str, EventIter<'a>, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
@@ -141,7 +637,7 @@ where
                 MarkedSectionStatus::RcData => raw::marked_section_body_character_data(input),
                 MarkedSectionStatus::Include => terminated(
                     recognize(|input| {
-                        content(input, config, MarkedSectionEndHandling::StopParsing)
+                        content_events(input, config, MarkedSectionEndHandling::StopParsing)
                     }),
                     raw::marked_section_end,
                 )(input),
@@ -163,18 +659,18 @@ where
             })(input),
             MarkedSectionStatus::RcData => {
                 let (rest, content) = raw::marked_section_body_character_data(input)?;
+                let mut expansions = 0;
+                let text = config.parse_rcdata_counting(config.trim(content), &mut expansions)?;
+                let expanded = was_expanded(&text, expansions);
                 Ok((
                     rest,
-                    EventIter::once(SgmlEvent::Character(
-                        config.parse_rcdata(config.trim(content))?,
-                    )),
+                    EventIter::once(SgmlEvent::Character(config.normalize_newlines(text)))
+                        .with_expansions(expansions)
+                        .with_expanded(0, expanded),
                 ))
             }
             MarkedSectionStatus::Include => terminated(
-                map(
-                    |input| content(input, config, MarkedSectionEndHandling::StopParsing),
-                    EventIter::from_iter,
-                ),
+                |input| content_events(input, config, MarkedSectionEndHandling::StopParsing),
                 raw::marked_section_body_character_data,
             )(input),
         },
@@ -190,11 +686,96 @@ where
 {
     map(raw::processing_instruction, |s| {
         EventIter::cond(!config.ignore_processing_instructions, || {
-            SgmlEvent::ProcessingInstruction(Cow::from(s))
+            if config.structured_processing_instructions {
+                let (target, data) = split_processing_instruction(s, config);
+                SgmlEvent::StructuredProcessingInstruction {
+                    target: target.into(),
+                    data: data.into(),
+                }
+            } else {
+                SgmlEvent::ProcessingInstruction(Cow::from(s))
+            }
         })
     })(input)
 }
 
+/// Matches an XML declaration (`<?xml version="1.0"?>`).
+///
+/// Fails with a recoverable error for any other processing instruction,
+/// including `xml` instructions with malformed or unknown pseudo-attributes,
+/// so they can be handled as regular processing instructions.
+pub fn xml_declaration<'a, E>(
+    input: &'a str,
+    config: &ParserConfig,
+) -> IResult<&'a str, SgmlEvent<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str>,
+{
+    // Unterminated instructions are left for the prolog to report
+    let (rest, pi) = raw::processing_instruction::<E>(input).map_err(|err| match err {
+        nom::Err::Failure(err) => nom::Err::Error(err),
+        err => err,
+    })?;
+    match split_processing_instruction(pi, config) {
+        ("xml", data) => match xml_declaration_fields(data) {
+            Some(event) => Ok((rest, event)),
+            None => Err(nom::Err::Error(E::from_error_kind(
+                input,
+                ErrorKind::Verify,
+            ))),
+        },
+        _ => Err(nom::Err::Error(E::from_error_kind(input, ErrorKind::Tag))),
+    }
+}
+
+/// Parses the pseudo-attributes of an XML declaration, which must appear in order:
+/// `version` (required), `encoding` and `standalone` (`yes` or `no`).
+fn xml_declaration_fields(data: &str) -> Option<SgmlEvent<'_>> {
+    type E<'a> = nom::error::Error<&'a str>;
+    let (rest, attributes) = many0(strip_spaces_after(raw::attribute::<E>))(data).ok()?;
+    if !rest.is_empty() {
+        return None;
+    }
+    let mut attributes = attributes.into_iter().peekable();
+    let mut take = |name| match attributes.next_if(|&(key, _)| key == name) {
+        Some((_, Some(value))) => Ok(Some(value)),
+        Some((_, None)) => Err(()),
+        None => Ok(None),
+    };
+    let version = take("version").ok()??;
+    let encoding = take("encoding").ok()?;
+    let standalone = match take("standalone").ok()? {
+        Some("yes") => Some(true),
+        Some("no") => Some(false),
+        Some(_) => return None,
+        None => None,
+    };
+    if attributes.next().is_some() {
+        return None;
+    }
+    Some(SgmlEvent::XmlDeclaration {
+        version: version.into(),
+        encoding: encoding.map(Cow::from),
+        standalone,
+    })
+}
+
+/// Splits a full processing instruction (`<?target data?>`) into target and data.
+///
+/// The split happens at the first whitespace run; a trailing `?` is removed.
+fn split_processing_instruction<'a>(pi: &'a str, config: &ParserConfig) -> (&'a str, &'a str) {
+    let content = pi.strip_prefix("<?").unwrap_or(pi);
+    let content = content.strip_suffix('>').unwrap_or(content);
+    let content = content.strip_suffix('?').unwrap_or(content);
+    match content.find(|c| config.is_whitespace(c)) {
+        Some(pos) => (
+            &content[..pos],
+            content[pos..].trim_start_matches(|c| config.is_whitespace(c)),
+        ),
+        None => (content, ""),
+    }
+}
+
 /// Matches the content main content area of a SGML document --- one or more [`content_item`]s.
 pub fn content<'a, E>(
     input: &'a str,
@@ -213,21 +794,52 @@ where
     )(input)
 }
 
+/// Matches content like [`content`], gathering all events into a single [`EventIter`].
+fn content_events<'a, E>(
+    input: &'a str,
+    config: &Scope<'a, '_>,
+    mse: MarkedSectionEndHandling,
+) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    map(
+        many1(terminated(
+            |input| content_item_in(input, config, mse),
+            many0_count(comment_declaration),
+        )),
+        EventIter::concat,
+    )(input)
+}
+
 /// Matches a single unit of content --- a tag, text data, processing instruction, or section declaration.
 pub fn content_item<'a, E>(
     input: &'a str,
     config: &ParserConfig,
     mse: MarkedSectionEndHandling,
 ) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    content_item_in(input, &Scope::new(config), mse)
+}
+
+/// Matches a single unit of content like [`content_item`],
+/// expanding the entities declared in the document being parsed.
+fn content_item_in<'a, E>(
+    input: &'a str,
+    config: &Scope<'a, '_>,
+    mse: MarkedSectionEndHandling,
+) -> IResult<&'a str, EventIter<'a>, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
     alt((
-        |input| text(input, config, mse),
-        |input| start_tag(input, config),
+        |input| text_in(input, config, mse),
+        |input| start_tag_in(input, config),
         map(|input| end_tag(input, config), EventIter::once),
         |input| processing_instruction(input, config),
-        |input| marked_section_declaration(input, config),
+        |input| marked_section_declaration_in(input, config),
         // When all else fails, sinalize we expected at least opening a tag
         |input| Err(nom::Err::Error(E::from_char(input, '<'))),
     ))(input)
@@ -235,35 +847,235 @@ where
 
 /// Matches an entire start tag, and outputs a sequence of events describing it.
 pub fn start_tag<'a, E>(input: &'a str, config: &ParserConfig) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    start_tag_in(input, &Scope::new(config))
+}
+
+/// Matches an entire start tag like [`start_tag`],
+/// expanding the entities declared in the document being parsed.
+fn start_tag_in<'a, E>(input: &'a str, config: &Scope<'a, '_>) -> IResult<&'a str, EventIter<'a>, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
     context(
         "start tag",
         alt((
-            map(
-                tuple((
-                    strip_spaces_after(|input| open_start_tag(input, config)),
-                    many0(strip_spaces_after(|input| attribute(input, config))),
+            |input| {
+                let (after_open, open) = terminated(
+                    |input| open_start_tag(input, config),
+                    |input| separators(input, config),
+                )(input)?;
+                let mut count = 0;
+                let mut expansions = 0;
+                let (rest, (attributes, close)) = tuple((
+                    // Most tags have no attributes, so avoid `many0`'s upfront allocation
+                    fold_many0(
+                        terminated(
+                            |input| {
+                                let (rest, attribute) =
+                                    attribute_counting(input, config, &mut expansions)?;
+                                count += 1;
+                                check_attribute_count(input, config, &open, count)?;
+                                Ok((rest, attribute))
+                            },
+                            |input| separators(input, config),
+                        ),
+                        EventVec::new,
+                        |mut attributes, attribute| {
+                            attributes.push(attribute);
+                            attributes
+                        },
+                    ),
                     cut(alt((xml_close_empty_element, close_start_tag))),
-                )),
-                EventIter::start_tag,
-            ),
+                ))(after_open)?;
+                let attributes = expand_minimized_attributes(config, &open, attributes);
+                check_attributes(input, config, &open, &attributes)?;
+                if close == SgmlEvent::CloseStartTag {
+                    let (rest, content) =
+                        element_content(rest, config, &open, &attributes, &mut expansions)?;
+                    if let Some((content, expanded)) = content {
+                        let mut middle = attributes;
+                        middle.push(close);
+                        let position = 1 + middle.len();
+                        let events = EventIter::start_tag((open, middle, content));
+                        return Ok((
+                            rest,
+                            events
+                                .with_expansions(expansions)
+                                .with_expanded(position, expanded),
+                        ));
+                    }
+                }
+                let events = EventIter::start_tag((open, attributes, close));
+                Ok((rest, events.with_expansions(expansions)))
+            },
             empty_start_tag,
         )),
     )(input)
 }
 
+/// Replaces attributes written as a bare value (`<ul compact>`) with their full form,
+/// when the configuration knows which attribute the value belongs to.
+fn expand_minimized_attributes<'a>(
+    config: &ParserConfig,
+    open: &SgmlEvent<'a>,
+    mut attributes: EventVec<'a>,
+) -> EventVec<'a> {
+    let element = open.tag_name().unwrap_or_default();
+    for attribute in &mut attributes {
+        if let SgmlEvent::Attribute { name, value } = attribute {
+            if value.is_none() {
+                if let Some(expanded) = config.minimized_attribute_name(element, name) {
+                    *value = Some(mem::replace(name, expanded));
+                }
+            }
+        }
+    }
+    attributes
+}
+
+/// Matches the content of an element as character data, when the configured
+/// [`ContentMode`] says it should not be parsed as markup.
+///
+/// Along with the text, returns whether references were expanded in it.
+fn element_content<'a, E>(
+    input: &'a str,
+    config: &Scope<'a, '_>,
+    open: &SgmlEvent<'a>,
+    attributes: &[SgmlEvent<'a>],
+    expansions: &mut usize,
+) -> IResult<&'a str, Option<(SgmlEvent<'a>, bool)>, E>
+where
+    E: ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    let name = open.tag_name().unwrap_or_default();
+    let mode = if config.content_mode_fn.is_some() {
+        let attributes = attributes
+            .iter()
+            .filter_map(SgmlEvent::as_attribute)
+            .collect::<Vec<_>>();
+        config.content_mode(name, &attributes)
+    } else {
+        ContentMode::Normal
+    };
+
+    let end = match mode {
+        ContentMode::Normal => return Ok((input, None)),
+        ContentMode::CData | ContentMode::RcData => find_end_tag(input, name),
+    };
+    let (content, rest) = input.split_at(end);
+    let content = config.trim(content);
+    if content.is_empty() {
+        return Ok((rest, None));
+    }
+    let (content, expanded) = match mode {
+        ContentMode::RcData => {
+            let mut count = 0;
+            let text = config.parse_rcdata_counting(content, &mut count)?;
+            *expansions += count;
+            let expanded = was_expanded(&text, count);
+            (config.normalize_newlines(text), expanded)
+        }
+        _ => (content.into(), false),
+    };
+    Ok((rest, Some((SgmlEvent::Character(content), expanded))))
+}
+
+/// Finds the position of the end tag for the given element (or of an empty end tag),
+/// or the end of the input if there is none.
+fn find_end_tag(input: &str, name: &str) -> usize {
+    input
+        .match_indices("</")
+        .map(|(position, _)| position)
+        .find(|&position| {
+            let after = &input[position + 2..];
+            if after.starts_with('>') {
+                return true;
+            }
+            match after.get(..name.len()) {
+                Some(candidate) => {
+                    candidate.eq_ignore_ascii_case(name)
+                        && !after[name.len()..].starts_with(raw::is_name_char)
+                }
+                None => false,
+            }
+        })
+        .unwrap_or(input.len())
+}
+
+/// Fails once a start tag has more attributes than the configured limit,
+/// given the number of attributes read so far.
+fn check_attribute_count<'a, E>(
+    input: &'a str,
+    config: &ParserConfig,
+    open: &SgmlEvent<'a>,
+    count: usize,
+) -> Result<(), nom::Err<E>>
+where
+    E: FromExternalError<&'a str, Error>,
+{
+    match config.max_attributes_per_element {
+        Some(limit) if count > limit => Err(nom::Err::Failure(E::from_external_error(
+            input,
+            ErrorKind::TooLarge,
+            Error::TooManyAttributes {
+                element: open.tag_name().unwrap_or_default().to_owned(),
+                limit,
+            },
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Validates the attributes of a start tag against the configured rules.
+fn check_attributes<'a, E>(
+    input: &'a str,
+    config: &ParserConfig,
+    open: &SgmlEvent<'a>,
+    attributes: &[SgmlEvent<'a>],
+) -> Result<(), nom::Err<E>>
+where
+    E: FromExternalError<&'a str, Error>,
+{
+    if config.reject_duplicate_attributes {
+        let mut seen = BTreeSet::new();
+        for (name, _) in attributes.iter().filter_map(SgmlEvent::as_attribute) {
+            if !seen.insert(name) {
+                return Err(nom::Err::Failure(E::from_external_error(
+                    input,
+                    ErrorKind::Verify,
+                    Error::DuplicateAttribute {
+                        element: open.tag_name().unwrap_or_default().to_owned(),
+                        attribute: name.to_string(),
+                    },
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Matches zero or more whitespace characters, as defined by [`ParserConfig::is_whitespace`].
+pub fn separators<'a, E>(input: &'a str, config: &ParserConfig) -> IResult<&'a str, &'a str, E>
+where
+    E: ParseError<&'a str>,
+{
+    take_while(|c| config.is_whitespace(c))(input)
+}
+
 pub fn open_start_tag<'a, E>(
     input: &'a str,
     config: &ParserConfig,
 ) -> IResult<&'a str, SgmlEvent<'a>, E>
 where
-    E: ParseError<&'a str> + ContextError<&'a str>,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
-    map(raw::open_start_tag, |name| SgmlEvent::OpenStartTag {
-        name: config.name_normalization.normalize(name.into()),
-    })(input)
+    let (rest, name) = raw::open_start_tag(input)?;
+    let name = config.name_normalization.normalize(name.into());
+    check_tag_name(input, config, &name)?;
+    Ok((rest, SgmlEvent::OpenStartTag { name }))
 }
 
 pub fn close_start_tag<'a, E>(input: &'a str) -> IResult<&'a str, SgmlEvent<'a>, E>
@@ -290,7 +1102,7 @@ where
     map(raw::empty_start_tag, |_| {
         EventIter::start_tag((
             SgmlEvent::OpenStartTag { name: "".into() },
-            vec![],
+            EventVec::new(),
             SgmlEvent::CloseStartTag,
         ))
     })(input)
@@ -300,53 +1112,147 @@ pub fn attribute<'a, E>(input: &'a str, config: &ParserConfig) -> IResult<&'a st
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
-    map(
-        |input| {
-            raw::attribute_parse_value(input, |value, quoted| {
-                if quoted {
-                    config.parse_rcdata(value)
-                } else {
-                    Ok(value.into())
-                }
-            })
-        },
-        |(name, value)| SgmlEvent::Attribute {
-            name: config.name_normalization.normalize(name.into()),
-            value,
-        },
-    )(input)
-}
-
-fn end_tag<'a, E>(input: &'a str, config: &ParserConfig) -> IResult<&'a str, SgmlEvent<'a>, E>
-where
-    E: ParseError<&'a str> + ContextError<&'a str>,
-{
-    map(raw::end_tag, |name| SgmlEvent::EndTag {
-        name: config
-            .name_normalization
-            .normalize(name.unwrap_or_default().into()),
-    })(input)
+    attribute_counting(input, &Scope::new(config), &mut 0)
 }
 
-pub fn text<'a, E>(
+/// Matches an attribute like [`attribute`], expanding the entities declared in the document
+/// being parsed, and adding the number of entity references replaced in its value to `expansions`.
+fn attribute_counting<'a, E>(
     input: &'a str,
-    config: &ParserConfig,
-    mse: MarkedSectionEndHandling,
-) -> IResult<&'a str, EventIter<'a>, E>
+    config: &Scope<'a, '_>,
+    expansions: &mut usize,
+) -> IResult<&'a str, SgmlEvent<'a>, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
 {
-    let (rest, text) = raw::text(input, mse)?;
+    let (rest, (name, value)) = raw::attribute_parse_value(input, |value, quoted| {
+        if quoted {
+            // The value is a slice of the input, right after the opening quote
+            let quote = input[..input.offset(value)].chars().next_back();
+            config
+                .parse_rcdata_counting(value, expansions)
+                .map(|value| (value, quote))
+        } else {
+            Ok((value.into(), None))
+        }
+    })?;
+
+    if value.is_none() && !config.allow_minimized_attributes {
+        return Err(nom::Err::Failure(E::from_external_error(
+            input,
+            ErrorKind::Verify,
+            Error::MinimizedAttribute(name.to_owned()),
+        )));
+    }
+
+    let name = config.name_normalization.normalize(name.into());
+    let event = match value {
+        Some((value, None)) if config.unquoted_attribute_events => {
+            SgmlEvent::UnquotedAttribute { name, value }
+        }
+        Some((value, Some(quote))) if config.quoted_attribute_events => {
+            SgmlEvent::QuotedAttribute { name, value, quote }
+        }
+        value => SgmlEvent::Attribute {
+            name,
+            value: value.map(|(value, _)| value),
+        },
+    };
+    Ok((rest, event))
+}
+
+fn end_tag<'a, E>(input: &'a str, config: &ParserConfig) -> IResult<&'a str, SgmlEvent<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    let (rest, name) = raw::end_tag(input)?;
+    let name = config
+        .name_normalization
+        .normalize(name.unwrap_or_default().into());
+    check_tag_name(input, config, &name)?;
+    Ok((rest, SgmlEvent::EndTag { name }))
+}
+
+/// Rejects tag names refused by the configured validation closure.
+/// Empty names, as in `<>` and `</>`, are not validated.
+fn check_tag_name<'a, E>(
+    input: &'a str,
+    config: &ParserConfig,
+    name: &str,
+) -> Result<(), nom::Err<E>>
+where
+    E: FromExternalError<&'a str, Error>,
+{
+    if name.is_empty() || config.is_valid_name(name) {
+        return Ok(());
+    }
+    Err(nom::Err::Failure(E::from_external_error(
+        input,
+        ErrorKind::Verify,
+        Error::InvalidTagName(name.to_owned()),
+    )))
+}
+
+pub fn text<'a, E>(
+    input: &'a str,
+    config: &ParserConfig,
+    mse: MarkedSectionEndHandling,
+) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    text_in(input, &Scope::new(config), mse)
+}
+
+/// Matches text like [`text`], expanding the entities declared in the document being parsed.
+fn text_in<'a, E>(
+    input: &'a str,
+    config: &Scope<'a, '_>,
+    mse: MarkedSectionEndHandling,
+) -> IResult<&'a str, EventIter<'a>, E>
+where
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, Error>,
+{
+    let (rest, text) = raw::text(input, mse)?;
+    if config.whitespace_handling == WhitespaceHandling::Separate
+        && text.chars().all(|c| config.is_whitespace(c))
+    {
+        let text = config.normalize_newlines(text.into());
+        return Ok((rest, EventIter::once(SgmlEvent::Whitespace(text))));
+    }
     let s = config.trim(text);
     if s.is_empty() {
         return Ok((rest, EventIter::empty()));
     }
+    let mut expansions = 0;
+    let text = config.parse_text_counting(s, &mut expansions)?;
+    let expanded = was_expanded(&text, expansions);
     Ok((
         rest,
-        EventIter::once(SgmlEvent::Character(config.parse_rcdata(s)?)),
+        EventIter::once(SgmlEvent::Character(config.normalize_newlines(text)))
+            .with_expansions(expansions)
+            .with_expanded(0, expanded),
     ))
 }
 
+/// Tests whether text returned by [`ParserConfig::parse_text`] or
+/// [`ParserConfig::parse_rcdata`] had any references expanded, given the number
+/// of entity references counted while parsing it.
+///
+/// Character references are not counted, but text is only copied when
+/// some reference was replaced.
+#[allow(clippy::ptr_arg)] // Whether the text is borrowed matters here
+fn was_expanded(text: &Cow<str>, expansions: usize) -> bool {
+    expansions > 0 || matches!(text, Cow::Owned(_))
+}
+
+/// Storage for the events between the first and last ones of an [`EventIter`],
+/// such as the attributes of a start tag.
+#[cfg(feature = "smallvec")]
+type EventVec<'a> = smallvec::SmallVec<[SgmlEvent<'a>; 4]>;
+#[cfg(not(feature = "smallvec"))]
+type EventVec<'a> = Vec<SgmlEvent<'a>>;
+
 /// An iterator over a sequence of events.
 ///
 /// This struct exists to minimize the number of allocations during the
@@ -354,27 +1260,36 @@ where
 #[derive(PartialEq)]
 pub struct EventIter<'a> {
     start: Option<SgmlEvent<'a>>,
-    middle: Vec<SgmlEvent<'a>>,
+    middle: EventVec<'a>,
     end: Option<SgmlEvent<'a>>,
     middle_next: usize,
+    /// The number of entity references replaced to produce these events.
+    expansions: usize,
+    /// The positions of the text events where references were expanded,
+    /// counting from the first event, in ascending order.
+    expanded: Vec<usize>,
 }
 
 impl<'a> EventIter<'a> {
-    const fn empty() -> Self {
+    fn empty() -> Self {
         EventIter {
             start: None,
-            middle: Vec::new(),
+            middle: EventVec::new(),
             end: None,
             middle_next: 0,
+            expansions: 0,
+            expanded: Vec::new(),
         }
     }
 
     fn once(event: SgmlEvent<'a>) -> Self {
         EventIter {
             start: Some(event),
-            middle: Vec::new(),
+            middle: EventVec::new(),
             end: None,
             middle_next: 0,
+            expansions: 0,
+            expanded: Vec::new(),
         }
     }
 
@@ -386,13 +1301,55 @@ impl<'a> EventIter<'a> {
         }
     }
 
-    fn start_tag((start, middle, end): (SgmlEvent<'a>, Vec<SgmlEvent<'a>>, SgmlEvent<'a>)) -> Self {
+    fn start_tag((start, middle, end): (SgmlEvent<'a>, EventVec<'a>, SgmlEvent<'a>)) -> Self {
         EventIter {
             start: Some(start),
             middle,
             end: Some(end),
             middle_next: 0,
+            expansions: 0,
+            expanded: Vec::new(),
+        }
+    }
+
+    /// Records the number of entity references replaced to produce these events.
+    fn with_expansions(mut self, expansions: usize) -> Self {
+        self.expansions = expansions;
+        self
+    }
+
+    /// Records that references were expanded in the text event at the given position,
+    /// which must come after any position recorded before.
+    fn with_expanded(mut self, position: usize, expanded: bool) -> Self {
+        if expanded {
+            self.expanded.push(position);
         }
+        self
+    }
+
+    /// Tests whether references were expanded in the event at the given position.
+    fn is_expanded(&self, position: usize) -> bool {
+        self.expanded.binary_search(&position).is_ok()
+    }
+
+    /// Joins the given iterators, which must not have been advanced yet,
+    /// adding up their expansions.
+    fn concat(items: Vec<EventIter<'a>>) -> Self {
+        let mut expansions = 0;
+        let mut expanded = Vec::new();
+        let mut offset = 0;
+        for item in &items {
+            expansions += item.expansions;
+            expanded.extend(item.expanded.iter().map(|position| offset + position));
+            offset += item.len();
+        }
+        let mut events = items
+            .into_iter()
+            .flatten()
+            .collect::<EventIter>()
+            .with_expansions(expansions);
+        events.expanded = expanded;
+        events
     }
 }
 
@@ -437,9 +1394,11 @@ impl<'a> FromIterator<SgmlEvent<'a>> for EventIter<'a> {
     {
         EventIter {
             start: None,
-            middle: Vec::from_iter(iter),
+            middle: EventVec::from_iter(iter),
             end: None,
             middle_next: 0,
+            expansions: 0,
+            expanded: Vec::new(),
         }
     }
 }
@@ -487,8 +1446,7 @@ mod tests {
                 </BODY>
             </HTML>
         "#;
-        let (rest, mut events) = document_entity::<E>(SAMPLE, &Default::default()).unwrap();
-        assert!(rest.is_empty(), "rest: {:?}", rest);
+        let mut events = Parser::new().parse(SAMPLE).unwrap().into_iter();
 
         assert_eq!(
             events.next(),
@@ -566,6 +1524,125 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_document_entity_content_mode() {
+        let parser = Parser::builder()
+            .expand_entities(|entity| match entity {
+                "amp" => Some("&"),
+                _ => None,
+            })
+            .on_start_tag(|name, attributes| match (name, attributes) {
+                ("pre", [("mode", Some("cdata"))]) => ContentMode::CData,
+                ("pre", [("mode", Some("rcdata"))]) => ContentMode::RcData,
+                _ => ContentMode::Normal,
+            })
+            .build();
+        let parse = |input| parser.parse(input).unwrap().into_vec();
+
+        assert_eq!(
+            parse("<pre><b>x &amp; y</b></pre>"),
+            parse("<pre><b>x & y</b></pre>")
+        );
+        assert_eq!(
+            parse("<pre mode=cdata><b>x &amp; y</b></PRE><pre mode=cdata></pre>"),
+            vec![
+                OpenStartTag { name: "pre".into() },
+                Attribute {
+                    name: "mode".into(),
+                    value: Some("cdata".into()),
+                },
+                CloseStartTag,
+                Character("<b>x &amp; y</b>".into()),
+                EndTag { name: "PRE".into() },
+                OpenStartTag { name: "pre".into() },
+                Attribute {
+                    name: "mode".into(),
+                    value: Some("cdata".into()),
+                },
+                CloseStartTag,
+                EndTag { name: "pre".into() },
+            ]
+        );
+        assert_eq!(
+            parse("<pre mode=rcdata></prefix>&amp;</></pre>"),
+            vec![
+                OpenStartTag { name: "pre".into() },
+                Attribute {
+                    name: "mode".into(),
+                    value: Some("rcdata".into()),
+                },
+                CloseStartTag,
+                Character("</prefix>&".into()),
+                EndTag { name: "".into() },
+                EndTag { name: "pre".into() },
+            ]
+        );
+        assert_eq!(
+            parse("<pre mode='cdata'/>x<pre mode=cdata><x>"),
+            vec![
+                OpenStartTag { name: "pre".into() },
+                Attribute {
+                    name: "mode".into(),
+                    value: Some("cdata".into()),
+                },
+                XmlCloseEmptyElement,
+                Character("x".into()),
+                OpenStartTag { name: "pre".into() },
+                Attribute {
+                    name: "mode".into(),
+                    value: Some("cdata".into()),
+                },
+                CloseStartTag,
+                Character("<x>".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_document_entity_normalize_newlines() {
+        let parser = Parser::builder()
+            .normalize_newlines(true)
+            .expand_marked_sections()
+            .build();
+        let input = "<p>a\r\nb\rc<![CDATA[d\r\ne]]><![RCDATA[f\rg]]></p>";
+        assert_eq!(
+            parser.parse(input).unwrap().into_vec(),
+            vec![
+                OpenStartTag { name: "p".into() },
+                CloseStartTag,
+                Character("a\nb\nc".into()),
+                Character("d\r\ne".into()),
+                Character("f\ng".into()),
+                EndTag { name: "p".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_document_entity_separate_whitespace() {
+        let parser = Parser::builder()
+            .whitespace_handling(WhitespaceHandling::Separate)
+            .build();
+        let input = "<p>\n  <b> bold </b>&#32;<![CDATA[ ]]>\n</p>\n";
+        assert_eq!(
+            parser.parse(input).unwrap().into_vec(),
+            vec![
+                OpenStartTag { name: "p".into() },
+                CloseStartTag,
+                Whitespace("\n  ".into()),
+                OpenStartTag { name: "b".into() },
+                CloseStartTag,
+                Character(" bold ".into()),
+                EndTag { name: "b".into() },
+                Character(" ".into()),
+                Character(" ".into()),
+                Whitespace("\n".into()),
+                EndTag { name: "p".into() },
+                Whitespace("\n".into()),
+            ]
+        );
+    }
+
     #[test]
     fn test_document_entity_ignore_markup_declarations_retain_whitespace() {
         const SAMPLE: &str = r#"
@@ -581,12 +1658,11 @@ mod tests {
             </HTML>
         "#;
 
-        let config = Parser::builder()
+        let parser = Parser::builder()
             .ignore_markup_declarations(true)
             .trim_whitespace(false)
-            .into_config();
-        let (rest, mut events) = document_entity::<E>(SAMPLE, &config).unwrap();
-        assert!(rest.is_empty(), "rest: {:?}", rest);
+            .build();
+        let mut events = parser.parse(SAMPLE).unwrap().into_iter();
 
         assert_eq!(
             events.next(),
@@ -668,6 +1744,73 @@ mod tests {
         assert_eq!(events.next(), Some(Character("\n        ".into())));
     }
 
+    #[test]
+    fn test_document_entity_resolve_internal_entities() {
+        const SAMPLE: &str = r#"
+            <!-- a comment -->
+            <?experiment>
+            <!SGML example>
+            <!DOCTYPE test [
+                <!ENTITY greeting "Hello">
+                <!ENTITY greeting "Overridden">
+                <!ENTITY % cond "IGNORE">
+            ]>
+            <TEST NAME="&greeting;">&greeting; &world;<![%cond;[ unwanted ]]></TEST>
+        "#;
+
+        let parser = Parser::builder()
+            .resolve_internal_entities(true)
+            .expand_marked_sections()
+            .expand_entities(|entity| match entity {
+                "world" => Some("world"),
+                "greeting" => Some("Unused"),
+                _ => None,
+            })
+            .build();
+        let events = parser.parse(SAMPLE).unwrap().into_iter();
+
+        let events = events.skip(3).collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                OpenStartTag {
+                    name: "TEST".into()
+                },
+                Attribute {
+                    name: "NAME".into(),
+                    value: Some("Hello".into()),
+                },
+                CloseStartTag,
+                Character("Hello world".into()),
+                EndTag {
+                    name: "TEST".into()
+                },
+            ]
+        );
+
+        assert!(Parser::new().parse(SAMPLE).is_err());
+    }
+
+    #[test]
+    fn test_document_entity() {
+        let builder = || {
+            Parser::builder()
+                .strip_bom(true)
+                .recognize_xml_declaration(true)
+                .allow_trailing_content(true)
+        };
+        let input = "\u{feff}<?xml version='1.0'?><x>text</x><!trailing>";
+        let (rest, events) = document_entity::<E>(input, &builder().into_config()).unwrap();
+        assert_eq!(rest, "<!trailing>");
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            builder().build().parse(input).unwrap().into_vec()
+        );
+
+        let config = Parser::builder().max_events(2).into_config();
+        assert!(document_entity::<E>("<x>text</x>", &config).is_err());
+    }
+
     #[test]
     fn test_markup_declaration() {
         let input = r##"<!DOCTYPE HTML><!SGML>"##;
@@ -691,6 +1834,166 @@ mod tests {
         assert_eq!(events.next(), None);
     }
 
+    #[test]
+    fn test_strict_markup_declarations() {
+        let config = Parser::builder()
+            .strict_markup_declarations(true)
+            .into_config();
+        let input = "<!doctype doc [ <!ELEMENT doc - - ANY> <!AttList doc a CDATA #IMPLIED> ]>";
+        let (rest, mut events) = markup_declaration::<E>(input, &config).unwrap();
+        assert_eq!(rest, "");
+        assert!(matches!(
+            events.next(),
+            Some(SgmlEvent::MarkupDeclaration { keyword, .. }) if keyword == "doctype"
+        ));
+
+        let input = "<!USEMAP map p>";
+        assert!(markup_declaration::<E>(input, &Default::default()).is_ok());
+        assert!(matches!(
+            markup_declaration::<E>(input, &config),
+            Err(nom::Err::Failure(_))
+        ));
+
+        let err = Parser::builder()
+            .strict_markup_declarations(true)
+            .parse("<!DOCTYPE doc [\n<!ENTITY a 'b'>\n<!ENTTY c 'd'>\n]>\n<doc></doc>")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("line 3: unrecognized markup declaration: ENTTY"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_split_internal_subset() {
+        let config = Parser::builder().split_internal_subset(true).into_config();
+        let input = r#"<!DOCTYPE doc SYSTEM "doc.dtd" [
+            <!ENTITY arrow "->">
+            <!ATTLIST doc title CDATA "a > b">
+        ]>"#;
+        let (rest, events) = markup_declaration::<E>(input, &config).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            [
+                MarkupDeclaration {
+                    keyword: "DOCTYPE".into(),
+                    body: r#"doc SYSTEM "doc.dtd""#.into(),
+                },
+                MarkupDeclaration {
+                    keyword: "ENTITY".into(),
+                    body: r#"arrow "->""#.into(),
+                },
+                MarkupDeclaration {
+                    keyword: "ATTLIST".into(),
+                    body: r#"doc title CDATA "a > b""#.into(),
+                },
+            ]
+        );
+
+        let (_, events) = markup_declaration::<E>("<!DOCTYPE doc [ ]>", &config).unwrap();
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            [MarkupDeclaration {
+                keyword: "DOCTYPE".into(),
+                body: "doc".into(),
+            }]
+        );
+
+        let input = "<!DOCTYPE doc><!ENTITY x 'y'>";
+        let (rest, events) = markup_declaration::<E>(input, &config).unwrap();
+        assert_eq!(rest, "<!ENTITY x 'y'>");
+        assert_eq!(events.len(), 1);
+    }
+
+    #[test]
+    fn test_markup_declaration_unknown_keyword_quoted_delimiters() {
+        let input = r#"<!USEMAP "map > one" x><p>text</p>"#;
+        assert_eq!(
+            Parser::new().parse(input).unwrap().into_vec(),
+            vec![
+                MarkupDeclaration {
+                    keyword: "USEMAP".into(),
+                    body: r#""map > one" x"#.into(),
+                },
+                OpenStartTag { name: "p".into() },
+                CloseStartTag,
+                Character("text".into()),
+                EndTag { name: "p".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_marked_section_xml_strict() {
+        let lenient = ParserConfig::default();
+        let strict = Parser::builder().xml_marked_sections(true).into_config();
+        let cdata = || EventIter::once(Character("<b>".into()));
+
+        let input = "<![CDATA[<b>]]>";
+        assert_eq!(
+            marked_section_declaration::<E>(input, &lenient),
+            Ok(("", cdata()))
+        );
+        assert_eq!(
+            marked_section_declaration::<E>(input, &strict),
+            Ok(("", cdata()))
+        );
+
+        for input in ["<![ CDATA[<b>]]>", "<![CDATA [<b>]]>", "<![ CDATA [<b>]]>"] {
+            assert_eq!(
+                marked_section_declaration::<E>(input, &lenient),
+                Ok(("", cdata())),
+                "{}",
+                input
+            );
+            assert!(
+                matches!(
+                    marked_section_declaration::<E>(input, &strict),
+                    Err(nom::Err::Failure(_))
+                ),
+                "{}",
+                input
+            );
+        }
+
+        for input in ["<![cdata[<b>]]>", "<![RCDATA[<b>]]>"] {
+            assert!(marked_section_declaration::<E>(input, &lenient).is_ok());
+            assert!(marked_section_declaration::<E>(input, &strict).is_err());
+        }
+
+        let expand_all = |strict| {
+            Parser::builder()
+                .expand_marked_sections()
+                .xml_marked_sections(strict)
+                .parse("<a><![INCLUDE[<b></b>]]><![IGNORE[<c></c>]]></a>")
+        };
+        assert!(expand_all(false).is_ok());
+        let err = expand_all(true).unwrap_err().to_string();
+        assert!(
+            err.contains("invalid marked section keyword: INCLUDE"),
+            "{}",
+            err
+        );
+
+        let keep = Parser::builder()
+            .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+            .xml_marked_sections(true)
+            .into_config();
+        assert_eq!(
+            marked_section_declaration::<E>("<![CDATA[<b>]]>", &keep),
+            Ok((
+                "",
+                EventIter::once(MarkedSection {
+                    status_keywords: "CDATA".into(),
+                    section: "<b>".into(),
+                })
+            ))
+        );
+    }
+
     #[test]
     fn test_processing_instruction() {
         let input = r##"<?experiment> "##;
@@ -713,6 +2016,123 @@ mod tests {
         assert_eq!(events.next(), None);
     }
 
+    #[test]
+    fn test_processing_instruction_structured() {
+        let config = Parser::builder().structured_pis(true).into_config();
+        let parse = |input| {
+            let (rest, mut events) = processing_instruction::<E>(input, &config).unwrap();
+            assert_eq!(rest, "");
+            events.next().unwrap()
+        };
+
+        assert_eq!(
+            parse("<?style tt = font courier>"),
+            StructuredProcessingInstruction {
+                target: "style".into(),
+                data: "tt = font courier".into(),
+            }
+        );
+        assert_eq!(
+            parse(r#"<?xml version="1.0"?>"#),
+            StructuredProcessingInstruction {
+                target: "xml".into(),
+                data: r#"version="1.0""#.into(),
+            }
+        );
+        assert_eq!(
+            parse("<?page \n break>"),
+            StructuredProcessingInstruction {
+                target: "page".into(),
+                data: "break".into(),
+            }
+        );
+        assert_eq!(
+            parse("<?experiment>"),
+            StructuredProcessingInstruction {
+                target: "experiment".into(),
+                data: "".into(),
+            }
+        );
+        assert_eq!(
+            parse("<?>"),
+            StructuredProcessingInstruction {
+                target: "".into(),
+                data: "".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_xml_declaration() {
+        let config = Default::default();
+        let parse = |input| xml_declaration::<E>(input, &config).map(|(_, event)| event);
+
+        assert_eq!(
+            parse(r#"<?xml version="1.0" encoding='UTF-8' standalone="no"?>"#),
+            Ok(XmlDeclaration {
+                version: "1.0".into(),
+                encoding: Some("UTF-8".into()),
+                standalone: Some(false),
+            })
+        );
+        assert_eq!(
+            parse(r#"<?xml version = "1.1" standalone="yes" ?>"#),
+            Ok(XmlDeclaration {
+                version: "1.1".into(),
+                encoding: None,
+                standalone: Some(true),
+            })
+        );
+        assert!(parse("<?xml>").is_err());
+        assert!(parse(r#"<?XML version="1.0"?>"#).is_err());
+        assert!(parse(r#"<?xml-stylesheet href="a.css"?>"#).is_err());
+        assert!(parse(r#"<?xml encoding="UTF-8" version="1.0"?>"#).is_err());
+        assert!(parse(r#"<?xml version="1.0" standalone="maybe"?>"#).is_err());
+        assert!(parse(r#"<?xml version="1.0" foo="bar"?>"#).is_err());
+        assert!(matches!(parse("<?xml"), Err(nom::Err::Error(_))));
+    }
+
+    #[test]
+    fn test_document_entity_xml_declaration() {
+        let parser = Parser::builder().recognize_xml_declaration(true).build();
+        let input = r#"
+            <?xml version="1.0"?>
+            <!DOCTYPE x>
+            <x><?xml version="1.0"?></x>
+        "#;
+        let expected = vec![
+            XmlDeclaration {
+                version: "1.0".into(),
+                encoding: None,
+                standalone: None,
+            },
+            MarkupDeclaration {
+                keyword: "DOCTYPE".into(),
+                body: "x".into(),
+            },
+            OpenStartTag { name: "x".into() },
+            CloseStartTag,
+            ProcessingInstruction(r#"<?xml version="1.0"?>"#.into()),
+            EndTag { name: "x".into() },
+        ];
+
+        assert_eq!(parser.parse(input).unwrap().into_vec(), expected);
+
+        let input = r#"<!DOCTYPE x><?xml version="1.0"?><x></x>"#;
+        let mut events = parser.parse(input).unwrap().into_iter();
+        assert_eq!(
+            events.nth(1),
+            Some(ProcessingInstruction(r#"<?xml version="1.0"?>"#.into()))
+        );
+
+        let input = r#"<?xml version="1.0"?><x></x>"#;
+        let mut events = Parser::new().parse(input).unwrap().into_iter();
+        assert_eq!(
+            events.next(),
+            Some(ProcessingInstruction(r#"<?xml version="1.0"?>"#.into()))
+        );
+    }
+
     #[test]
     fn test_start_tag() {
         let config = Default::default();
@@ -817,6 +2237,36 @@ mod tests {
         assert_eq!(events.next(), None);
     }
 
+    #[test]
+    fn test_start_tag_custom_whitespace() {
+        let config = Parser::builder()
+            .whitespace_chars(" \t\r\n\x0c")
+            .into_config();
+        let (rest, mut events) =
+            start_tag::<E>("<a\x0chref='test.htm'\x0c\x0ctarget>", &config).unwrap();
+        assert_eq!(rest, "");
+
+        assert_eq!(events.next(), Some(OpenStartTag { name: "a".into() }));
+        assert_eq!(
+            events.next(),
+            Some(Attribute {
+                name: "href".into(),
+                value: Some("test.htm".into()),
+            })
+        );
+        assert_eq!(
+            events.next(),
+            Some(Attribute {
+                name: "target".into(),
+                value: None,
+            })
+        );
+        assert_eq!(events.next(), Some(CloseStartTag));
+        assert_eq!(events.next(), None);
+
+        start_tag::<E>("<a\x0chref='test.htm'>", &Default::default()).unwrap_err();
+    }
+
     #[test]
     fn test_start_tag_xml_no_content() {
         let config = Default::default();
@@ -828,6 +2278,19 @@ mod tests {
         assert_eq!(events.next(), None);
     }
 
+    #[test]
+    fn test_start_tag_no_attributes_does_not_allocate() {
+        let config = Default::default();
+        let (_, events) = start_tag::<E>("<p>", &config).unwrap();
+        #[cfg(not(feature = "smallvec"))]
+        assert_eq!(events.middle.capacity(), 0);
+        #[cfg(feature = "smallvec")]
+        assert!(!events.middle.spilled());
+
+        let (_, events) = start_tag::<E>("<p class=x>", &config).unwrap();
+        assert_eq!(events.middle.len(), 1);
+    }
+
     #[test]
     fn test_start_tag_empty() {
         let config = Default::default();
@@ -839,6 +2302,272 @@ mod tests {
         assert_eq!(events.next(), None);
     }
 
+    #[test]
+    fn test_attribute_unquoted_events() {
+        let config = Parser::builder()
+            .unquoted_attribute_events(true)
+            .into_config();
+        assert_eq!(
+            attribute::<E>("colspan=2 ", &config),
+            Ok((
+                " ",
+                UnquotedAttribute {
+                    name: "colspan".into(),
+                    value: "2".into(),
+                }
+            ))
+        );
+        assert_eq!(
+            attribute::<E>("colspan='2' ", &config),
+            Ok((
+                " ",
+                Attribute {
+                    name: "colspan".into(),
+                    value: Some("2".into()),
+                }
+            ))
+        );
+        assert_eq!(
+            attribute::<E>("disabled ", &config),
+            Ok((
+                " ",
+                Attribute {
+                    name: "disabled".into(),
+                    value: None,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_attribute_quoted_events() {
+        let config = Parser::builder()
+            .quoted_attribute_events(true)
+            .into_config();
+        assert_eq!(
+            attribute::<E>("title='a &#34;b&#34;' ", &config),
+            Ok((
+                " ",
+                QuotedAttribute {
+                    name: "title".into(),
+                    value: "a \"b\"".into(),
+                    quote: '\'',
+                }
+            ))
+        );
+        assert_eq!(
+            attribute::<E>("title = \"\" ", &config),
+            Ok((
+                " ",
+                QuotedAttribute {
+                    name: "title".into(),
+                    value: "".into(),
+                    quote: '"',
+                }
+            ))
+        );
+        assert_eq!(
+            attribute::<E>("colspan=2 ", &config),
+            Ok((
+                " ",
+                Attribute {
+                    name: "colspan".into(),
+                    value: Some("2".into()),
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_attribute_entity_quotes() {
+        // Quotes produced by entities never close the value, since the value
+        // is delimited before references are expanded
+        let config = Parser::builder()
+            .expand_entities(|entity| match entity {
+                "quot" => Some("\""),
+                "apos" => Some("'"),
+                "ldquo" => Some("\u{201c}"),
+                "rdquo" => Some("\u{201d}"),
+                _ => None,
+            })
+            .into_config();
+        assert_eq!(
+            attribute::<E>("title=\"say &quot;hi&quot; &ldquo;x&rdquo;\" ", &config),
+            Ok((
+                " ",
+                Attribute {
+                    name: "title".into(),
+                    value: Some("say \"hi\" \u{201c}x\u{201d}".into()),
+                }
+            ))
+        );
+        assert_eq!(
+            attribute::<E>("title='&apos;' ", &config),
+            Ok((
+                " ",
+                Attribute {
+                    name: "title".into(),
+                    value: Some("'".into()),
+                }
+            ))
+        );
+
+        let (rest, events) =
+            start_tag::<E>("<a title=\"&quot;\" href=\"/&quot;x\">", &config).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            [
+                OpenStartTag { name: "a".into() },
+                Attribute {
+                    name: "title".into(),
+                    value: Some("\"".into()),
+                },
+                Attribute {
+                    name: "href".into(),
+                    value: Some("/\"x".into()),
+                },
+                CloseStartTag,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_attribute_minimized_rejected() {
+        let config = Parser::builder()
+            .allow_minimized_attributes(false)
+            .into_config();
+        assert_eq!(
+            attribute::<E>("checked=checked ", &config),
+            Ok((
+                " ",
+                Attribute {
+                    name: "checked".into(),
+                    value: Some("checked".into()),
+                }
+            ))
+        );
+        assert!(matches!(
+            attribute::<E>("disabled ", &config),
+            Err(nom::Err::Failure(_))
+        ));
+
+        let err = Parser::builder()
+            .allow_minimized_attributes(false)
+            .parse("<form>\n<input type=checkbox disabled></form>")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("attribute without value: disabled"), "{}", err);
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_start_tag_expand_minimized_attributes() {
+        let config = Parser::builder()
+            .lowercase_names()
+            .expand_minimized_attributes(|element, token| match (element, token) {
+                ("ul", "compact") => Some("compact"),
+                ("p", "left" | "right") => Some("align"),
+                _ => None,
+            })
+            .into_config();
+        let (_, events) = start_tag::<E>("<UL COMPACT>", &config).unwrap();
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            [
+                OpenStartTag { name: "ul".into() },
+                Attribute {
+                    name: "compact".into(),
+                    value: Some("compact".into()),
+                },
+                CloseStartTag,
+            ]
+        );
+
+        let (_, events) = start_tag::<E>("<p right hidden class=left>", &config).unwrap();
+        assert_eq!(
+            events.collect::<Vec<_>>(),
+            [
+                OpenStartTag { name: "p".into() },
+                Attribute {
+                    name: "align".into(),
+                    value: Some("right".into()),
+                },
+                Attribute {
+                    name: "hidden".into(),
+                    value: None,
+                },
+                Attribute {
+                    name: "class".into(),
+                    value: Some("left".into()),
+                },
+                CloseStartTag,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_start_tag_max_attributes() {
+        let config = Parser::builder()
+            .max_attributes_per_element(2)
+            .into_config();
+        let (_, events) = start_tag::<E>("<td colspan=2 rowspan=3>", &config).unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            start_tag::<E>("<td colspan=2 rowspan=3 nowrap>", &config),
+            Err(nom::Err::Failure(_))
+        ));
+        // The tag is rejected at the first attribute over the limit,
+        // without reading any further
+        match start_tag::<E>("<td a=1 b=2 c=3 d='&undefined;'>", &config) {
+            Err(nom::Err::Failure(err)) => {
+                assert_eq!(err.input, "c=3 d='&undefined;'>");
+                assert_eq!(err.code, ErrorKind::TooLarge);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let err = Parser::builder()
+            .max_attributes_per_element(2)
+            .parse("<table>\n<td colspan=2 rowspan=3 nowrap></table>")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("too many attributes in start tag for td: limit is 2"),
+            "{}",
+            err
+        );
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_start_tag_duplicate_attributes() {
+        let input = r#"<a href="/" HREF="/home">"#;
+        let (_, events) = start_tag::<E>(input, &Default::default()).unwrap();
+        assert_eq!(events.len(), 4);
+
+        let config = Parser::builder()
+            .reject_duplicate_attributes(true)
+            .into_config();
+        assert!(start_tag::<E>(input, &config).is_ok());
+        assert!(matches!(
+            start_tag::<E>(r#"<a href="/" title="Home" href="/home">"#, &config),
+            Err(nom::Err::Failure(_))
+        ));
+
+        let err = Parser::builder()
+            .lowercase_names()
+            .reject_duplicate_attributes(true)
+            .parse(r#"<p><a href="/" HREF="/home"></a></p>"#)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("duplicate attribute in start tag for a: href"),
+            "{}",
+            err
+        );
+    }
+
     #[test]
     fn test_attribute_unquoted_is_literal() {
         let config = Default::default();
@@ -854,6 +2583,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_names() {
+        let config = Parser::builder()
+            .validate_names(|name| name.chars().all(|c| c.is_ascii_alphanumeric()))
+            .into_config();
+        assert_eq!(
+            open_start_tag::<E>("<td2 ", &config),
+            Ok((" ", OpenStartTag { name: "td2".into() }))
+        );
+        assert_eq!(
+            end_tag::<E>("</>", &config),
+            Ok(("", EndTag { name: "".into() }))
+        );
+        assert!(matches!(
+            open_start_tag::<E>("<svg:rect>", &config),
+            Err(nom::Err::Failure(_))
+        ));
+        assert!(matches!(
+            end_tag::<E>("</svg:rect>", &config),
+            Err(nom::Err::Failure(_))
+        ));
+
+        let parser = Parser::builder()
+            .validate_names(|name| !name.contains(':') && !name.contains(' '))
+            .build();
+        assert!(parser.parse("<doc><my-tag x:y=1></my-tag></doc>").is_ok());
+        let err = parser
+            .parse("<doc>\n<svg:rect></svg:rect></doc>")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("line 2: invalid tag name: svg:rect"),
+            "{}",
+            err
+        );
+        let err = parser.parse("<doc></x:doc>").unwrap_err().to_string();
+        assert!(err.contains("invalid tag name: x:doc"), "{}", err);
+    }
+
     #[test]
     fn test_end_tag() {
         let config = Default::default();
@@ -913,7 +2681,7 @@ mod tests {
     fn test_event_iter_complete() {
         let mut iter = EventIter::start_tag((
             OpenStartTag { name: "foo".into() },
-            vec![
+            EventVec::from_iter([
                 Attribute {
                     name: "x".into(),
                     value: Some("y".into()),
@@ -922,7 +2690,7 @@ mod tests {
                     name: "z".into(),
                     value: None,
                 },
-            ],
+            ]),
             CloseStartTag,
         ));
 
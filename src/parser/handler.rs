@@ -0,0 +1,254 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::SgmlEvent;
+
+/// A push-style interface for consuming a document, as an alternative
+/// to collecting its events.
+///
+/// Used by [`Parser::parse_with_handler`](super::Parser::parse_with_handler),
+/// which calls these methods in document order. All methods do nothing by default,
+/// so implementations only need to override the ones they care about.
+/// Returning an error from any method aborts parsing, and the error is propagated.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::parser::Handler;
+/// #[derive(Default)]
+/// struct Outline(Vec<String>);
+///
+/// impl Handler for Outline {
+///     type Error = sgmlish::Error;
+///
+///     fn start_element(&mut self, name: &str, attributes: &[(&str, Option<&str>)]) -> sgmlish::Result<()> {
+///         self.0.push(format!("{} ({} attributes)", name, attributes.len()));
+///         Ok(())
+///     }
+/// }
+///
+/// # fn main() -> sgmlish::Result<()> {
+/// let mut outline = Outline::default();
+/// sgmlish::Parser::new().parse_with_handler(r#"<a href="/"><b>Home</b></a>"#, &mut outline)?;
+/// assert_eq!(outline.0, ["a (1 attributes)", "b (0 attributes)"]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Handler {
+    /// The error type returned by the handler; parse errors are converted into it.
+    type Error: From<crate::Error>;
+
+    /// Called once a start tag is complete, with its attributes in order.
+    ///
+    /// For XML-style empty elements (`<br/>`), this is immediately followed by
+    /// a call to [`end_element`](Handler::end_element).
+    fn start_element(
+        &mut self,
+        name: &str,
+        attributes: &[(&str, Option<&str>)],
+    ) -> Result<(), Self::Error> {
+        let _ = (name, attributes);
+        Ok(())
+    }
+
+    /// Called for an end tag. Empty end tags (`</>`) have an empty name.
+    fn end_element(&mut self, name: &str) -> Result<(), Self::Error> {
+        let _ = name;
+        Ok(())
+    }
+
+    /// Called for text content.
+    fn characters(&mut self, text: &str) -> Result<(), Self::Error> {
+        let _ = text;
+        Ok(())
+    }
+
+    /// Called for whitespace-only text, when whitespace is handled as
+    /// [`Separate`](super::WhitespaceHandling::Separate).
+    fn whitespace(&mut self, text: &str) -> Result<(), Self::Error> {
+        let _ = text;
+        Ok(())
+    }
+
+    /// Called for a processing instruction, with its full text (`<?example>`).
+    fn processing_instruction(&mut self, instruction: &str) -> Result<(), Self::Error> {
+        let _ = instruction;
+        Ok(())
+    }
+
+    /// Called for any other event: markup declarations, XML declarations,
+    /// and marked sections that were kept unmodified.
+    fn declaration(&mut self, event: &SgmlEvent) -> Result<(), Self::Error> {
+        let _ = event;
+        Ok(())
+    }
+}
+
+/// Dispatches events to a [`Handler`], gathering start tags along the way.
+pub(crate) struct Dispatcher<'a, 'h, H> {
+    handler: &'h mut H,
+    start_tag: Option<StartTag<'a>>,
+}
+
+/// A start tag whose attributes are still being gathered.
+struct StartTag<'a> {
+    name: Cow<'a, str>,
+    /// The attribute events, in order.
+    attributes: Vec<SgmlEvent<'a>>,
+}
+
+impl<'a, 'h, H: Handler> Dispatcher<'a, 'h, H> {
+    pub fn new(handler: &'h mut H) -> Self {
+        Dispatcher {
+            handler,
+            start_tag: None,
+        }
+    }
+
+    pub fn dispatch(&mut self, event: SgmlEvent<'a>) -> Result<(), H::Error> {
+        match event {
+            SgmlEvent::OpenStartTag { name } => {
+                self.start_tag = Some(StartTag {
+                    name,
+                    attributes: Vec::new(),
+                })
+            }
+            SgmlEvent::Attribute { .. }
+            | SgmlEvent::UnquotedAttribute { .. }
+            | SgmlEvent::QuotedAttribute { .. } => {
+                if let Some(start_tag) = &mut self.start_tag {
+                    start_tag.attributes.push(event);
+                }
+            }
+            SgmlEvent::CloseStartTag => {
+                self.start_element()?;
+            }
+            SgmlEvent::XmlCloseEmptyElement => {
+                if let Some(name) = self.start_element()? {
+                    self.handler.end_element(&name)?;
+                }
+            }
+            SgmlEvent::EndTag { name } => self.handler.end_element(&name)?,
+            SgmlEvent::Character(text) => self.handler.characters(&text)?,
+            SgmlEvent::Whitespace(text) => self.handler.whitespace(&text)?,
+            SgmlEvent::ProcessingInstruction(instruction) => {
+                self.handler.processing_instruction(&instruction)?
+            }
+            event @ SgmlEvent::StructuredProcessingInstruction { .. } => {
+                self.handler.processing_instruction(&event.to_string())?
+            }
+            event => self.handler.declaration(&event)?,
+        }
+        Ok(())
+    }
+
+    /// Reports the pending start tag, and outputs its name.
+    fn start_element(&mut self) -> Result<Option<Cow<'a, str>>, H::Error> {
+        let StartTag { name, attributes } = match self.start_tag.take() {
+            Some(start_tag) => start_tag,
+            None => return Ok(None),
+        };
+        let attributes = attributes
+            .iter()
+            .filter_map(SgmlEvent::as_attribute)
+            .collect::<Vec<_>>();
+        self.handler.start_element(&name, &attributes)?;
+        Ok(Some(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    /// Records every call as a string, optionally failing on a given element
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+        fail_on: Option<&'static str>,
+    }
+
+    impl Handler for Recorder {
+        type Error = crate::Error;
+
+        fn start_element(
+            &mut self,
+            name: &str,
+            attributes: &[(&str, Option<&str>)],
+        ) -> crate::Result<()> {
+            if self.fail_on == Some(name) {
+                return Err(crate::Error::ParseError(format!("refusing {}", name)));
+            }
+            self.calls.push(format!("start {} {:?}", name, attributes));
+            Ok(())
+        }
+
+        fn end_element(&mut self, name: &str) -> crate::Result<()> {
+            self.calls.push(format!("end {}", name));
+            Ok(())
+        }
+
+        fn characters(&mut self, text: &str) -> crate::Result<()> {
+            self.calls.push(format!("text {}", text));
+            Ok(())
+        }
+
+        fn processing_instruction(&mut self, instruction: &str) -> crate::Result<()> {
+            self.calls.push(format!("pi {}", instruction));
+            Ok(())
+        }
+
+        fn declaration(&mut self, event: &SgmlEvent) -> crate::Result<()> {
+            self.calls.push(format!("declaration {}", event));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_parse_with_handler() {
+        let mut recorder = Recorder::default();
+        Parser::builder()
+            .unquoted_attribute_events(true)
+            .build()
+            .parse_with_handler(
+                "<!DOCTYPE x><?page><x a=1 b='2' c>one<br/>two</x>",
+                &mut recorder,
+            )
+            .unwrap();
+        assert_eq!(
+            recorder.calls,
+            [
+                "declaration <!DOCTYPE x>",
+                "pi <?page>",
+                r#"start x [("a", Some("1")), ("b", Some("2")), ("c", None)]"#,
+                "text one",
+                "start br []",
+                "end br",
+                "text two",
+                "end x",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_with_handler_errors() {
+        let mut recorder = Recorder {
+            fail_on: Some("b"),
+            ..Default::default()
+        };
+        let err = Parser::new()
+            .parse_with_handler("<a>x</a><b>y</b><c>z</c>", &mut recorder)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "refusing b");
+        assert_eq!(recorder.calls, ["start a []", "text x", "end a"]);
+
+        let mut recorder = Recorder::default();
+        let err = Parser::new()
+            .parse_with_handler("<a>x</a><b y='>", &mut recorder)
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::ParseError(_)));
+        assert_eq!(recorder.calls, ["start a []", "text x", "end a"]);
+    }
+}
@@ -0,0 +1,234 @@
+use core::fmt;
+use core::ops::Range;
+
+use crate::prelude::*;
+use crate::SgmlFragment;
+
+/// A fragment that remembers the exact source text of every event.
+///
+/// This is created by [`Parser::parse_preserving_layout`](super::Parser::parse_preserving_layout).
+/// Events are grouped by the markup they were parsed from: a start tag, with all
+/// of its attributes, is a single span, while each end tag, piece of text and
+/// declaration gets its own. Whitespace and comments that produce no events,
+/// like the spaces in `<a  href = "x" >` or text trimmed away, belong to the span
+/// of the next event (or of the last one, at the end of the input), so spans
+/// cover the whole input, in order, without gaps.
+///
+/// Displaying a `LayoutFragment` writes the source text verbatim. Editors can use
+/// [`spans`](LayoutFragment::spans) to copy the original text of untouched markup,
+/// serializing only the events they changed.
+///
+/// # Memory cost
+///
+/// The fragment keeps the input borrowed for as long as it lives, even if events
+/// are turned into owned data, and stores two `usize` offsets per span,
+/// on top of the events themselves. Since a span has one or more events,
+/// this is at most 16 bytes per event on 64-bit platforms; no text is copied.
+/// The index of every text event where references were expanded is also kept.
+///
+/// # Example
+///
+/// ```rust
+/// # fn main() -> sgmlish::Result<()> {
+/// let input = r#"<a  href = "x" >link</a >"#;
+/// let layout = sgmlish::Parser::new().parse_preserving_layout(input)?;
+/// assert_eq!(layout.to_string(), input);
+/// assert_eq!(layout.fragment().to_string(), r#"<a href="x">link</a>"#);
+///
+/// let spans = layout.spans().map(|(_, source)| source).collect::<Vec<_>>();
+/// assert_eq!(spans, [r#"<a  href = "x" >"#, "link", "</a >"]);
+/// assert_eq!(layout.source(1), Some(r#"<a  href = "x" >"#));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LayoutFragment<'a> {
+    fragment: SgmlFragment<'a>,
+    input: &'a str,
+    /// The index of the first event of each span, and the offset where its source starts
+    spans: Vec<(usize, usize)>,
+    /// The indices of the text events where references were expanded, in ascending order
+    expanded: Vec<usize>,
+}
+
+impl<'a> LayoutFragment<'a> {
+    pub(crate) fn new(
+        fragment: SgmlFragment<'a>,
+        input: &'a str,
+        spans: Vec<(usize, usize)>,
+        expanded: Vec<usize>,
+    ) -> Self {
+        LayoutFragment {
+            fragment,
+            input,
+            spans,
+            expanded,
+        }
+    }
+
+    /// Returns the parsed events.
+    pub fn fragment(&self) -> &SgmlFragment<'a> {
+        &self.fragment
+    }
+
+    /// Discards the source text, returning only the parsed events.
+    pub fn into_fragment(self) -> SgmlFragment<'a> {
+        self.fragment
+    }
+
+    /// Returns the source text of the markup the event at the given index was parsed from,
+    /// or `None` if the index is out of bounds.
+    ///
+    /// Events parsed from the same markup, like a start tag and its attributes,
+    /// share the same source text.
+    pub fn source(&self, index: usize) -> Option<&'a str> {
+        if index >= self.fragment.len() {
+            return None;
+        }
+        let span = self.spans.partition_point(|&(first, _)| first <= index) - 1;
+        Some(self.span_source(span))
+    }
+
+    /// Tests whether the event at the given index is text where entity, character
+    /// or short references were expanded while parsing.
+    ///
+    /// This lets serializers tell `&amp;` apart from a literal `&` that was accepted as text,
+    /// and write the reference back. Text that was only trimmed, or whose line endings were
+    /// rewritten with [`normalize_newlines`](super::ParserBuilder::normalize_newlines),
+    /// is not considered expanded, nor is text taken from a `CDATA` marked section.
+    ///
+    /// Returns `false` for any other event, and if the index is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let layout = sgmlish::Parser::new().parse_preserving_layout("<a>x &#38; y</a><b>x & y</b>")?;
+    /// assert_eq!(layout.fragment().as_slice()[2], layout.fragment().as_slice()[6]);
+    /// assert!(layout.was_expanded(2));
+    /// assert!(!layout.was_expanded(6));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn was_expanded(&self, index: usize) -> bool {
+        self.expanded.binary_search(&index).is_ok()
+    }
+
+    /// Returns an iterator over the spans of the input, in order, as the range
+    /// of indices of the events parsed from each span, along with its source text.
+    pub fn spans(&self) -> impl Iterator<Item = (Range<usize>, &'a str)> + '_ {
+        (0..self.spans.len()).map(move |span| {
+            let first = self.spans[span].0;
+            let end = self
+                .spans
+                .get(span + 1)
+                .map_or(self.fragment.len(), |&(first, _)| first);
+            (first..end, self.span_source(span))
+        })
+    }
+
+    fn span_source(&self, span: usize) -> &'a str {
+        let start = self.spans[span].1;
+        let end = self
+            .spans
+            .get(span + 1)
+            .map_or(self.input.len(), |&(_, start)| start);
+        &self.input[start..end]
+    }
+}
+
+impl fmt::Display for LayoutFragment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::LayoutFragment;
+    use crate::{Parser, SgmlEvent};
+
+    #[test]
+    fn test_parse_preserving_layout() {
+        let input = concat!(
+            "\u{feff}<!DOCTYPE doc>\n",
+            "<doc  lang = en >\n",
+            "  <!-- note -->\n",
+            "  <p\tclass='x'>one<br/> two</p\n>\n",
+            "</doc> <?pi>\n",
+        );
+        let parser = Parser::builder().strip_bom(true).build();
+        let layout = parser.parse_preserving_layout(input).unwrap();
+        assert_eq!(layout.to_string(), input);
+        assert_eq!(layout.fragment(), &parser.parse(input).unwrap());
+
+        let spans = layout
+            .spans()
+            .map(|(events, source)| (events.len(), source))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [
+                (1, "\u{feff}<!DOCTYPE doc>\n"),
+                (3, "<doc  lang = en >"),
+                (3, "\n  <!-- note -->\n  <p\tclass='x'>"),
+                (1, "one"),
+                (2, "<br/>"),
+                (1, " two"),
+                (1, "</p\n>"),
+                (1, "\n</doc>"),
+                (1, " <?pi>\n"),
+            ]
+        );
+
+        let index = layout
+            .fragment()
+            .iter()
+            .position(|event| matches!(event, SgmlEvent::Attribute { name, .. } if name == "class"))
+            .unwrap();
+        assert_eq!(
+            layout.source(index),
+            Some("\n  <!-- note -->\n  <p\tclass='x'>")
+        );
+        assert_eq!(layout.source(layout.fragment().len()), None);
+    }
+
+    fn text_at<'a>(layout: &'a LayoutFragment, index: usize) -> &'a str {
+        match &layout.fragment().as_slice()[index] {
+            SgmlEvent::Character(text) => text.as_ref(),
+            event => panic!("expected text, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_was_expanded() {
+        let input = "<p> one &amp; two </p><p>one & two</p><p><![CDATA[&amp;]]></p><br>";
+        let parser = Parser::builder().predefined_xml_entities(true).build();
+        let layout = parser.parse_preserving_layout(input).unwrap();
+        assert_eq!(text_at(&layout, 2), "one & two");
+        assert!(layout.was_expanded(2));
+        assert_eq!(text_at(&layout, 6), "one & two");
+        assert!(!layout.was_expanded(6));
+        assert_eq!(text_at(&layout, 10), "&amp;");
+        assert!(!layout.was_expanded(10));
+
+        assert!(!layout.was_expanded(0));
+        assert!(!layout.was_expanded(layout.fragment().len()));
+
+        // The expanded text also appears verbatim in the source
+        let layout = parser.parse_preserving_layout("<p>&amp;</p>").unwrap();
+        assert_eq!(text_at(&layout, 2), "&");
+        assert!(layout.was_expanded(2));
+
+        let layout = parser
+            .parse_preserving_layout("<p><!-- & -->&amp;</p>")
+            .unwrap();
+        assert_eq!(text_at(&layout, 2), "&");
+        assert!(layout.was_expanded(2));
+
+        let parser = Parser::builder().normalize_newlines(true).build();
+        let layout = parser.parse_preserving_layout("<p>a\r\nb</p>").unwrap();
+        assert_eq!(text_at(&layout, 2), "a\nb");
+        assert!(!layout.was_expanded(2));
+    }
+}
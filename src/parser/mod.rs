@@ -1,17 +1,30 @@
 //! Access to configuration and inner workings of the parser.
 
-use std::borrow::Cow;
-use std::fmt;
+use alloc::borrow::Cow;
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::cmp::Reverse;
+use core::fmt;
+use core::iter::FusedIterator;
 
+use crate::dtd::{self, EntityKind, ExternalEntityDeclaration};
 use crate::marked_sections::MarkedSectionStatus;
-use crate::{entities, text, SgmlFragment};
+use crate::prelude::*;
+use crate::{entities, text, SgmlEvent, SgmlFragment};
+
+use self::events::OpenElements;
 
 mod error;
 pub mod events;
+mod handler;
+mod layout;
 pub mod raw;
 pub mod util;
 
 pub use error::*;
+pub use handler::Handler;
+pub use layout::LayoutFragment;
 
 /// Parses the given string using a [`Parser`] with default settings,
 /// then yielding an [`SgmlFragment`].
@@ -29,7 +42,12 @@ pub fn parse(input: &str) -> crate::Result<SgmlFragment> {
 /// The parser is only capable of working directly with strings,
 /// meaning the content must be decoded beforehand. If you want to work with
 /// data in character sets other than UTF-8, you may want to have a look at the
-/// [`encoding_rs`] crate.
+/// [`encoding_rs`] crate, or at `parse_bytes` and `parse_reader`,
+/// available with the `encoding` feature.
+///
+/// Parsers are `Send` and `Sync`, so a single parser can be shared across threads,
+/// e.g. behind an [`Arc`]; for this reason, all closures given to [`ParserBuilder`]
+/// must be `Send + Sync` as well.
 ///
 /// [`encoding_rs`]: https://docs.rs/encoding_rs/
 #[derive(Debug, Default)]
@@ -79,50 +97,624 @@ impl Parser {
             + nom::error::ContextError<&'a str>
             + nom::error::FromExternalError<&'a str, crate::Error>,
     {
-        use nom::Finish;
-        let (rest, events) = events::document_entity::<E>(input, &self.config).finish()?;
-        debug_assert!(rest.is_empty(), "document_entity should be all_consuming");
-
-        let events = events.collect::<Vec<_>>();
+        let events = self
+            .parse_iter_with_detailed_errors::<E>(input)
+            .collect::<Result<Vec<_>, E>>()?;
 
         Ok(SgmlFragment::from(events))
     }
+
+    /// Parses a single top-level element, along with its content, and returns it together
+    /// with the rest of the input, which is left unparsed.
+    ///
+    /// This is useful for streams made of several concatenated documents or fragments.
+    /// Whitespace and comments before the element are skipped; other markup before it,
+    /// like a `DOCTYPE` declaration or processing instructions, is included in the fragment.
+    /// The element ends at its end tag, or at an end tag for an element that encloses it,
+    /// as in `<a><b></a>`; without an end tag, it extends to the end of the input.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::Parser;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let parser = Parser::new();
+    /// let input = "<msg id=1>hello</msg>\n<msg id=2><b>hi</b></msg>";
+    ///
+    /// let (first, rest) = parser.parse_one(input)?;
+    /// assert_eq!(first.to_string(), r#"<msg id="1">hello</msg>"#);
+    /// assert_eq!(rest, "\n<msg id=2><b>hi</b></msg>");
+    ///
+    /// let (second, rest) = parser.parse_one(rest)?;
+    /// assert_eq!(second.to_string(), r#"<msg id="2"><b>hi</b></msg>"#);
+    /// assert_eq!(rest, "");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_one<'a>(&self, input: &'a str) -> crate::Result<(SgmlFragment<'a>, &'a str)> {
+        let mut events = self.parse_iter_with_detailed_errors::<ContextualizedError<_>>(input);
+        let mut fragment = Vec::new();
+        let mut open_elements = OpenElements::default();
+        let mut opened_element = false;
+
+        for event in events.by_ref() {
+            let event = event.map_err(|err| crate::Error::ParseError(err.describe(&input)))?;
+            open_elements.track(&event);
+            opened_element |= matches!(event, SgmlEvent::OpenStartTag { .. });
+            // End tags before the first start tag don't close anything
+            let closes_element = opened_element
+                && matches!(
+                    event,
+                    SgmlEvent::XmlCloseEmptyElement | SgmlEvent::EndTag { .. }
+                );
+            fragment.push(event);
+            if closes_element && open_elements.is_empty() {
+                break;
+            }
+        }
+
+        Ok((fragment.into(), events.remaining_input()))
+    }
+
+    /// Parses the given input, keeping track of the source text of every event,
+    /// so that markup can be written back exactly as it was found.
+    ///
+    /// See [`LayoutFragment`] for details.
+    pub fn parse_preserving_layout<'a>(&self, input: &'a str) -> crate::Result<LayoutFragment<'a>> {
+        let mut events = self.parse_iter_with_detailed_errors::<ContextualizedError<_>>(input);
+        let mut fragment = Vec::new();
+        let mut spans = Vec::new();
+        let mut expanded = Vec::new();
+        let mut span_start = 0;
+
+        while let Some(event) = events.next() {
+            let event = event.map_err(|err| crate::Error::ParseError(err.describe(&input)))?;
+            // Events are parsed in batches, one per piece of markup, so the input
+            // only advances when the first event of a new piece is returned
+            let offset = input.len() - events.remaining_input().len();
+            if offset != span_start {
+                spans.push((fragment.len(), span_start));
+                span_start = offset;
+            }
+            if events.last_event_expanded() {
+                expanded.push(fragment.len());
+            }
+            fragment.push(event);
+        }
+
+        Ok(LayoutFragment::new(fragment.into(), input, spans, expanded))
+    }
+
+    /// Parses the given input, as in [`parse`](Parser::parse), while collecting
+    /// some statistics about the document.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::Parser;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let (fragment, stats) = Parser::new().parse_with_stats("<a><b>caf&#233;</b></a>")?;
+    /// assert_eq!(stats.events, fragment.len());
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.characters, 4);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_stats<'a>(
+        &self,
+        input: &'a str,
+    ) -> crate::Result<(SgmlFragment<'a>, ParseStats)> {
+        let mut events = self.parse_iter_with_detailed_errors::<ContextualizedError<_>>(input);
+        let mut fragment = Vec::new();
+        let mut stats = ParseStats::default();
+        let mut open_elements = OpenElements::default();
+
+        for event in events.by_ref() {
+            let event = event.map_err(|err| crate::Error::ParseError(err.describe(&input)))?;
+            open_elements.track(&event);
+            stats.max_depth = stats.max_depth.max(open_elements.len());
+            if let SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) = &event {
+                stats.characters += text.chars().count();
+            }
+            fragment.push(event);
+        }
+
+        stats.events = fragment.len();
+        stats.entity_expansions = events.entity_expansions();
+        Ok((fragment.into(), stats))
+    }
+
+    /// Reads all data from the given reader, decodes it with the given encoding,
+    /// then parses it.
+    ///
+    /// A byte order mark for the given encoding is skipped. To detect the encoding
+    /// from the data instead, use [`parse_reader_auto`](Parser::parse_reader_auto).
+    /// Since the data is read into a temporary buffer, the resulting fragment
+    /// owns all of its contents.
+    ///
+    /// Fails with an [`IoError`](crate::Error::IoError) if reading fails,
+    /// or if the data is not valid in the given encoding.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::Parser;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let data: &[u8] = b"<p>caf\xE9</p>";
+    /// let fragment = Parser::new().parse_reader(data, encoding_rs::WINDOWS_1252)?;
+    /// assert_eq!(fragment.to_string(), "<p>café</p>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encoding")]
+    pub fn parse_reader<R: std::io::Read>(
+        &self,
+        mut reader: R,
+        encoding: &'static encoding_rs::Encoding,
+    ) -> crate::Result<SgmlFragment<'static>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let bytes = match encoding_rs::Encoding::for_bom(&bytes) {
+            Some((bom_encoding, bom_len)) if bom_encoding == encoding => &bytes[bom_len..],
+            _ => &bytes[..],
+        };
+        let input = decode(encoding, bytes)?;
+        Ok(self.parse(&input)?.into_owned())
+    }
+
+    /// Reads all data from the given reader, then decodes and parses it
+    /// as in [`parse_bytes`](Parser::parse_bytes), detecting its character encoding.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::Parser;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let data: &[u8] = b"\xFF\xFE<\0p\0>\0\xE9\0<\0/\0p\0>\0";
+    /// let fragment = Parser::new().parse_reader_auto(data)?;
+    /// assert_eq!(fragment.to_string(), "<p>é</p>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "encoding")]
+    pub fn parse_reader_auto<R: std::io::Read>(
+        &self,
+        mut reader: R,
+    ) -> crate::Result<SgmlFragment<'static>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.parse_bytes(&bytes)
+    }
+
+    /// Decodes the given bytes, detecting their character encoding, then parses them.
+    ///
+    /// The encoding is chosen by the first of these that applies:
+    ///
+    /// 1. A byte order mark for UTF-8, UTF-16LE or UTF-16BE, which is then skipped;
+    /// 2. The `encoding` pseudo-attribute of an XML declaration
+    ///    (`<?xml version="1.0" encoding="ISO-8859-1"?>`) at the very start of the data;
+    /// 3. UTF-8, otherwise.
+    ///
+    /// Encoding names are resolved as specified by the [WHATWG Encoding Standard],
+    /// which treats some of them as aliases (`ISO-8859-1` is decoded as `windows-1252`, for instance).
+    /// SGML declarations are not looked at, since they describe character sets
+    /// rather than naming an encoding.
+    ///
+    /// Fails with [`Error::UnknownEncoding`](crate::Error::UnknownEncoding)
+    /// if the declared encoding is not recognized, and with an
+    /// [`IoError`](crate::Error::IoError) if the data is not valid in its encoding.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::Parser;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let data = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><p>caf\xE9</p>";
+    /// let fragment = Parser::builder().ignore_processing_instructions(true).build().parse_bytes(data)?;
+    /// assert_eq!(fragment.to_string(), "<p>café</p>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [WHATWG Encoding Standard]: https://encoding.spec.whatwg.org/
+    #[cfg(feature = "encoding")]
+    pub fn parse_bytes(&self, bytes: &[u8]) -> crate::Result<SgmlFragment<'static>> {
+        let input = self.decode_sniffed(bytes)?;
+        Ok(self.parse(&input)?.into_owned())
+    }
+
+    /// Decodes text according to its byte order mark or XML declaration,
+    /// as described in [`parse_bytes`](Parser::parse_bytes).
+    #[cfg(feature = "encoding")]
+    fn decode_sniffed<'b>(&self, bytes: &'b [u8]) -> crate::Result<Cow<'b, str>> {
+        use encoding_rs::{Encoding, UTF_8};
+
+        let (encoding, bytes) = match Encoding::for_bom(bytes) {
+            Some((encoding, bom_len)) => (encoding, &bytes[bom_len..]),
+            None => match self.declared_encoding(bytes) {
+                Some(label) => {
+                    let encoding = Encoding::for_label(label.as_bytes())
+                        .ok_or_else(|| crate::Error::UnknownEncoding(label.into()))?;
+                    (encoding, bytes)
+                }
+                None => (UTF_8, bytes),
+            },
+        };
+        Ok(decode(encoding, bytes)?)
+    }
+
+    /// Reads the encoding named by an XML declaration at the start of the given bytes.
+    #[cfg(feature = "encoding")]
+    fn declared_encoding<'b>(&self, bytes: &'b [u8]) -> Option<&'b str> {
+        type E<'a> = nom::error::Error<&'a str>;
+        if !bytes.starts_with(b"<?xml") {
+            return None;
+        }
+        let end = bytes.iter().position(|&b| b == b'>')?;
+        let declaration = core::str::from_utf8(&bytes[..=end]).ok()?;
+        match events::xml_declaration::<E>(declaration, &self.config) {
+            Ok((
+                _,
+                SgmlEvent::XmlDeclaration {
+                    encoding: Some(Cow::Borrowed(encoding)),
+                    ..
+                },
+            )) => Some(encoding),
+            _ => None,
+        }
+    }
+
+    /// Parses the given input lazily, yielding events one at a time.
+    ///
+    /// This avoids collecting all events into memory, and allows stopping
+    /// early without parsing the remainder of the document.
+    /// If a parse error is found, it is yielded as an `Err` item,
+    /// after which the iterator ends.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::{Parser, SgmlEvent};
+    /// let parser = Parser::new();
+    /// let mut events = parser.parse_iter(r#"<a><b>text</b><c class="unterminated></a>"#);
+    /// assert_eq!(events.next().unwrap().unwrap(), SgmlEvent::OpenStartTag { name: "a".into() });
+    /// assert!(events.any(|event| event.is_err()));
+    /// assert!(events.next().is_none());
+    /// ```
+    pub fn parse_iter<'a, 'p>(&'p self, input: &'a str) -> ParseIter<'a, 'p> {
+        ParseIter {
+            input,
+            events: self.parse_iter_with_detailed_errors(input),
+        }
+    }
+
+    /// Parses the given input lazily, using a different error handler for parser errors.
+    ///
+    /// See [`parse_iter`](Parser::parse_iter) and
+    /// [`parse_with_detailed_errors`](Parser::parse_with_detailed_errors) for details.
+    pub fn parse_iter_with_detailed_errors<'a, 'p, E>(
+        &'p self,
+        input: &'a str,
+    ) -> events::DocumentEvents<'a, 'p, E>
+    where
+        E: nom::error::ParseError<&'a str>
+            + nom::error::ContextError<&'a str>
+            + nom::error::FromExternalError<&'a str, crate::Error>,
+    {
+        events::DocumentEvents::new(input, &self.config)
+    }
+
+    /// Parses the given input, reporting its contents to the given [`Handler`]
+    /// instead of collecting events.
+    ///
+    /// The input is parsed lazily, as in [`parse_iter`](Parser::parse_iter),
+    /// so the handler sees the document up to the first parse error, which is then returned.
+    /// If the handler returns an error, parsing stops immediately.
+    pub fn parse_with_handler<H: Handler>(
+        &self,
+        input: &str,
+        handler: &mut H,
+    ) -> Result<(), H::Error> {
+        let mut dispatcher = handler::Dispatcher::new(handler);
+        for event in self.parse_iter(input) {
+            dispatcher.dispatch(event?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes text in the given encoding, rejecting malformed data.
+#[cfg(feature = "encoding")]
+fn decode<'b>(
+    encoding: &'static encoding_rs::Encoding,
+    bytes: &'b [u8],
+) -> std::io::Result<Cow<'b, str>> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("data is not valid {}", encoding.name()),
+            )
+        })
+}
+
+/// Statistics about a parsed document, as returned by [`Parser::parse_with_stats`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ParseStats {
+    /// The number of events produced.
+    pub events: usize,
+    /// The number of entity references (`&name;`) that were replaced,
+    /// including those in attribute values. Character references (`&#123;`)
+    /// are not counted.
+    pub entity_expansions: usize,
+    /// The largest number of elements open at the same time. Elements closed by
+    /// an end tag for an enclosing element count as closed from then on.
+    pub max_depth: usize,
+    /// The number of characters of text content, after entity expansion and
+    /// whitespace trimming; text in attribute values and markup is not counted.
+    pub characters: usize,
+}
+
+/// An iterator over the events of a document, which are parsed lazily.
+///
+/// This is created by [`Parser::parse_iter`].
+#[derive(Debug)]
+pub struct ParseIter<'a, 'p> {
+    input: &'a str,
+    events: events::DocumentEvents<'a, 'p, ContextualizedError<&'a str>>,
+}
+
+impl<'a, 'p> Iterator for ParseIter<'a, 'p> {
+    type Item = crate::Result<SgmlEvent<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let input = &self.input;
+        self.events
+            .next()
+            .map(|result| result.map_err(|err| crate::Error::ParseError(err.describe(input))))
+    }
 }
 
+impl FusedIterator for ParseIter<'_, '_> {}
+
 /// The configuration for a [`Parser`].
+#[derive(Clone)]
 pub struct ParserConfig {
+    /// Defines how whitespace in text should be handled.
+    /// Defaults to [`WhitespaceHandling::Trim`].
+    pub whitespace_handling: WhitespaceHandling,
     /// When `true`, leading and trailing whitespace from
     /// [`Character`](crate::SgmlEvent::Character) events will be trimmed.
     /// Defaults to `true`.
+    ///
+    /// [`ParserBuilder`] keeps this in sync with
+    /// [`whitespace_handling`](Self::whitespace_handling). Setting it to `false`
+    /// turns [`Trim`](WhitespaceHandling::Trim) into [`Keep`](WhitespaceHandling::Keep);
+    /// other modes don't trim regardless.
+    #[deprecated(note = "use `whitespace_handling` instead")]
     pub trim_whitespace: bool,
+    /// When `true`, `\r\n` and lone `\r` line endings in text are rewritten as `\n`.
+    /// Text within `CDATA` marked sections is left untouched.
+    /// Defaults to `false`.
+    pub normalize_newlines: bool,
     /// Defines how tag and attribute names should be handled.
     pub name_normalization: NameNormalization,
+    /// When `true`, hexadecimal character references (`&#x6f;`) are expanded,
+    /// even though they are only valid in XML. When `false`, they are
+    /// treated as entity references named `#x6f`.
+    /// Defaults to `true`.
+    pub allow_hex_char_refs: bool,
+    /// When `true`, the standard function character references `&#RE;`, `&#RS;`,
+    /// `&#SPACE;` and `&#TAB;` are resolved without consulting the entity closure.
+    /// See [`entities::function_char`] for their replacement text.
+    /// Defaults to `false`.
+    pub resolve_function_chars: bool,
+    /// When `true`, the entities predefined by XML (`&amp;`, `&lt;`, `&gt;`, `&quot;`
+    /// and `&apos;`) are resolved in text and attribute values, whenever
+    /// neither the internal subset nor the entity closure defines them.
+    /// See [`entities::xml_entity`].
+    /// Defaults to `false`.
+    pub predefined_xml_entities: bool,
+    /// Defines how entity names should be handled before being looked up.
+    /// Does not affect character references (`&#33;`).
+    pub entity_name_normalization: NameNormalization,
     pub marked_section_handling: MarkedSectionHandling,
+    /// When `true`, marked sections must be written exactly as in XML, `<![CDATA[`,
+    /// with no whitespace or parameter entities in the status keyword;
+    /// any other marked section, including `INCLUDE` and `IGNORE`, is rejected.
+    /// Accepted sections are still handled according to [`marked_section_handling`].
+    /// Defaults to `false`.
+    ///
+    /// [`marked_section_handling`]: Self::marked_section_handling
+    pub xml_marked_sections: bool,
     pub ignore_markup_declarations: bool,
+    /// When `true`, markup declarations other than `DOCTYPE`, `ENTITY`, `ELEMENT`,
+    /// `ATTLIST`, `NOTATION` and `SGML`, including those in the internal subset
+    /// of the `DOCTYPE` declaration, are rejected with a parse error.
+    /// Defaults to `false`.
+    pub strict_markup_declarations: bool,
+    /// When `true`, the markup declarations in the internal subset of the `DOCTYPE`
+    /// declaration are emitted as separate events, after the `DOCTYPE` itself.
+    /// Defaults to `false`.
+    pub split_internal_subset: bool,
     pub ignore_processing_instructions: bool,
+    /// When `true`, processing instructions are emitted as
+    /// [`StructuredProcessingInstruction`](crate::SgmlEvent::StructuredProcessingInstruction)
+    /// events, split into target and data.
+    /// Defaults to `false`.
+    pub structured_processing_instructions: bool,
+    /// When `true`, an `xml` processing instruction at the start of the document
+    /// is emitted as an [`XmlDeclaration`](crate::SgmlEvent::XmlDeclaration) event.
+    /// Defaults to `false`.
+    pub recognize_xml_declaration: bool,
+    /// When `true`, attributes with unquoted values (`COLSPAN=2`) are emitted as
+    /// [`UnquotedAttribute`](crate::SgmlEvent::UnquotedAttribute) events.
+    /// Defaults to `false`.
+    pub unquoted_attribute_events: bool,
+    /// When `true`, attributes with quoted values (`CLASS='x'`) are emitted as
+    /// [`QuotedAttribute`](crate::SgmlEvent::QuotedAttribute) events,
+    /// which record the quote character.
+    /// Defaults to `false`.
+    pub quoted_attribute_events: bool,
+    /// When `false`, attributes written without a value (`<input disabled>`)
+    /// are rejected with a parse error.
+    /// Defaults to `true`.
+    pub allow_minimized_attributes: bool,
+    /// When set, start tags with more attributes than this are rejected with a parse error.
+    /// Defaults to `None` (no limit).
+    pub max_attributes_per_element: Option<usize>,
+    /// When set, inputs longer than this many bytes are rejected with a parse error
+    /// before parsing starts.
+    /// Defaults to `None` (no limit).
+    pub max_input_len: Option<usize>,
+    /// When set, parsing stops with a parse error once the input produces
+    /// more events than this.
+    /// Defaults to `None` (no limit).
+    pub max_events: Option<usize>,
+    /// When `true`, start tags declaring the same attribute more than once are
+    /// rejected with a parse error. Names are compared after normalization.
+    /// Defaults to `false`.
+    pub reject_duplicate_attributes: bool,
+    /// When `true`, reaching the end of the input while elements are still open,
+    /// or right after a tag open delimiter (`<` or `</`), is a parse error.
+    /// Defaults to `false`.
+    pub require_balanced: bool,
+    /// When `true`, a byte order mark (U+FEFF) at the start of the input is skipped.
+    /// Defaults to `false`.
+    pub strip_bom: bool,
+    /// When `true`, content at the end of the input that cannot be parsed as
+    /// an event is ignored, instead of being rejected with a parse error.
+    /// Defaults to `false`.
+    pub allow_trailing_content: bool,
+    /// When `true`, entities declared in the internal subset of the `DOCTYPE`
+    /// declaration (`<!DOCTYPE example [ <!ENTITY x "y"> ]>`) are used to
+    /// expand references in the document content, taking precedence over the
+    /// configured entity closures.
+    /// Defaults to `false`.
+    pub resolve_internal_entities: bool,
+    static_entities: BTreeMap<&'static str, &'static str>,
+    short_references: BTreeMap<String, String>,
     entity_fn: Option<EntityFn>,
     parameter_entity_fn: Option<EntityFn>,
+    external_entity_fn: Option<ExternalEntityFn>,
+    whitespace_fn: Option<WhitespaceFn>,
+    sdata_fn: Option<SDataFn>,
+    content_mode_fn: Option<ContentModeFn>,
+    minimized_attribute_fn: Option<MinimizedAttributeFn>,
+    name_validation_fn: Option<NameValidationFn>,
 }
 
-type EntityFn = Box<dyn Fn(&str) -> Option<Cow<'static, str>>>;
+type EntityFn = Arc<dyn Fn(&str) -> Option<Cow<'static, str>> + Send + Sync>;
+type ExternalEntityFn =
+    Arc<dyn Fn(&ExternalEntityDeclaration) -> Option<Cow<'static, str>> + Send + Sync>;
+type SDataFn = Arc<dyn Fn(&str, &str) -> Option<Cow<'static, str>> + Send + Sync>;
+type WhitespaceFn = Arc<dyn Fn(char) -> bool + Send + Sync>;
+type ContentModeFn = Arc<dyn Fn(&str, &[(&str, Option<&str>)]) -> ContentMode + Send + Sync>;
+type MinimizedAttributeFn = Arc<dyn Fn(&str, &str) -> Option<Cow<'static, str>> + Send + Sync>;
+type NameValidationFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
 
 impl ParserConfig {
+    /// Tests whether the given character is whitespace according to the configured rules.
+    ///
+    /// Unless overridden with [`ParserBuilder::whitespace`], this is [`text::is_sgml_whitespace`].
+    pub fn is_whitespace(&self, c: char) -> bool {
+        match &self.whitespace_fn {
+            Some(f) => f(c),
+            None => text::is_sgml_whitespace(c),
+        }
+    }
+
+    /// Tests whether the given tag name is accepted by the closure set with
+    /// [`ParserBuilder::validate_names`]. All names are accepted if there is none.
+    pub fn is_valid_name(&self, name: &str) -> bool {
+        match &self.name_validation_fn {
+            Some(f) => f(name),
+            None => true,
+        }
+    }
+
     /// Trims the given text according to the configured rules.
     pub fn trim<'a>(&self, text: &'a str) -> &'a str {
-        if self.trim_whitespace {
-            text.trim_matches(text::is_sgml_whitespace)
+        #[allow(deprecated)]
+        if self.whitespace_handling == WhitespaceHandling::Trim && self.trim_whitespace {
+            text.trim_matches(|c| self.is_whitespace(c))
+        } else {
+            text
+        }
+    }
+
+    /// Rewrites line endings in the given text as `\n`, if configured to do so.
+    pub fn normalize_newlines<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        if self.normalize_newlines && text.contains('\r') {
+            text.replace("\r\n", "\n").replace('\r', "\n").into()
         } else {
             text
         }
     }
 
+    /// Decides how the content of an element should be scanned, given its start tag,
+    /// using the closure set by [`ParserBuilder::on_start_tag`].
+    pub fn content_mode(&self, name: &str, attributes: &[(&str, Option<&str>)]) -> ContentMode {
+        match &self.content_mode_fn {
+            Some(f) => f(name, attributes),
+            None => ContentMode::Normal,
+        }
+    }
+
+    /// Finds the attribute a minimized attribute value (`<ul compact>`) belongs to,
+    /// using the closure set by [`ParserBuilder::expand_minimized_attributes`].
+    pub fn minimized_attribute_name(
+        &self,
+        element: &str,
+        token: &str,
+    ) -> Option<Cow<'static, str>> {
+        self.minimized_attribute_fn
+            .as_ref()
+            .and_then(|f| f(element, token))
+    }
+
     /// Parses the given replaceable character data, returning its final form.
+    ///
+    /// Text without references is returned borrowed. So is text made of a single
+    /// reference, such as `&nbsp;`, when the replacement text is borrowed `'static` data,
+    /// as with [`ParserBuilder::entities`] and the [predefined XML entities].
+    /// Entities declared in the document are only known while parsing it;
+    /// there, a lone reference to one is replaced with a slice of the document,
+    /// unless character references had to be expanded in its declaration.
+    ///
+    /// [predefined XML entities]: ParserBuilder::predefined_xml_entities
     pub fn parse_rcdata<'a, E>(&self, rcdata: &'a str) -> Result<Cow<'a, str>, nom::Err<E>>
     where
         E: nom::error::ContextError<&'a str> + nom::error::FromExternalError<&'a str, crate::Error>,
     {
-        let f = self.entity_fn.as_deref().unwrap_or(&|_| None);
-        entities::expand_entities(rcdata, f).map_err(|err| into_nom_failure(rcdata, err))
+        Scope::new(self).parse_rcdata_counting(rcdata, &mut 0)
+    }
+
+    /// Parses text found in element content, applying short references
+    /// (see [`ParserBuilder::short_references`]) before expanding entity references.
+    pub fn parse_text<'a, E>(&self, text: &'a str) -> Result<Cow<'a, str>, nom::Err<E>>
+    where
+        E: nom::error::ContextError<&'a str> + nom::error::FromExternalError<&'a str, crate::Error>,
+    {
+        Scope::new(self).parse_text_counting(text, &mut 0)
+    }
+
+    /// Looks up an entity in the sources set up through [`ParserBuilder`],
+    /// whose replacement texts don't borrow from the configuration.
+    fn lookup_configured_entity(&self, entity: &str) -> Option<Cow<'static, str>> {
+        self.static_entities
+            .get(entity)
+            .map(|&text| text.into())
+            .or_else(|| self.entity_fn.as_ref().and_then(|f| f(entity)))
+            .or_else(|| {
+                if self.predefined_xml_entities {
+                    entities::xml_entity(entity).map(Cow::Borrowed)
+                } else {
+                    None
+                }
+            })
     }
 
     /// Parses parameter entities in the given markup declaration text, returning its final form.
@@ -130,12 +722,264 @@ impl ParserConfig {
         &self,
         text: &'a str,
     ) -> Result<Cow<'a, str>, nom::Err<E>>
+    where
+        E: nom::error::ContextError<&'a str> + nom::error::FromExternalError<&'a str, crate::Error>,
+    {
+        Scope::new(self).parse_markup_declaration_text(text)
+    }
+
+    /// Whether the `DOCTYPE` internal subset needs to be read for entity declarations.
+    pub(crate) fn declares_entities(&self) -> bool {
+        self.resolve_internal_entities || self.external_entity_fn.is_some()
+    }
+}
+
+/// The entities declared in the internal subset of a document's `DOCTYPE` declaration.
+///
+/// Replacement texts are kept as found in the document, borrowing from it
+/// unless character references had to be expanded.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DeclaredEntities<'a> {
+    general: BTreeMap<String, (EntityKind, Cow<'a, str>)>,
+    parameter: BTreeMap<String, Cow<'a, str>>,
+}
+
+impl<'a> DeclaredEntities<'a> {
+    /// Registers the entities declared in the given declaration subset,
+    /// as found in `<!DOCTYPE example [ ... ]>`.
+    ///
+    /// Entities declared with literal values are registered if
+    /// [`resolve_internal_entities`](ParserConfig::resolve_internal_entities) is enabled;
+    /// external entities, if they can be resolved through the closure set by
+    /// [`ParserBuilder::resolve_external_entities`].
+    ///
+    /// As in SGML, the first declaration of an entity is the one that prevails;
+    /// declarations with literal values are registered first.
+    pub(crate) fn declare(&mut self, config: &ParserConfig, subset: &'a str) {
+        if config.resolve_internal_entities {
+            self.declare_literal_entities(config, subset);
+        }
+        if let Some(f) = &config.external_entity_fn {
+            for decl in dtd::external_entity_declarations(subset) {
+                let name = config.entity_name_normalization.normalize(decl.name.into());
+                if decl.is_parameter {
+                    if let Entry::Vacant(entry) = self.parameter.entry(name.into_owned()) {
+                        if let Some(text) = f(&decl) {
+                            entry.insert(text);
+                        }
+                    }
+                } else if let Entry::Vacant(entry) = self.general.entry(name.into_owned()) {
+                    if let Some(text) = f(&decl) {
+                        entry.insert((EntityKind::Text, text));
+                    }
+                }
+            }
+        }
+    }
+
+    fn declare_literal_entities(&mut self, config: &ParserConfig, subset: &'a str) {
+        for decl in dtd::entity_declarations(subset) {
+            let name = config.entity_name_normalization.normalize(decl.name.into());
+            if decl.is_parameter {
+                self.parameter.entry(name.into_owned()).or_insert(decl.text);
+            } else {
+                self.general
+                    .entry(name.into_owned())
+                    .or_insert((decl.kind, decl.text));
+            }
+        }
+    }
+}
+
+/// The configuration in effect while parsing a document, along with
+/// the entities declared in it, if any.
+///
+/// This dereferences to the [`ParserConfig`], so it can be passed
+/// wherever only the configuration is needed.
+#[derive(Clone, Copy)]
+pub(crate) struct Scope<'a, 'c> {
+    config: &'c ParserConfig,
+    entities: Option<&'c DeclaredEntities<'a>>,
+}
+
+impl<'a, 'c> Scope<'a, 'c> {
+    /// Creates a scope where no entities are declared.
+    pub(crate) fn new(config: &'c ParserConfig) -> Self {
+        Scope {
+            config,
+            entities: None,
+        }
+    }
+
+    /// Creates a scope where the given entities are declared.
+    pub(crate) fn with_entities(
+        config: &'c ParserConfig,
+        entities: &'c DeclaredEntities<'a>,
+    ) -> Self {
+        Scope {
+            config,
+            entities: Some(entities),
+        }
+    }
+
+    /// Parses replaceable character data like [`ParserConfig::parse_rcdata`],
+    /// adding the number of entity references replaced to `expansions`.
+    pub(crate) fn parse_rcdata_counting<E>(
+        &self,
+        rcdata: &'a str,
+        expansions: &mut usize,
+    ) -> Result<Cow<'a, str>, nom::Err<E>>
+    where
+        E: nom::error::ContextError<&'a str> + nom::error::FromExternalError<&'a str, crate::Error>,
+    {
+        // Without references, there is nothing to expand
+        if !rcdata.contains('&') {
+            return Ok(rcdata.into());
+        }
+        // Text made of a single reference can be replaced without copying
+        if let Some(replacement) = self.resolve_lone_entity(rcdata) {
+            *expansions += 1;
+            return Ok(replacement);
+        }
+        let f = |entity: &str| {
+            let replacement = self.resolve_entity(entity);
+            *expansions += replacement.is_some() as usize;
+            replacement
+        };
+        let result = if self.allow_hex_char_refs {
+            entities::expand_entities(rcdata, f)
+        } else {
+            entities::expand_entities_without_hex(rcdata, f)
+        };
+        result.map_err(|err| into_nom_failure(rcdata, err))
+    }
+
+    /// Parses text found in element content like [`ParserConfig::parse_text`],
+    /// adding the number of entity references replaced to `expansions`.
+    pub(crate) fn parse_text_counting<E>(
+        &self,
+        text: &'a str,
+        expansions: &mut usize,
+    ) -> Result<Cow<'a, str>, nom::Err<E>>
+    where
+        E: nom::error::ContextError<&'a str> + nom::error::FromExternalError<&'a str, crate::Error>,
+    {
+        if self.short_references.is_empty() {
+            return self.parse_rcdata_counting(text, expansions);
+        }
+        let mut output = String::new();
+        let mut rest = text;
+        loop {
+            // The earliest delimiter wins; among those starting at the same place, the longest
+            let next = self
+                .short_references
+                .iter()
+                .filter_map(|(delimiter, entity)| {
+                    rest.find(delimiter.as_str())
+                        .map(|pos| (pos, delimiter, entity))
+                })
+                .min_by_key(|&(pos, delimiter, _)| (pos, Reverse(delimiter.len())));
+            let (pos, delimiter, entity) = match next {
+                Some(next) => next,
+                // No delimiters at all, so nothing to allocate
+                None if rest.len() == text.len() => {
+                    return self.parse_rcdata_counting(text, expansions)
+                }
+                None => break,
+            };
+            output.push_str(&self.parse_rcdata_counting(&rest[..pos], expansions)?);
+            let start = text.len() - rest.len() + pos;
+            let replacement = self.resolve_entity(entity).ok_or_else(|| {
+                let err = entities::EntityError {
+                    entity: entity.clone(),
+                    position: start..start + delimiter.len(),
+                };
+                into_nom_failure(text, err)
+            })?;
+            *expansions += 1;
+            output.push_str(&replacement);
+            rest = &rest[pos + delimiter.len()..];
+        }
+        output.push_str(&self.parse_rcdata_counting(rest, expansions)?);
+        Ok(output.into())
+    }
+
+    /// Looks up the replacement text of an entity in all configured sources.
+    fn resolve_entity(&self, entity: &str) -> Option<Cow<'_, str>> {
+        if self.resolve_function_chars {
+            let function_char = entity.strip_prefix('#').and_then(entities::function_char);
+            if let Some(replacement) = function_char {
+                return Some(replacement.into());
+            }
+        }
+        let entity = self.entity_name_normalization.normalize(entity.into());
+        self.lookup_declared_entity(&entity)
+            .or_else(|| self.lookup_configured_entity(&entity))
+    }
+
+    /// Resolves text consisting of exactly one entity reference (`&name;`),
+    /// returning the replacement text without copying it when it is either
+    /// a slice of the document or `'static`.
+    ///
+    /// Returns `None` if the text is anything else, or if the replacement text
+    /// must be expanded as usual, as for `SDATA` entities.
+    fn resolve_lone_entity(&self, text: &str) -> Option<Cow<'a, str>> {
+        let name = text.strip_prefix('&')?.strip_suffix(';')?;
+        if raw::name::<()>(name) != Ok(("", name)) {
+            return None;
+        }
+        let name = self.entity_name_normalization.normalize(name.into());
+        match self
+            .entities
+            .and_then(|entities| entities.general.get(&*name))
+        {
+            Some((EntityKind::Text | EntityKind::CData, Cow::Borrowed(text))) => {
+                Some(Cow::Borrowed(*text))
+            }
+            Some(_) => None,
+            None => self.lookup_configured_entity(&name),
+        }
+    }
+
+    /// Parses parameter entities like [`ParserConfig::parse_markup_declaration_text`],
+    /// including those declared in the document.
+    pub(crate) fn parse_markup_declaration_text<E>(
+        &self,
+        text: &'a str,
+    ) -> Result<Cow<'a, str>, nom::Err<E>>
     where
         E: nom::error::ContextError<&'a str> + nom::error::FromExternalError<&'a str, crate::Error>,
     {
         let f = self.parameter_entity_fn.as_deref().unwrap_or(&|_| None);
+        let f = |entity: &str| {
+            let entity = self.entity_name_normalization.normalize(entity.into());
+            self.entities
+                .and_then(|entities| entities.parameter.get(&*entity))
+                .map(|text| Cow::Borrowed(text.as_ref()))
+                .or_else(|| f(&entity))
+        };
         entities::expand_parameter_entities(text, f).map_err(|err| into_nom_failure(text, err))
     }
+
+    /// Looks up a general entity declared in the document,
+    /// passing `SDATA` text through the configured mapping, if any.
+    fn lookup_declared_entity(&self, name: &str) -> Option<Cow<'_, str>> {
+        let (kind, text) = self.entities?.general.get(name)?;
+        if *kind == EntityKind::SData {
+            if let Some(mapped) = self.sdata_fn.as_ref().and_then(|f| f(name, text)) {
+                return Some(mapped);
+            }
+        }
+        Some(Cow::Borrowed(text.as_ref()))
+    }
+}
+
+impl core::ops::Deref for Scope<'_, '_> {
+    type Target = ParserConfig;
+
+    fn deref(&self) -> &ParserConfig {
+        self.config
+    }
 }
 
 /// How tag and attribute names should be handled.
@@ -169,6 +1013,43 @@ impl NameNormalization {
     }
 }
 
+/// How whitespace in text should be handled.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WhitespaceHandling {
+    /// Trim leading and trailing whitespace from [`Character`](crate::SgmlEvent::Character)
+    /// events, dropping whitespace-only text altogether.
+    Trim,
+    /// Keep all whitespace as part of [`Character`](crate::SgmlEvent::Character) events.
+    Keep,
+    /// Emit whitespace-only runs of text (such as indentation between tags) as
+    /// [`Whitespace`](crate::SgmlEvent::Whitespace) events, and keep other text untrimmed.
+    ///
+    /// Only text written as whitespace is affected: character references
+    /// (like `&#32;`) and `CDATA` marked sections still produce `Character` events.
+    Separate,
+}
+
+// `#[default]` on variants requires Rust 1.62
+#[allow(clippy::derivable_impls)]
+impl Default for WhitespaceHandling {
+    fn default() -> Self {
+        WhitespaceHandling::Trim
+    }
+}
+
+/// How the content of an element should be scanned; see [`ParserBuilder::on_start_tag`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContentMode {
+    /// Parse the content as usual, recognizing tags and other markup.
+    Normal,
+    /// Treat everything up to the element's end tag as character data,
+    /// like the content of a `CDATA` marked section.
+    CData,
+    /// Treat everything up to the element's end tag as character data,
+    /// expanding only entity and character references.
+    RcData,
+}
+
 /// How marked sections (`<![CDATA[example]]>`) should be handled.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum MarkedSectionHandling {
@@ -229,15 +1110,46 @@ where
 
 impl Default for ParserConfig {
     /// Creates a new, default `ParserConfig`. See [`Parser::new`] for the default settings.
+    #[allow(deprecated)]
     fn default() -> Self {
         ParserConfig {
+            whitespace_handling: Default::default(),
             trim_whitespace: true,
+            normalize_newlines: false,
             name_normalization: Default::default(),
+            allow_hex_char_refs: true,
+            resolve_function_chars: false,
+            predefined_xml_entities: false,
+            entity_name_normalization: Default::default(),
             marked_section_handling: Default::default(),
+            xml_marked_sections: false,
             ignore_markup_declarations: false,
+            strict_markup_declarations: false,
+            split_internal_subset: false,
             ignore_processing_instructions: false,
+            structured_processing_instructions: false,
+            recognize_xml_declaration: false,
+            unquoted_attribute_events: false,
+            quoted_attribute_events: false,
+            allow_minimized_attributes: true,
+            max_attributes_per_element: None,
+            max_input_len: None,
+            max_events: None,
+            reject_duplicate_attributes: false,
+            require_balanced: false,
+            strip_bom: false,
+            allow_trailing_content: false,
+            resolve_internal_entities: false,
+            static_entities: BTreeMap::new(),
+            short_references: BTreeMap::new(),
             entity_fn: None,
             parameter_entity_fn: None,
+            external_entity_fn: None,
+            whitespace_fn: None,
+            sdata_fn: None,
+            content_mode_fn: None,
+            minimized_attribute_fn: None,
+            name_validation_fn: None,
         }
     }
 }
@@ -245,10 +1157,20 @@ impl Default for ParserConfig {
 impl fmt::Debug for ParserConfig {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("ParserConfig")
-            .field("trim_whitespace", &self.trim_whitespace)
+            .field("whitespace_handling", &self.whitespace_handling)
+            .field("normalize_newlines", &self.normalize_newlines)
             .field("process_marked_sections", &self.marked_section_handling)
             .field("expand_entity", &omit(&self.entity_fn))
             .field("expand_parameter_entity", &omit(&self.parameter_entity_fn))
+            .field("resolve_external_entity", &omit(&self.external_entity_fn))
+            .field("whitespace", &omit(&self.whitespace_fn))
+            .field("map_sdata_entity", &omit(&self.sdata_fn))
+            .field("on_start_tag", &omit(&self.content_mode_fn))
+            .field(
+                "expand_minimized_attributes",
+                &omit(&self.minimized_attribute_fn),
+            )
+            .field("validate_names", &omit(&self.name_validation_fn))
             .finish()
     }
 }
@@ -267,15 +1189,105 @@ impl ParserBuilder {
     }
 
     /// Defines whether whitespace surrounding text should be trimmed.
-    pub fn trim_whitespace(mut self, trim_whitespace: bool) -> Self {
-        self.config.trim_whitespace = trim_whitespace;
-        self
+    ///
+    /// This is a shorthand for [`whitespace_handling`](ParserBuilder::whitespace_handling)
+    /// with either [`Trim`](WhitespaceHandling::Trim) or [`Keep`](WhitespaceHandling::Keep).
+    pub fn trim_whitespace(self, trim_whitespace: bool) -> Self {
+        self.whitespace_handling(if trim_whitespace {
+            WhitespaceHandling::Trim
+        } else {
+            WhitespaceHandling::Keep
+        })
     }
 
-    /// Defines how tag and attribute names should be normalized.
-    pub fn name_normalization(mut self, name_normalization: NameNormalization) -> Self {
-        self.config.name_normalization = name_normalization;
-        self
+    /// Changes how whitespace in text should be handled.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::parser::WhitespaceHandling;
+    /// # use sgmlish::SgmlEvent;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .whitespace_handling(WhitespaceHandling::Separate)
+    ///     .parse("<a>\n  <b> text </b>\n</a>")?;
+    /// assert_eq!(sgml.as_slice()[2], SgmlEvent::Whitespace("\n  ".into()));
+    /// assert_eq!(sgml.as_slice()[5], SgmlEvent::Character(" text ".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn whitespace_handling(mut self, handling: WhitespaceHandling) -> Self {
+        self.config.whitespace_handling = handling;
+        #[allow(deprecated)]
+        {
+            self.config.trim_whitespace = handling == WhitespaceHandling::Trim;
+        }
+        self
+    }
+
+    /// Changes whether `\r\n` and lone `\r` line endings in text should be rewritten as `\n`.
+    ///
+    /// This is disabled by default. Text within `CDATA` marked sections is never rewritten,
+    /// while line endings produced by character references (`&#13;`) are.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .normalize_newlines(true)
+    ///     .parse("<a>one\r\ntwo\rthree</a>")?;
+    /// assert_eq!(sgml.to_string(), "<a>one\ntwo\nthree</a>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn normalize_newlines(mut self, normalize: bool) -> Self {
+        self.config.normalize_newlines = normalize;
+        self
+    }
+
+    /// Defines a closure to be used to identify whitespace characters.
+    ///
+    /// The closure is used when trimming text and when skipping separators
+    /// between attributes in start tags. The default is [`text::is_sgml_whitespace`].
+    ///
+    /// Transforms applied after parsing do not see this setting; to collapse the same
+    /// characters, pass the closure to [`transforms::collapse_whitespace_with`](crate::transforms::collapse_whitespace_with).
+    pub fn whitespace<F>(mut self, f: F) -> Self
+    where
+        F: Fn(char) -> bool + Send + Sync + 'static,
+    {
+        self.config.whitespace_fn = Some(Arc::new(f));
+        self
+    }
+
+    /// Defines the exact set of characters that should be treated as whitespace.
+    ///
+    /// This replaces the default set (`" \t\r\n"`), so it should be included
+    /// when the intention is to extend it.
+    ///
+    /// # Example
+    ///
+    /// Also treating form feeds as whitespace:
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .whitespace_chars(" \t\r\n\x0c")
+    ///     .parse("<P>\x0cHello\x0c</P>")?;
+    /// assert_eq!(sgml.as_slice()[2], sgmlish::SgmlEvent::Character("Hello".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn whitespace_chars(self, chars: &str) -> Self {
+        let chars = chars.to_owned();
+        self.whitespace(move |c| chars.contains(c))
+    }
+
+    /// Defines how tag and attribute names should be normalized.
+    pub fn name_normalization(mut self, name_normalization: NameNormalization) -> Self {
+        self.config.name_normalization = name_normalization;
+        self
     }
 
     /// Normalizes all tag and attribute names to lowercase.
@@ -318,10 +1330,198 @@ impl ParserBuilder {
     /// ```
     pub fn expand_entities<F, T>(mut self, f: F) -> Self
     where
-        F: Fn(&str) -> Option<T> + 'static,
+        F: Fn(&str) -> Option<T> + Send + Sync + 'static,
         T: Into<Cow<'static, str>>,
     {
-        self.config.entity_fn = Some(Box::new(move |entity| f(entity).map(Into::into)));
+        self.config.entity_fn = Some(Arc::new(move |entity| f(entity).map(Into::into)));
+        self
+    }
+
+    /// Registers a list of entities, given as pairs of name and replacement text.
+    ///
+    /// This can be called more than once to register more entities; a name registered
+    /// again replaces the previous text. Names are matched after
+    /// [entity name normalization](ParserBuilder::entity_name_normalization).
+    ///
+    /// References are resolved from the first of these sources that knows the entity:
+    ///
+    /// 1. entities declared in the document, if
+    ///    [`resolve_internal_entities`](ParserBuilder::resolve_internal_entities) is enabled;
+    /// 2. entities registered with this method;
+    /// 3. the closure set with [`expand_entities`](ParserBuilder::expand_entities);
+    /// 4. the entities predefined by XML, if
+    ///    [`predefined_xml_entities`](ParserBuilder::predefined_xml_entities) is enabled.
+    ///
+    /// Text consisting of a single reference, like `&nbsp;`, takes the replacement
+    /// text as is, without copying it: from sources 2 to 4, or, for an entity declared
+    /// in the document, as a slice of the document itself.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .entities(&[("amp", "&"), ("nbsp", "\u{a0}")])
+    ///     .expand_entities(|entity| (entity == "amp").then(|| "and"))
+    ///     .parse("<memo>Sonic&nbsp;&amp; Knuckles</memo>")?;
+    /// assert_eq!(sgml.as_slice()[2], sgmlish::SgmlEvent::Character("Sonic\u{a0}& Knuckles".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn entities(mut self, entities: &[(&'static str, &'static str)]) -> Self {
+        self.config.static_entities.extend(entities.iter().copied());
+        self
+    }
+
+    /// Registers short references, given as pairs of delimiter and entity name.
+    ///
+    /// This is a simplified form of SGML's `SHORTREF` maps: in text within element
+    /// content, every occurrence of a delimiter is replaced with the expansion of
+    /// its entity, as if a reference to it had been written instead. Entities are
+    /// resolved like any other reference (see [`entities`](ParserBuilder::entities)),
+    /// and an unknown entity is a parse error. Where delimiters overlap,
+    /// the one that starts first wins, then the longest one.
+    ///
+    /// This can be called more than once to register more short references;
+    /// a delimiter registered again replaces the previous entity. Empty delimiters are ignored.
+    ///
+    /// # Limitations
+    ///
+    /// * The same map applies everywhere, rather than changing with the current element
+    ///   as `USEMAP` would allow.
+    /// * Delimiters are only recognized in text, after any whitespace trimming;
+    ///   not within tags, attribute values, declarations, marked sections,
+    ///   or elements whose content is scanned in a [`ContentMode`] other than `Normal`.
+    /// * With [`WhitespaceHandling::Separate`], whitespace-only text
+    ///   is reported as is, without short references.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .short_references([("--", "mdash")])
+    ///     .entities(&[("mdash", "\u{2014}")])
+    ///     .parse("<p>Wait -- what?</p>")?;
+    /// assert_eq!(sgml.to_string(), "<p>Wait \u{2014} what?</p>");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn short_references<I, K, V>(mut self, short_references: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let short_references = short_references
+            .into_iter()
+            .map(|(delimiter, entity)| (delimiter.into(), entity.into()))
+            .filter(|(delimiter, _)| !delimiter.is_empty());
+        self.config.short_references.extend(short_references);
+        self
+    }
+
+    /// Changes whether hexadecimal character references (`&#x6f;`) are accepted.
+    ///
+    /// These are only valid in XML, but accepted by default.
+    /// When disabled, they are resolved like entity references instead,
+    /// with names such as `#x6f`, so they usually result in errors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder()
+    ///     .allow_hex_char_refs(false)
+    ///     .build();
+    /// assert!(parser.parse("<p>&#111;</p>").is_ok());
+    /// assert!(parser.parse("<p>&#x6f;</p>").is_err());
+    /// ```
+    pub fn allow_hex_char_refs(mut self, allow: bool) -> Self {
+        self.config.allow_hex_char_refs = allow;
+        self
+    }
+
+    /// Changes whether the standard function character references (`&#RE;`, `&#RS;`,
+    /// `&#SPACE;` and `&#TAB;`) should be resolved by the parser.
+    ///
+    /// Record ends (`RE`) become line feeds, and record starts (`RS`) are dropped.
+    /// This is disabled by default, in which case they are passed to the
+    /// [entity closure](ParserBuilder::expand_entities) with names like `#SPACE`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::SgmlEvent;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .resolve_function_chars(true)
+    ///     .parse("<p>one&#SPACE;two&#RE;</p>")?;
+    /// assert_eq!(sgml.as_slice()[2], SgmlEvent::Character("one two\n".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_function_chars(mut self, resolve: bool) -> Self {
+        self.config.resolve_function_chars = resolve;
+        self
+    }
+
+    /// Changes whether the entities predefined by XML (`&amp;`, `&lt;`, `&gt;`, `&quot;`
+    /// and `&apos;`) should be resolved without an [entity closure](ParserBuilder::expand_entities).
+    ///
+    /// They apply to both text and attribute values, and only when the entity is not
+    /// otherwise defined, so a closure can still override them.
+    /// Since [`SgmlFragment`]'s `Display` output escapes characters with
+    /// character references (`&#38;`), it can be parsed back with or without this setting.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::SgmlEvent;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .predefined_xml_entities(true)
+    ///     .parse(r#"<p title="a &amp; b">&lt;tag&gt;</p>"#)?;
+    /// assert_eq!(
+    ///     sgml.as_slice()[1],
+    ///     SgmlEvent::Attribute { name: "title".into(), value: Some("a & b".into()) }
+    /// );
+    /// assert_eq!(sgml.as_slice()[3], SgmlEvent::Character("<tag>".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn predefined_xml_entities(mut self, resolve: bool) -> Self {
+        self.config.predefined_xml_entities = resolve;
+        self
+    }
+
+    /// Defines how entity names should be normalized before being resolved.
+    ///
+    /// The normalized name is the one passed to the closures given to
+    /// [`expand_entities`](ParserBuilder::expand_entities) and
+    /// [`expand_parameter_entities`](ParserBuilder::expand_parameter_entities),
+    /// and also applies to entities declared in the `DOCTYPE` internal subset.
+    /// Character references (`&#33;`) are not affected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::parser::NameNormalization;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let parser = sgmlish::Parser::builder()
+    ///     .entity_name_normalization(NameNormalization::ToLowercase)
+    ///     .expand_entities(|entity| match entity {
+    ///         "eacute" => Some("é"),
+    ///         _ => None,
+    ///     })
+    ///     .build();
+    ///
+    /// let sgml = parser.parse("<p>&eacute; &EACUTE; &Eacute;</p>")?;
+    /// assert_eq!(sgml.as_slice()[2], sgmlish::SgmlEvent::Character("é é é".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn entity_name_normalization(mut self, normalization: NameNormalization) -> Self {
+        self.config.entity_name_normalization = normalization;
         self
     }
 
@@ -329,12 +1529,198 @@ impl ParserBuilder {
     ///
     /// For information on parameter entities and the closure,
     /// see [`entities::expand_parameter_entities`].
+    ///
+    /// Parameter entities are expanded in the status keywords of marked sections
+    /// before they are classified, so that `<![ %HTML.Reserved; [ ... ]]>` is ignored
+    /// if `HTML.Reserved` resolves to `IGNORE`. Without a closure (or a declaration in
+    /// the internal subset), such marked sections are rejected.
     pub fn expand_parameter_entities<F, T>(mut self, f: F) -> Self
     where
-        F: Fn(&str) -> Option<T> + 'static,
+        F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+        T: Into<Cow<'static, str>>,
+    {
+        self.config.parameter_entity_fn = Some(Arc::new(move |entity| f(entity).map(Into::into)));
+        self
+    }
+
+    /// Changes whether entities declared in the `DOCTYPE` internal subset should
+    /// be used to expand entity references in the document.
+    ///
+    /// Both general entities (`<!ENTITY example "text">`) and parameter entities
+    /// (`<!ENTITY % example "INCLUDE">`) are recognized, as long as they are
+    /// declared with a literal value. Declarations take effect in the document
+    /// content, after the prolog; for that reason, the `DOCTYPE` declaration
+    /// must come before any marked section in the prolog.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .resolve_internal_entities(true)
+    ///     .parse(r#"
+    ///         <!DOCTYPE memo [ <!ENTITY product "sgmlish"> ]>
+    ///         <memo>Hello from &product;!</memo>
+    ///     "#)?;
+    /// assert_eq!(sgml.as_slice()[3], sgmlish::SgmlEvent::Character("Hello from sgmlish!".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_internal_entities(mut self, resolve: bool) -> Self {
+        self.config.resolve_internal_entities = resolve;
+        self
+    }
+
+    /// Defines a closure to resolve external entities declared in the `DOCTYPE`
+    /// internal subset, such as `<!ENTITY chapter1 SYSTEM "ch1.sgm">`.
+    ///
+    /// Unlike the closure given to [`expand_entities`](ParserBuilder::expand_entities),
+    /// which is called for every reference, this closure is called once per parse for
+    /// each external entity declared, as soon as the `DOCTYPE` declaration is read,
+    /// so it is a good place to fetch or load the entity text. It receives the
+    /// declaration, with its public and system identifiers; the returned text is
+    /// then used for all references to that entity in the document, like the text
+    /// of an internal entity. Parameter entities are resolved the same way,
+    /// for use in marked section keywords.
+    ///
+    /// When the closure returns `None`, the entity is left undeclared, so references
+    /// to it fall back to the [`expand_entities`](ParserBuilder::expand_entities) closure.
+    /// Entities declared with data types (e.g. `NDATA`) are not passed to the closure.
+    ///
+    /// This works independently of [`resolve_internal_entities`](ParserBuilder::resolve_internal_entities);
+    /// when both are enabled, an entity declared both ways resolves to its literal value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .resolve_external_entities(|decl| match decl.system_id {
+    ///         Some("legal.txt") => Some("All rights reserved."),
+    ///         _ => None,
+    ///     })
+    ///     .parse(r#"
+    ///         <!DOCTYPE memo [ <!ENTITY legal SYSTEM "legal.txt"> ]>
+    ///         <memo>&legal;</memo>
+    ///     "#)?;
+    /// assert_eq!(sgml.as_slice()[3], sgmlish::SgmlEvent::Character("All rights reserved.".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_external_entities<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn(&ExternalEntityDeclaration) -> Option<T> + Send + Sync + 'static,
+        T: Into<Cow<'static, str>>,
+    {
+        self.config.external_entity_fn = Some(Arc::new(move |decl| f(decl).map(Into::into)));
+        self
+    }
+
+    /// Defines how to map the text of `SDATA` entities declared in the internal subset,
+    /// such as `<!ENTITY mdash SDATA "[mdash ]">`.
+    ///
+    /// The closure receives the entity name and its declared text; when it returns `None`,
+    /// the declared text is used verbatim. This only has an effect when
+    /// [`resolve_internal_entities`](ParserBuilder::resolve_internal_entities) is enabled.
+    ///
+    /// `CDATA` entities need no special treatment, since replacement text
+    /// is never scanned for markup.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .resolve_internal_entities(true)
+    ///     .map_sdata_entities(|_name, text| match text {
+    ///         "[mdash ]" => Some("\u{2014}"),
+    ///         _ => None,
+    ///     })
+    ///     .parse(r#"
+    ///         <!DOCTYPE memo [ <!ENTITY mdash SDATA "[mdash ]"> ]>
+    ///         <memo>sgmlish&mdash;SGML</memo>
+    ///     "#)?;
+    /// assert_eq!(sgml.as_slice()[3], sgmlish::SgmlEvent::Character("sgmlish\u{2014}SGML".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn map_sdata_entities<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &str) -> Option<T> + Send + Sync + 'static,
         T: Into<Cow<'static, str>>,
     {
-        self.config.parameter_entity_fn = Some(Box::new(move |entity| f(entity).map(Into::into)));
+        self.config.sdata_fn = Some(Arc::new(move |name, text| f(name, text).map(Into::into)));
+        self
+    }
+
+    /// Defines a closure that decides how the content of each element should be scanned,
+    /// based on its start tag.
+    ///
+    /// The closure receives the element name and its attributes, after normalization
+    /// and entity expansion, and is invoked once the start tag is closed
+    /// (XML-style empty elements, like `<br/>`, have no content and are skipped).
+    /// When it returns [`ContentMode::CData`] or [`ContentMode::RcData`], everything up to
+    /// the next end tag for the same element (compared case-insensitively), or an empty
+    /// end tag (`</>`), is emitted as a single [`Character`](crate::SgmlEvent::Character) event.
+    /// Without an end tag, the content extends to the end of the input.
+    ///
+    /// The closure runs while the parser is borrowed, so it cannot change the configuration;
+    /// it may be invoked more than once for the same tag if the parser backtracks,
+    /// so it should return the same result for the same input.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::parser::ContentMode;
+    /// # use sgmlish::SgmlEvent;
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .on_start_tag(|name, attributes| {
+    ///         if name == "pre" && attributes.iter().any(|(name, _)| *name == "raw") {
+    ///             ContentMode::CData
+    ///         } else {
+    ///             ContentMode::Normal
+    ///         }
+    ///     })
+    ///     .parse("<pre raw><b>not bold</b></pre>")?;
+    /// assert_eq!(sgml.as_slice()[3], SgmlEvent::Character("<b>not bold</b>".into()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_start_tag<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &[(&str, Option<&str>)]) -> ContentMode + Send + Sync + 'static,
+    {
+        self.config.content_mode_fn = Some(Arc::new(f));
+        self
+    }
+
+    /// Defines a closure that decides which tag names are acceptable.
+    ///
+    /// The closure receives the name of every start and end tag, after
+    /// [name normalization](ParserBuilder::name_normalization); when it returns `false`,
+    /// parsing fails with an error naming the tag and pointing to its position.
+    /// Empty tags (`<>` and `</>`) have no name, and are not checked.
+    ///
+    /// By default, any name accepted by the parser is valid: an alphabetic character
+    /// followed by alphanumeric characters, `.`, `-`, `_` or `:`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder()
+    ///     .validate_names(|name| !name.contains(':'))
+    ///     .build();
+    /// assert!(parser.parse("<doc><p>text</p></doc>").is_ok());
+    ///
+    /// let err = parser.parse("<doc><svg:rect></svg:rect></doc>").unwrap_err();
+    /// assert!(err.to_string().contains("invalid tag name: svg:rect"));
+    /// ```
+    pub fn validate_names<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.config.name_validation_fn = Some(Arc::new(f));
         self
     }
 
@@ -352,6 +1738,25 @@ impl ParserBuilder {
         self.marked_section_handling(MarkedSectionHandling::ExpandAll)
     }
 
+    /// Changes whether only XML-style `<![CDATA[` marked sections are accepted.
+    ///
+    /// SGML allows whitespace and parameter entities around the status keyword,
+    /// so `<![ CDATA [` is normally a valid `CDATA` section; in this mode,
+    /// it is rejected, as are `RCDATA`, `INCLUDE` and `IGNORE` sections.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::Parser;
+    /// let parser = Parser::builder().xml_marked_sections(true).build();
+    /// assert!(parser.parse("<a><![CDATA[<b>]]></a>").is_ok());
+    /// assert!(parser.parse("<a><![ CDATA [<b>]]></a>").is_err());
+    /// ```
+    pub fn xml_marked_sections(mut self, strict: bool) -> Self {
+        self.config.xml_marked_sections = strict;
+        self
+    }
+
     /// Changes whether markup declarations (`<!EXAMPLE>`) should be ignored
     /// or present in the event stream.
     pub fn ignore_markup_declarations(mut self, ignore: bool) -> Self {
@@ -359,6 +1764,65 @@ impl ParserBuilder {
         self
     }
 
+    /// Changes whether markup declarations with unrecognized keywords should be rejected.
+    ///
+    /// When disabled (the default), any markup declaration is accepted.
+    /// When enabled, only `DOCTYPE`, `ENTITY`, `ELEMENT`, `ATTLIST`, `NOTATION` and `SGML`
+    /// declarations are, in any case; anything else, like a misspelled `<!ELEMNT>`,
+    /// is a parse error naming the keyword. Declarations in the internal subset
+    /// of the `DOCTYPE` declaration are checked as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder()
+    ///     .strict_markup_declarations(true)
+    ///     .build();
+    /// assert!(parser.parse("<!DOCTYPE doc [ <!ELEMENT doc - - ANY> ]><doc></doc>").is_ok());
+    ///
+    /// let err = parser
+    ///     .parse("<!DOCTYPE doc [ <!ELEMNT doc - - ANY> ]><doc></doc>")
+    ///     .unwrap_err();
+    /// assert!(err.to_string().contains("unrecognized markup declaration: ELEMNT"));
+    /// ```
+    pub fn strict_markup_declarations(mut self, strict: bool) -> Self {
+        self.config.strict_markup_declarations = strict;
+        self
+    }
+
+    /// Changes whether the declarations in the internal subset of the `DOCTYPE`
+    /// declaration should be emitted as separate events.
+    ///
+    /// When disabled (the default), the whole `DOCTYPE` declaration, internal subset
+    /// included, is a single [`MarkupDeclaration`](crate::SgmlEvent::MarkupDeclaration) event.
+    /// When enabled, that event's body omits the subset, and each markup declaration
+    /// found in the subset follows as a `MarkupDeclaration` event of its own.
+    /// Comments, marked sections and parameter entity references in the subset are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use sgmlish::{Parser, SgmlEvent};
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = Parser::builder()
+    ///     .split_internal_subset(true)
+    ///     .parse(r#"<!DOCTYPE doc [ <!ENTITY a "x>y"> <!ELEMENT doc - - ANY> ]><doc></doc>"#)?;
+    /// assert_eq!(
+    ///     sgml.as_slice()[..3],
+    ///     [
+    ///         SgmlEvent::MarkupDeclaration { keyword: "DOCTYPE".into(), body: "doc".into() },
+    ///         SgmlEvent::MarkupDeclaration { keyword: "ENTITY".into(), body: r#"a "x>y""#.into() },
+    ///         SgmlEvent::MarkupDeclaration { keyword: "ELEMENT".into(), body: "doc - - ANY".into() },
+    ///     ]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split_internal_subset(mut self, split: bool) -> Self {
+        self.config.split_internal_subset = split;
+        self
+    }
+
     /// Changes whether processing instructions (`<?example>`) should be ignored
     /// or present in the event stream.
     pub fn ignore_processing_instructions(mut self, ignore: bool) -> Self {
@@ -366,17 +1830,350 @@ impl ParserBuilder {
         self
     }
 
-    /// Builds a new parser from the given configuration.
-    pub fn build(self) -> Parser {
-        Parser {
-            config: self.config,
-        }
-    }
-
-    /// Parses the given input with the built parser.
+    /// Changes whether processing instructions should be split into target and data.
     ///
-    /// To reuse the same parser for multiple inputs, use [`build()`](ParserBuilder::build)
-    /// then [`Parser::parse()`].
+    /// When enabled, processing instructions are emitted as
+    /// [`StructuredProcessingInstruction`](crate::SgmlEvent::StructuredProcessingInstruction)
+    /// events instead of [`ProcessingInstruction`](crate::SgmlEvent::ProcessingInstruction).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .structured_pis(true)
+    ///     .parse(r#"<?xml version="1.0"?><x></x>"#)?;
+    /// assert_eq!(
+    ///     sgml.as_slice()[0],
+    ///     sgmlish::SgmlEvent::StructuredProcessingInstruction {
+    ///         target: "xml".into(),
+    ///         data: r#"version="1.0""#.into(),
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn structured_pis(mut self, structured: bool) -> Self {
+        self.config.structured_processing_instructions = structured;
+        self
+    }
+
+    /// Changes whether an XML declaration at the start of the document should be
+    /// recognized.
+    ///
+    /// When enabled, a leading `<?xml ...?>` processing instruction is emitted as an
+    /// [`XmlDeclaration`](crate::SgmlEvent::XmlDeclaration) event, with its `version`,
+    /// `encoding` and `standalone` pseudo-attributes parsed.
+    /// This applies even when processing instructions are ignored.
+    /// Instructions elsewhere in the document, or whose pseudo-attributes are not
+    /// well-formed, are still treated as regular processing instructions.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .recognize_xml_declaration(true)
+    ///     .parse(r#"<?xml version="1.0" encoding="UTF-8"?><x></x>"#)?;
+    /// assert_eq!(
+    ///     sgml.as_slice()[0],
+    ///     sgmlish::SgmlEvent::XmlDeclaration {
+    ///         version: "1.0".into(),
+    ///         encoding: Some("UTF-8".into()),
+    ///         standalone: None,
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn recognize_xml_declaration(mut self, recognize: bool) -> Self {
+        self.config.recognize_xml_declaration = recognize;
+        self
+    }
+
+    /// Changes whether attributes with unquoted values should be told apart
+    /// from quoted ones.
+    ///
+    /// When enabled, attributes such as `COLSPAN=2` are emitted as
+    /// [`UnquotedAttribute`](crate::SgmlEvent::UnquotedAttribute) events,
+    /// so they can be reproduced without quotes.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .unquoted_attribute_events(true)
+    ///     .parse(r#"<td colspan=2 class="x"></td>"#)?;
+    /// assert_eq!(sgml.to_string(), r#"<td colspan=2 class="x"></td>"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn unquoted_attribute_events(mut self, enabled: bool) -> Self {
+        self.config.unquoted_attribute_events = enabled;
+        self
+    }
+
+    /// Changes whether attributes with quoted values should record their quote character.
+    ///
+    /// When enabled, attributes such as `CLASS='x'` are emitted as
+    /// [`QuotedAttribute`](crate::SgmlEvent::QuotedAttribute) events,
+    /// so they can be reproduced with the same quotes. Combined with
+    /// [`unquoted_attribute_events`](ParserBuilder::unquoted_attribute_events),
+    /// attributes are displayed as they were written, save for entity references.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .quoted_attribute_events(true)
+    ///     .parse(r#"<a href='x' class="y"></a>"#)?;
+    /// assert_eq!(sgml.to_string(), r#"<a href='x' class="y"></a>"#);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn quoted_attribute_events(mut self, enabled: bool) -> Self {
+        self.config.quoted_attribute_events = enabled;
+        self
+    }
+
+    /// Changes whether attributes may be written without a value, as in `<input disabled>`.
+    ///
+    /// This is allowed by default, as in SGML. Disabling it rejects such attributes
+    /// with a parse error naming the attribute, which helps enforce XML-style well-formedness.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder()
+    ///     .allow_minimized_attributes(false)
+    ///     .build();
+    /// assert!(parser.parse(r#"<input disabled="disabled">"#).is_ok());
+    ///
+    /// let err = parser.parse("<input disabled>").unwrap_err();
+    /// assert!(err.to_string().contains("attribute without value: disabled"));
+    /// ```
+    pub fn allow_minimized_attributes(mut self, allow: bool) -> Self {
+        self.config.allow_minimized_attributes = allow;
+        self
+    }
+
+    /// Defines a closure that reconstructs the full form of minimized attributes.
+    ///
+    /// In SGML, an attribute whose declared value is a name token group,
+    /// such as `<!ATTLIST ul compact (compact) #IMPLIED>`, may be minimized down to
+    /// the value itself: `<ul compact>` stands for `<ul compact="compact">`, and
+    /// `<p center>` may stand for `<p align="center">`.
+    ///
+    /// The closure receives the element name and the token written in the start tag,
+    /// after name normalization, and returns the name of the attribute it is a value of.
+    /// The attribute is then emitted as an [`Attribute`](crate::SgmlEvent::Attribute) event
+    /// with the token as its value. When the closure returns `None`,
+    /// the attribute is kept without a value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .expand_minimized_attributes(|element, token| match (element, token) {
+    ///         ("p", "left" | "center" | "right") => Some("align"),
+    ///         (_, "compact") => Some("compact"),
+    ///         _ => None,
+    ///     })
+    ///     .parse("<p center>Title</p><ul compact hidden></ul>")?;
+    /// assert_eq!(
+    ///     sgml.to_string(),
+    ///     r#"<p align="center">Title</p><ul compact="compact" hidden></ul>"#
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn expand_minimized_attributes<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn(&str, &str) -> Option<T> + Send + Sync + 'static,
+        T: Into<Cow<'static, str>>,
+    {
+        self.config.minimized_attribute_fn = Some(Arc::new(move |element, token| {
+            f(element, token).map(Into::into)
+        }));
+        self
+    }
+
+    /// Limits the number of attributes a single start tag may have.
+    ///
+    /// Start tags exceeding the limit are rejected with a parse error,
+    /// which helps guard against hostile input. By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder()
+    ///     .max_attributes_per_element(2)
+    ///     .build();
+    /// assert!(parser.parse(r#"<a href="/" title="Home"></a>"#).is_ok());
+    ///
+    /// let err = parser.parse(r#"<a href="/" title="Home" class="nav"></a>"#).unwrap_err();
+    /// assert!(err.to_string().contains("too many attributes in start tag for a: limit is 2"));
+    /// ```
+    pub fn max_attributes_per_element(mut self, limit: usize) -> Self {
+        self.config.max_attributes_per_element = Some(limit);
+        self
+    }
+
+    /// Limits the length of the input, in bytes.
+    ///
+    /// Longer inputs are rejected with a parse error before any event is produced.
+    /// Together with [`max_events`](ParserBuilder::max_events), this bounds the memory
+    /// used for hostile input. By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder().max_input_len(16).build();
+    /// assert!(parser.parse("<p>Hello</p>").is_ok());
+    ///
+    /// let err = parser.parse("<p>Hello, world</p>").unwrap_err();
+    /// assert!(err.to_string().contains("input too long: 19 bytes, limit is 16"));
+    /// ```
+    pub fn max_input_len(mut self, limit: usize) -> Self {
+        self.config.max_input_len = Some(limit);
+        self
+    }
+
+    /// Limits the number of events the input may produce.
+    ///
+    /// Events are counted as they are produced, so the error is reported as soon as
+    /// the limit is exceeded, also when parsing lazily with
+    /// [`parse_iter`](Parser::parse_iter). By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder().max_events(4).build();
+    /// assert!(parser.parse("<p>Hello</p>").is_ok());
+    ///
+    /// let err = parser.parse("<p>Hello<br></p>").unwrap_err();
+    /// assert!(err.to_string().contains("too many events: limit is 4"));
+    /// ```
+    pub fn max_events(mut self, limit: usize) -> Self {
+        self.config.max_events = Some(limit);
+        self
+    }
+
+    /// Changes whether attributes repeated within a start tag should be rejected.
+    ///
+    /// When disabled (the default), every occurrence is emitted as an event.
+    /// When enabled, a repeated attribute is a parse error. Names are compared after
+    /// [name normalization](ParserBuilder::name_normalization), so `HREF` and `href`
+    /// collide when names are lowercased.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let err = sgmlish::Parser::builder()
+    ///     .lowercase_names()
+    ///     .reject_duplicate_attributes(true)
+    ///     .parse(r#"<a href="/" HREF="/home"></a>"#)
+    ///     .unwrap_err();
+    /// assert!(err.to_string().contains("duplicate attribute in start tag for a: href"));
+    /// ```
+    pub fn reject_duplicate_attributes(mut self, reject: bool) -> Self {
+        self.config.reject_duplicate_attributes = reject;
+        self
+    }
+
+    /// Changes whether the input must close every element it opens.
+    ///
+    /// When disabled (the default), the parser returns whatever events it found
+    /// when the input ends. When enabled, ending the input with open elements is
+    /// a parse error naming them, as is ending it right after a `<` or `</`.
+    ///
+    /// The check happens while parsing, so it also applies to
+    /// [`parse_iter`](Parser::parse_iter) and [`parse_one`](Parser::parse_one).
+    /// Unlike [`SgmlFragment::validate_balanced`](crate::SgmlFragment::validate_balanced),
+    /// end tags may still be omitted when an end tag for an enclosing element
+    /// (or an empty end tag, `</>`) closes them, as in `<ul><li>one</ul>`;
+    /// end tags that match no open element are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let parser = sgmlish::Parser::builder().require_balanced(true).build();
+    /// assert!(parser.parse("<ul><li>one<li>two</ul>").is_ok());
+    ///
+    /// let err = parser.parse("<doc><p>unclosed").unwrap_err();
+    /// assert!(err.to_string().contains("unclosed elements at end of input: doc, p"));
+    /// ```
+    pub fn require_balanced(mut self, require: bool) -> Self {
+        self.config.require_balanced = require;
+        self
+    }
+
+    /// Changes whether a leading byte order mark (U+FEFF) should be skipped.
+    ///
+    /// This is disabled by default, in which case the mark is treated as any other
+    /// character. Error locations are still reported relative to the original input.
+    /// `Parser::parse_bytes` and `Parser::parse_reader`, available with the `encoding` feature,
+    /// always skip a byte order mark, regardless of this setting.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .strip_bom(true)
+    ///     .parse("\u{feff}<a></a>")?;
+    /// assert_eq!(sgml, sgmlish::parse("<a></a>")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn strip_bom(mut self, strip: bool) -> Self {
+        self.config.strip_bom = strip;
+        self
+    }
+
+    /// Changes whether unparseable content at the end of the input should be ignored.
+    ///
+    /// This is disabled by default, in which case the parser expects to consume the whole
+    /// input. When enabled, parsing stops at the first piece of markup after the document
+    /// content that cannot start an event, such as a malformed declaration,
+    /// and the events found up to that point are returned.
+    ///
+    /// Errors within markup that was recognized, like an unterminated comment
+    /// or attribute value, are still reported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # fn main() -> sgmlish::Result<()> {
+    /// let input = "<a>text</a>\n<!garbage";
+    /// assert!(sgmlish::parse(input).is_err());
+    ///
+    /// let sgml = sgmlish::Parser::builder()
+    ///     .allow_trailing_content(true)
+    ///     .parse(input)?;
+    /// assert_eq!(sgml, sgmlish::parse("<a>text</a>")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn allow_trailing_content(mut self, allow: bool) -> Self {
+        self.config.allow_trailing_content = allow;
+        self
+    }
+
+    /// Builds a new parser from the given configuration.
+    pub fn build(self) -> Parser {
+        Parser {
+            config: self.config,
+        }
+    }
+
+    /// Parses the given input with the built parser.
+    ///
+    /// To reuse the same parser for multiple inputs, use [`build()`](ParserBuilder::build)
+    /// then [`Parser::parse()`].
     pub fn parse(self, input: &str) -> crate::Result<SgmlFragment> {
         self.build().parse(input)
     }
@@ -403,6 +2200,640 @@ impl fmt::Debug for Ellipsis {
 mod tests {
     use super::*;
 
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn test_parse_reader() {
+        let expected = parse("<p>café</p>").unwrap();
+        let parser = Parser::new();
+
+        let utf8: &[u8] = b"<p>caf\xC3\xA9</p>";
+        assert_eq!(
+            parser.parse_reader(utf8, encoding_rs::UTF_8).unwrap(),
+            expected
+        );
+        let utf8_bom: &[u8] = b"\xEF\xBB\xBF<p>caf\xC3\xA9</p>";
+        assert_eq!(
+            parser.parse_reader(utf8_bom, encoding_rs::UTF_8).unwrap(),
+            expected
+        );
+        let latin1: &[u8] = b"<p>caf\xE9</p>";
+        assert_eq!(
+            parser
+                .parse_reader(latin1, encoding_rs::WINDOWS_1252)
+                .unwrap(),
+            expected
+        );
+
+        let utf16be = b"\xFE\xFF"
+            .iter()
+            .copied()
+            .chain("<p>café</p>".encode_utf16().flat_map(u16::to_be_bytes))
+            .collect::<Vec<u8>>();
+        assert_eq!(
+            parser
+                .parse_reader(&utf16be[..], encoding_rs::UTF_16BE)
+                .unwrap(),
+            expected
+        );
+        assert_eq!(parser.parse_reader_auto(&utf16be[..]).unwrap(), expected);
+
+        assert!(matches!(
+            parser.parse_reader(latin1, encoding_rs::UTF_8),
+            Err(crate::Error::IoError(_))
+        ));
+        let odd_length: &[u8] = b"\xFF\xFE<\x00p";
+        assert!(matches!(
+            parser.parse_reader_auto(odd_length),
+            Err(crate::Error::IoError(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn test_parse_bytes() {
+        let parser = Parser::builder()
+            .ignore_processing_instructions(true)
+            .build();
+        let expected = parse("<p>café</p>").unwrap();
+
+        let latin1: &[u8] = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>\n<p>caf\xE9</p>";
+        assert_eq!(parser.parse_bytes(latin1).unwrap(), expected);
+        let undeclared: &[u8] = b"<p>caf\xC3\xA9</p>";
+        assert_eq!(parser.parse_bytes(undeclared).unwrap(), expected);
+
+        // The byte order mark takes precedence over the declaration
+        let utf8_bom: &[u8] =
+            b"\xEF\xBB\xBF<?xml version='1.0' encoding='ISO-8859-1'?><p>caf\xC3\xA9</p>";
+        assert_eq!(parser.parse_bytes(utf8_bom).unwrap(), expected);
+        let utf16le = b"\xFF\xFE"
+            .iter()
+            .copied()
+            .chain("<p>café</p>".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<u8>>();
+        assert_eq!(parser.parse_bytes(&utf16le).unwrap(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "encoding")]
+    fn test_parse_bytes_errors() {
+        let parser = Parser::new();
+        let unknown: &[u8] = b"<?xml version=\"1.0\" encoding=\"EBCDIC-XYZ\"?><p></p>";
+        assert!(matches!(
+            parser.parse_bytes(unknown),
+            Err(crate::Error::UnknownEncoding(label)) if label == "EBCDIC-XYZ"
+        ));
+        let undeclared_latin1: &[u8] = b"<p>caf\xE9</p>";
+        assert!(matches!(
+            parser.parse_bytes(undeclared_latin1),
+            Err(crate::Error::IoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_short_references() {
+        let parser = Parser::builder()
+            .trim_whitespace(false)
+            .short_references([("\t", "tab"), ("\n", "RE"), ("\n\n", "para")])
+            .entities(&[("tab", "    "), ("re", " "), ("para", "\u{b6}")])
+            .entity_name_normalization(NameNormalization::ToLowercase)
+            .build();
+        assert_eq!(
+            parser
+                .parse("<p a='\t'>one\ttwo\nthree\n\n&#9;</p>")
+                .unwrap(),
+            Parser::builder()
+                .trim_whitespace(false)
+                .parse("<p a='\t'>one    two three\u{b6}\t</p>")
+                .unwrap()
+        );
+
+        // Entities must be defined, as with any other reference
+        let parser = Parser::builder().short_references([("\t", "tab")]).build();
+        assert!(parser.parse("<p>one\ttwo</p>").is_err());
+        assert!(parser.parse("<p>one two</p>").is_ok());
+    }
+
+    #[test]
+    fn test_strip_bom() {
+        let parser = Parser::builder().strip_bom(true).build();
+        assert_eq!(
+            parser.parse("\u{feff}<a/>").unwrap(),
+            parse("<a/>").unwrap()
+        );
+        assert_eq!(parser.parse("<a/>").unwrap(), parse("<a/>").unwrap());
+        assert_eq!(
+            parser
+                .parse_iter("\u{feff}<a/>")
+                .collect::<crate::Result<Vec<_>>>()
+                .unwrap(),
+            parse("<a/>").unwrap().into_vec()
+        );
+
+        let err = parser.parse("\u{feff}<a b='>").unwrap_err().to_string();
+        assert!(err.contains("line 1"), "{}", err);
+
+        assert_ne!(
+            Parser::new().parse("\u{feff}<a/>").ok(),
+            Some(parse("<a/>").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_entity_name_normalization() {
+        let parser = Parser::builder()
+            .entity_name_normalization(NameNormalization::ToUppercase)
+            .resolve_internal_entities(true)
+            .expand_marked_sections()
+            .expand_entities(|entity| match entity {
+                "AMP" => Some("&"),
+                _ => None,
+            })
+            .build();
+        let fragment = parser
+            .parse(concat!(
+                r#"<!DOCTYPE x [<!ENTITY Declared "d"><!ENTITY % cond "include">]>"#,
+                r#"<x>&amp;&Amp;&#65;&declared;<![ %COND; [!]]></x>"#,
+            ))
+            .unwrap();
+        assert_eq!(fragment.as_slice()[3], SgmlEvent::Character("&&Ad".into()));
+        assert_eq!(fragment.as_slice()[4], SgmlEvent::Character("!".into()));
+
+        assert!(parser.parse("<x>&other;</x>").is_err());
+    }
+
+    #[test]
+    fn test_resolve_external_entities() {
+        use std::sync::Mutex;
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let parser = Parser::builder()
+            .expand_marked_sections()
+            .resolve_external_entities({
+                let calls = calls.clone();
+                move |decl| {
+                    calls.lock().unwrap().push(decl.name.to_owned());
+                    match (decl.public_id, decl.system_id) {
+                        (_, Some("a.txt")) => Some("from a"),
+                        (Some("-//Example//TEXT B//EN"), _) => Some("from b"),
+                        (_, Some("cond.ent")) => Some("INCLUDE"),
+                        _ => None,
+                    }
+                }
+            })
+            .expand_entities(|name| match name {
+                "fallback" => Some("from closure"),
+                _ => None,
+            })
+            .build();
+        let fragment = parser
+            .parse(concat!(
+                r#"<!DOCTYPE x [<!ENTITY a SYSTEM "a.txt"><!ENTITY b PUBLIC "-//Example//TEXT B//EN">"#,
+                r#"<!ENTITY fallback SYSTEM "missing.txt"><!ENTITY literal "literal">"#,
+                r#"<!ENTITY % cond SYSTEM "cond.ent">]>"#,
+                r#"<x>&a; &b; &a; &fallback;<![ %cond; [!]]></x>"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            fragment.as_slice()[3],
+            SgmlEvent::Character("from a from b from a from closure".into())
+        );
+        assert_eq!(fragment.as_slice()[4], SgmlEvent::Character("!".into()));
+        assert_eq!(*calls.lock().unwrap(), ["a", "b", "fallback", "cond"]);
+
+        // Internal entities are only resolved if enabled
+        assert!(parser
+            .parse(r#"<!DOCTYPE x [<!ENTITY literal "literal">]><x>&literal;</x>"#)
+            .is_err());
+
+        let parser = Parser::builder()
+            .resolve_internal_entities(true)
+            .resolve_external_entities(|_| Some("external"))
+            .build();
+        let fragment = parser
+            .parse(r#"<!DOCTYPE x [<!ENTITY e SYSTEM "e.txt"><!ENTITY e "literal">]><x>&e;</x>"#)
+            .unwrap();
+        assert_eq!(
+            fragment.as_slice()[3],
+            SgmlEvent::Character("literal".into())
+        );
+    }
+
+    #[test]
+    fn test_sdata_and_cdata_entities() {
+        let parser = Parser::builder()
+            .resolve_internal_entities(true)
+            .map_sdata_entities(|name, _| match name {
+                "mdash" => Some("\u{2014}"),
+                _ => None,
+            })
+            .build();
+        let fragment = parser
+            .parse(concat!(
+                r#"<!DOCTYPE x [<!ENTITY mdash SDATA "[mdash ]"><!ENTITY ndash SDATA "[ndash ]">"#,
+                r#"<!ENTITY lt CDATA "<">]>"#,
+                r#"<x>&mdash;&ndash;&lt;</x>"#,
+            ))
+            .unwrap();
+        assert_eq!(
+            fragment.as_slice()[3],
+            SgmlEvent::Character("\u{2014}[ndash ]<".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_iter_matches_parse() {
+        let parser = Parser::builder()
+            .resolve_internal_entities(true)
+            .structured_pis(true)
+            .build();
+        let inputs = [
+            "",
+            "   ",
+            "<a>text</a>",
+            "<!-- c --> <!DOCTYPE doc [<!ENTITY e 'entity'>]> <?pi data> <doc>&e;</doc> <?end> <!-- c -->",
+            "<a><![CDATA[<b>]]></a>",
+            "<a href='unterminated></a>",
+            "<a></a> trailing <b>",
+            "text <?pi> </a>",
+            "<!DOCTYPE doc>",
+        ];
+        for input in inputs {
+            let expected = parser.parse(input).map(SgmlFragment::into_vec);
+            let actual = parser.parse_iter(input).collect::<crate::Result<Vec<_>>>();
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => assert_eq!(expected, actual, "input: {:?}", input),
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(
+                        expected.to_string(),
+                        actual.to_string(),
+                        "input: {:?}",
+                        input
+                    )
+                }
+                (expected, actual) => panic!(
+                    "input: {:?}\nexpected: {:?}\nactual: {:?}",
+                    input, expected, actual
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_entities() {
+        let parser = Parser::builder()
+            .entities(&[("lt", "<"), ("gt", ">"), ("amp", "&")])
+            .build();
+        let sgml = parser.parse("<p title='&lt;&amp;&gt;'>a &lt; b &amp;&amp; c &gt; d</p>");
+        assert_eq!(
+            sgml.unwrap().as_slice(),
+            [
+                SgmlEvent::OpenStartTag { name: "p".into() },
+                SgmlEvent::Attribute {
+                    name: "title".into(),
+                    value: Some("<&>".into()),
+                },
+                SgmlEvent::CloseStartTag,
+                SgmlEvent::Character("a < b && c > d".into()),
+                SgmlEvent::EndTag { name: "p".into() },
+            ]
+        );
+        assert!(parser.parse("<p>&nbsp;</p>").is_err());
+
+        // Registered entities take precedence over the closure,
+        // and are in turn overridden by declarations in the document
+        let parser = Parser::builder()
+            .entities(&[("a", "registered"), ("b", "registered")])
+            .expand_entities(|entity| match entity {
+                "b" | "c" => Some("closure"),
+                _ => None,
+            })
+            .resolve_internal_entities(true)
+            .build();
+        let sgml = parser
+            .parse("<!DOCTYPE x [ <!ENTITY a 'declared'> ]><x>&a; &b; &c;</x>")
+            .unwrap();
+        assert_eq!(
+            sgml.as_slice()[3],
+            SgmlEvent::Character("declared registered closure".into())
+        );
+    }
+
+    #[test]
+    fn test_lone_entity_reference_is_borrowed() {
+        let parser = Parser::builder()
+            .entities(&[("nbsp", "\u{a0}")])
+            .predefined_xml_entities(true)
+            .resolve_internal_entities(true)
+            .build();
+        let input = "<!DOCTYPE x [ <!ENTITY d 'declared'> ]><x a='&amp;'>&nbsp;<y>&d;</y></x>";
+        let sgml = parser.parse(input).unwrap();
+        let events = sgml.as_slice();
+        assert!(matches!(
+            &events[2],
+            SgmlEvent::Attribute {
+                value: Some(Cow::Borrowed("&")),
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[4],
+            SgmlEvent::Character(Cow::Borrowed("\u{a0}"))
+        ));
+        // Declared entities are replaced with their text in the document
+        match &events[7] {
+            SgmlEvent::Character(Cow::Borrowed(text)) => {
+                assert_eq!(*text, "declared");
+                assert!(input.as_bytes().as_ptr_range().contains(&text.as_ptr()));
+            }
+            event => panic!("expected borrowed text, got {:?}", event),
+        }
+        let sgml = parser
+            .parse("<!DOCTYPE x [ <!ENTITY d '&#100;eclared'> ]><x a='&d;'></x>")
+            .unwrap();
+        assert!(matches!(
+            &sgml.as_slice()[2],
+            SgmlEvent::Attribute {
+                value: Some(Cow::Owned(text)),
+                ..
+            } if text == "declared"
+        ));
+
+        // Without a document, no entities are declared
+        assert!(parser.config.parse_rcdata::<()>("&d;").is_err());
+    }
+
+    #[test]
+    fn test_parse_with_stats() {
+        let input = concat!(
+            "<!DOCTYPE doc [ <!ENTITY me \"sgmlish\"> ]>\n",
+            "<doc title='by &me;'>\n",
+            "  <sec><p>Hi &me; &amp; &#33;</sec>\n",
+            "  <sec><p>&lt;3<br/></p></sec>\n",
+            "</doc>",
+        );
+        let parser = Parser::builder()
+            .resolve_internal_entities(true)
+            .predefined_xml_entities(true)
+            .build();
+        let (fragment, stats) = parser.parse_with_stats(input).unwrap();
+        assert_eq!(fragment, parser.parse(input).unwrap());
+        assert_eq!(
+            stats,
+            ParseStats {
+                events: 20,
+                entity_expansions: 4,
+                max_depth: 4,
+                characters: "Hi sgmlish & !".len() + "<3".len(),
+            }
+        );
+
+        // Counts start over for every document
+        let (_, stats) = parser.parse_with_stats("<a>&lt;</a>").unwrap();
+        assert_eq!(stats.entity_expansions, 1);
+        assert_eq!(stats.max_depth, 1);
+
+        // References in marked sections count too, but not those kept as written
+        let parser = Parser::builder()
+            .predefined_xml_entities(true)
+            .marked_section_handling(MarkedSectionHandling::ExpandAll)
+            .build();
+        let (_, stats) = parser
+            .parse_with_stats(
+                "<a><![INCLUDE[<b>&amp;</b>]]><![RCDATA[&lt;&gt;]]><![CDATA[&amp;]]></a>",
+            )
+            .unwrap();
+        assert_eq!(stats.entity_expansions, 3);
+    }
+
+    #[test]
+    fn test_shared_across_threads() {
+        use std::thread;
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Parser>();
+
+        let parser = Arc::new(
+            Parser::builder()
+                .expand_entities(|entity| match entity {
+                    "thread" => Some("shared"),
+                    _ => None,
+                })
+                .build(),
+        );
+        let handles = (0..4)
+            .map(|i| {
+                let parser = Arc::clone(&parser);
+                thread::spawn(move || {
+                    let input = format!("<a n={}>&thread; &#33;</a>", i);
+                    let (fragment, stats) = parser.parse_with_stats(&input).unwrap();
+                    assert_eq!(stats.entity_expansions, 1);
+                    fragment.into_owned()
+                })
+            })
+            .collect::<Vec<_>>();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let fragment = handle.join().unwrap();
+            assert_eq!(
+                fragment.as_slice()[1],
+                SgmlEvent::Attribute {
+                    name: "n".into(),
+                    value: Some(i.to_string().into()),
+                }
+            );
+            assert_eq!(
+                fragment.as_slice()[3],
+                SgmlEvent::Character("shared !".into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_one() {
+        let parser = Parser::new();
+        let input = concat!(
+            "<!-- leading --> <a x=1><b>one</b><c/></a><!-- trailing --> ",
+            "<?pi><d>two<e></d>",
+            "<f>three</>text after",
+        );
+
+        let (fragment, rest) = parser.parse_one(input).unwrap();
+        assert_eq!(fragment, parse("<a x=1><b>one</b><c/></a>").unwrap());
+        assert_eq!(rest, " <?pi><d>two<e></d><f>three</>text after");
+
+        let (fragment, rest) = parser.parse_one(rest).unwrap();
+        assert_eq!(fragment, parse("<?pi><d>two<e></d>").unwrap());
+        assert_eq!(rest, "<f>three</>text after");
+
+        let (fragment, rest) = parser.parse_one(rest).unwrap();
+        assert_eq!(fragment.to_string(), "<f>three</>");
+        assert_eq!(rest, "text after");
+
+        let (fragment, rest) = parser.parse_one("<br/> <p>").unwrap();
+        assert_eq!(fragment.to_string(), "<br/>");
+        assert_eq!(rest, " <p>");
+
+        let (fragment, rest) = parser.parse_one("<p>unclosed").unwrap();
+        assert_eq!(fragment.to_string(), "<p>unclosed");
+        assert_eq!(rest, "");
+
+        // A stray end tag is kept, but does not end the element
+        let (fragment, rest) = parser.parse_one("</x><p>one</p><p>two</p>").unwrap();
+        assert_eq!(fragment.to_string(), "</x><p>one</p>");
+        assert_eq!(rest, "<p>two</p>");
+
+        let err = parser.parse_one("<a b='></a>").unwrap_err();
+        assert!(matches!(err, crate::Error::ParseError(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_require_balanced_unclosed_element() {
+        let lenient = Parser::new();
+        let strict = Parser::builder().require_balanced(true).build();
+
+        for input in &[
+            "<doc><p>text</p></doc>",
+            "<ul><li>one<li>two</ul><br/>",
+            "<doc><p>text</></> <?pi>",
+            "<doc>text</doc></stray>",
+        ] {
+            assert_eq!(strict.parse(input).unwrap(), lenient.parse(input).unwrap());
+        }
+
+        let input = "<doc>\n<sec><p>one</p>\n<p>two";
+        assert!(lenient.parse(input).is_ok());
+        let err = strict.parse(input).unwrap_err().to_string();
+        assert!(
+            err.contains("line 3: unclosed elements at end of input: doc, sec, p"),
+            "{}",
+            err
+        );
+
+        let err = strict
+            .parse("<doc><p>text</doc><i>")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("unclosed elements at end of input: i"),
+            "{}",
+            err
+        );
+
+        let mut events = strict.parse_iter("<doc>text");
+        assert_eq!(events.by_ref().filter(Result::is_ok).count(), 3);
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_require_balanced_truncated_tag() {
+        let lenient = Parser::new();
+        let strict = Parser::builder().require_balanced(true).build();
+
+        for input in &["<doc>text<", "<doc>text</"] {
+            assert!(lenient.parse(input).is_ok(), "input: {:?}", input);
+            let err = strict.parse(input).unwrap_err().to_string();
+            assert!(err.contains("expected '>', got end of input"), "{}", err);
+        }
+
+        let err = strict.parse("<doc><a href=x").unwrap_err().to_string();
+        assert!(err.contains("expected '>', got end of input"), "{}", err);
+        assert!(strict.parse("<doc>a < b</doc>").is_ok());
+    }
+
+    #[test]
+    fn test_max_input_len() {
+        let parser = Parser::builder().max_input_len(12).build();
+        assert!(parser.parse("<p>Hello</p>").is_ok());
+
+        let err = parser
+            .parse_with_detailed_errors::<ContextualizedError<_>>("<p>Hello!</p>")
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.error,
+                Some(crate::Error::InputTooLong { len: 13, limit: 12 })
+            ),
+            "{:?}",
+            err
+        );
+
+        // Checked before anything is parsed
+        let mut events = parser.parse_iter("<p>Hello</p><br>");
+        assert!(events.next().unwrap().is_err());
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_deeply_nested_elements() {
+        // Elements are parsed one tag at a time, so nesting does not grow the stack
+        let depth = 100_000;
+        let input = format!("{}text{}", "<a>".repeat(depth), "</a>".repeat(depth));
+        let parser = Parser::builder().require_balanced(true).build();
+        let (fragment, stats) = parser.parse_with_stats(&input).unwrap();
+        assert_eq!(fragment.len(), 3 * depth + 1);
+        assert_eq!(stats.max_depth, depth);
+        assert_eq!(parser.parse_iter(&input).count(), 3 * depth + 1);
+    }
+
+    #[test]
+    fn test_max_events() {
+        let parser = Parser::builder()
+            .max_events(7)
+            .require_balanced(true)
+            .build();
+        assert_eq!(parser.parse("<a><b>x</b></a>").unwrap().len(), 7);
+
+        let err = parser
+            .parse_with_detailed_errors::<ContextualizedError<_>>("<a><b>x</b><c></c></a>")
+            .unwrap_err();
+        assert!(
+            matches!(err.error, Some(crate::Error::TooManyEvents { limit: 7 })),
+            "{:?}",
+            err
+        );
+
+        // Checked as events are produced
+        let mut events = parser.parse_iter("<a>x<b>y<c>z");
+        assert_eq!(events.by_ref().take_while(Result::is_ok).count(), 7);
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_allow_trailing_content() {
+        let parser = Parser::builder().allow_trailing_content(true).build();
+        let expected = parse("<a>x</a>").unwrap();
+        assert_eq!(parser.parse("<a>x</a> \n\t").unwrap(), expected);
+        assert_eq!(parser.parse("<a>x</a>\n<!junk").unwrap(), expected);
+        assert_eq!(parser.parse("<a>x</a>&#xZZ; more").unwrap(), expected);
+        assert!(parse("<a>x</a>\n<!junk").is_err());
+        assert!(parse("<a>x</a>&#xZZ; more").is_err());
+
+        // Malformed markup that was recognized is still an error
+        assert!(parser.parse("<a>x</a><!-- unterminated").is_err());
+    }
+
+    #[test]
+    fn test_parse_iter_stops_after_error() {
+        let parser = Parser::new();
+        let mut events = parser.parse_iter("<a>ok</a> <b foo='bar></b>");
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            SgmlEvent::OpenStartTag { name: "a".into() }
+        );
+        assert!(events.by_ref().any(|event| event.is_err()));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_parse_iter_is_lazy() {
+        // The error at the end is only found if the document is fully parsed
+        let parser = Parser::new();
+        let mut events = parser.parse_iter("<a>ok</a> <b foo='bar></b>");
+        assert_eq!(
+            events.nth(2).unwrap().unwrap(),
+            SgmlEvent::Character("ok".into())
+        );
+    }
+
     #[test]
     fn test_config_trim() {
         let config = ParserConfig::default();
@@ -415,6 +2846,150 @@ mod tests {
         assert_eq!(config.trim(" hello "), " hello ");
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn test_config_trim_whitespace_field() {
+        let mut config = ParserConfig::default();
+        assert!(config.trim_whitespace);
+        config.trim_whitespace = false;
+        assert_eq!(config.trim(" hello "), " hello ");
+
+        let config = Parser::builder().trim_whitespace(false).into_config();
+        assert!(!config.trim_whitespace);
+        let config = Parser::builder()
+            .whitespace_handling(WhitespaceHandling::Separate)
+            .into_config();
+        assert!(!config.trim_whitespace);
+        let config = Parser::builder()
+            .whitespace_handling(WhitespaceHandling::Keep)
+            .whitespace_handling(WhitespaceHandling::Trim)
+            .into_config();
+        assert!(config.trim_whitespace);
+        assert_eq!(config.trim(" hello "), "hello");
+    }
+
+    #[test]
+    fn test_config_custom_whitespace() {
+        let config = ParserConfig::default();
+        assert!(config.is_whitespace('\n'));
+        assert!(!config.is_whitespace('\x0c'));
+        assert_eq!(config.trim("\x0c hello \x0c"), "\x0c hello \x0c");
+
+        let config = Parser::builder()
+            .whitespace_chars(" \t\r\n\x0c")
+            .into_config();
+        assert!(config.is_whitespace('\n'));
+        assert!(config.is_whitespace('\x0c'));
+        assert_eq!(config.trim("\x0c hello \x0c"), "hello");
+
+        let config = Parser::builder().whitespace(|c| c == '_').into_config();
+        assert!(!config.is_whitespace(' '));
+        assert_eq!(config.trim("_ hello _"), " hello ");
+    }
+
+    #[test]
+    fn test_config_normalize_newlines() {
+        let config = ParserConfig::default();
+        assert_eq!(config.normalize_newlines("a\r\nb\rc".into()), "a\r\nb\rc");
+
+        let config = Parser::builder().normalize_newlines(true).into_config();
+        assert_eq!(config.normalize_newlines("a\r\nb\rc".into()), "a\nb\nc");
+        assert_eq!(config.normalize_newlines("\r\r\n\n".into()), "\n\n\n");
+        assert!(matches!(
+            config.normalize_newlines("a\nb".into()),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_resolve_function_chars() {
+        let config = Parser::builder()
+            .resolve_function_chars(true)
+            .expand_entities(|entity| match entity {
+                "#SPACE" => Some("custom"),
+                _ => None,
+            })
+            .into_config();
+        let parse = |rcdata| config.parse_rcdata::<nom::error::Error<_>>(rcdata).unwrap();
+        assert_eq!(parse("a&#RE;b"), "a\nb");
+        assert_eq!(parse("a&#RS;b"), "ab");
+        assert_eq!(parse("a&#SPACE;b"), "a b");
+        assert_eq!(parse("a&#TAB;b"), "a\tb");
+        assert_eq!(parse("&#re;&#rs;&#space;&#tab;"), "\n \t");
+
+        let config = Parser::builder()
+            .expand_entities(|entity| match entity {
+                "#SPACE" => Some("custom"),
+                _ => None,
+            })
+            .into_config();
+        assert_eq!(
+            config
+                .parse_rcdata::<nom::error::Error<_>>("a&#SPACE;b")
+                .unwrap(),
+            "acustomb"
+        );
+        assert!(config
+            .parse_rcdata::<nom::error::Error<_>>("a&#RE;b")
+            .is_err());
+    }
+
+    #[test]
+    fn test_predefined_xml_entities() {
+        let input = r#"<p title="a &amp; b &quot;c&quot;" alt='&apos;d&apos;'>x &lt; y &gt; z</p>"#;
+        assert!(parse(input).is_err());
+
+        let parser = Parser::builder().predefined_xml_entities(true).build();
+        let fragment = parser.parse(input).unwrap();
+        assert_eq!(
+            fragment.as_slice()[1],
+            SgmlEvent::Attribute {
+                name: "title".into(),
+                value: Some(r#"a & b "c""#.into()),
+            }
+        );
+        assert_eq!(
+            fragment.as_slice()[2],
+            SgmlEvent::Attribute {
+                name: "alt".into(),
+                value: Some("'d'".into()),
+            }
+        );
+        assert_eq!(
+            fragment.as_slice()[4],
+            SgmlEvent::Character("x < y > z".into())
+        );
+        assert!(parser.parse("<p>&AMP;</p>").is_err());
+
+        // Serialized output uses character references, understood with or without the setting
+        let serialized = fragment.to_string();
+        assert_eq!(parser.parse(&serialized).unwrap(), fragment);
+        assert_eq!(parse(&serialized).unwrap(), fragment);
+
+        // The entity closure takes precedence
+        let parser = Parser::builder()
+            .predefined_xml_entities(true)
+            .expand_entities(|entity| match entity {
+                "amp" => Some("and"),
+                _ => None,
+            })
+            .build();
+        let fragment = parser.parse("<p>a &amp; b &lt;</p>").unwrap();
+        assert_eq!(
+            fragment.as_slice()[2],
+            SgmlEvent::Character("a and b <".into())
+        );
+    }
+
+    #[test]
+    fn test_config_parse_rcdata_borrows_plain_text() {
+        let config = Parser::builder()
+            .expand_entities(|_| Some("entity"))
+            .into_config();
+        let result = config.parse_rcdata::<nom::error::Error<_>>("plain <text> ]]> only");
+        assert!(matches!(result, Ok(Cow::Borrowed("plain <text> ]]> only"))));
+    }
+
     #[test]
     fn test_config_parse_rcdata() {
         let config = ParserConfig::default();
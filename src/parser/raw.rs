@@ -69,7 +69,8 @@ where
     )(input)
 }
 
-fn declaration_subset<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
+/// Matches a declaration subset (`[<!ENTITY example "x">]`) and outputs its contents.
+pub fn declaration_subset<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str>,
 {
@@ -89,7 +90,7 @@ where
     )(input)
 }
 
-const MARKED_SECTION_START: &str = "<![";
+pub(crate) const MARKED_SECTION_START: &str = "<![";
 const MARKED_SECTION_END: &str = "]]>";
 
 /// Matches `<![foo[` and outputs `foo`.
@@ -125,25 +126,49 @@ where
 ///
 /// The content of `IGNORE` marked sections will match `<![` and `]]>` pairs,
 /// stopping on the first unmatched `]]>` found.
+///
+/// Pairs are counted rather than matched recursively, so deeply nested
+/// sections cannot exhaust the stack.
 pub fn marked_section_body_ignore<'a, E>(input: &'a str) -> IResult<&'a str, &'a str, E>
 where
     E: ParseError<&'a str> + ContextError<&'a str>,
 {
-    use nom::{FindSubstring, Slice};
-    let (close_suffix, close_match) = marked_section_body_character_data(input)?;
-    match close_match.find_substring(MARKED_SECTION_START) {
-        Some(n) => {
-            let (suffix_after_matched_pair, _) =
-                context("nested marked section pair", marked_section_body_ignore)(
-                    input.slice(n + MARKED_SECTION_START.len()..),
-                )?;
-            let (final_suffix, _) = marked_section_body_ignore(suffix_after_matched_pair)?;
-            Ok((
-                final_suffix,
-                input.slice(..input.len() - final_suffix.len() - MARKED_SECTION_END.len()),
-            ))
+    let find_from =
+        |pos: usize, delimiter: &str| input[pos..].find(delimiter).map(|found| pos + found);
+
+    let mut depth = 0usize;
+    let mut pos = 0;
+    let mut next_start = find_from(0, MARKED_SECTION_START);
+    let mut next_end = find_from(0, MARKED_SECTION_END);
+    loop {
+        let end = match next_end {
+            Some(end) => end,
+            None => {
+                // Report the missing delimiter as a non-nested section would
+                let rest = &input[pos..];
+                let err = marked_section_body_character_data(rest).unwrap_err();
+                return Err(if depth > 0 {
+                    err.map(|err| E::add_context(rest, "nested marked section pair", err))
+                } else {
+                    err
+                });
+            }
+        };
+        match next_start {
+            Some(start) if start < end => {
+                depth += 1;
+                pos = start + MARKED_SECTION_START.len();
+                next_start = find_from(pos, MARKED_SECTION_START);
+            }
+            _ if depth == 0 => {
+                return Ok((&input[end + MARKED_SECTION_END.len()..], &input[..end]))
+            }
+            _ => {
+                depth -= 1;
+                pos = end + MARKED_SECTION_END.len();
+                next_end = find_from(pos, MARKED_SECTION_END);
+            }
         }
-        _ => Ok((close_suffix, close_match)),
     }
 }
 
@@ -205,10 +230,8 @@ where
     use nom::{FindSubstring, InputTake};
     let next_tag = input.find_substring("<").unwrap_or(input.len());
     let split_pos = match mse {
-        MarkedSectionEndHandling::StopParsing => input
-            .find_substring("]]>")
-            .unwrap_or(next_tag)
-            .min(next_tag),
+        // Only look as far as the next tag, to avoid rescanning the rest of the input
+        MarkedSectionEndHandling::StopParsing => input[..next_tag].find("]]>").unwrap_or(next_tag),
         MarkedSectionEndHandling::TreatAsText => next_tag,
     };
     if split_pos == 0 {
@@ -477,6 +500,22 @@ mod tests {
         markup_declaration::<E>("< !doctype>").unwrap_err();
     }
 
+    #[test]
+    fn test_markup_declaration_quoted_delimiters() {
+        assert_eq!(
+            markup_declaration::<E>(r##"<!ENTITY x "a > b"> "##),
+            Ok((" ", ("ENTITY", r##"x "a > b""##)))
+        );
+        assert_eq!(
+            markup_declaration::<E>(r##"<!USEMAP 'map>1' [x]>"##),
+            Ok(("", ("USEMAP", r##"'map>1' [x]"##)))
+        );
+        assert_eq!(
+            markup_declaration::<E>(r##"<!DOCTYPE x [<!ENTITY gt ">"><!ENTITY q '"'>]>"##),
+            Ok(("", ("DOCTYPE", r##"x [<!ENTITY gt ">"><!ENTITY q '"'>]"##)))
+        );
+    }
+
     #[test]
     fn test_marked_section_start() {
         assert_eq!(
@@ -580,6 +619,15 @@ mod tests {
                 "<!] <![CDATA[hello]]> <!",
             ))
         );
+        let nested = format!(
+            "{}{}]]>",
+            "<![IGNORE[".repeat(100_000),
+            "]]>".repeat(100_000)
+        );
+        assert_eq!(
+            marked_section_body_ignore::<E>(&nested),
+            Ok(("", &nested[..nested.len() - 3]))
+        );
         marked_section_body_ignore::<E>("").unwrap_err();
         marked_section_body_ignore::<E>("hello").unwrap_err();
         marked_section_body_ignore::<E>("]>").unwrap_err();
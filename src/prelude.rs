@@ -0,0 +1,7 @@
+//! Items from `alloc` that are part of the standard prelude, so modules
+//! work the same with or without the `std` feature.
+
+pub(crate) use alloc::borrow::ToOwned;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec;
+pub(crate) use alloc::vec::Vec;
@@ -0,0 +1,570 @@
+//! Serialize a Rust data structure into SGML data.
+//!
+//! The mapping mirrors the one used when [deserializing](crate::de),
+//! so that serialized values can be read back with [`from_fragment`](crate::from_fragment):
+//!
+//! * A struct becomes an element. At the top level, the element is named after
+//!   the struct (as renamed by `#[serde(rename)]`); elsewhere, after its field.
+//! * Fields with strings, numbers, booleans, characters and unit enum variants
+//!   become attributes. `None` fields are left out.
+//! * Fields with structs and maps become child elements, named after the field.
+//!   Sequences become one child element per item, all named after the field;
+//!   unit values become empty elements.
+//! * Enum variants with data become an element named after the variant,
+//!   inside the element for the field, as in `<background><color>red</color></background>`.
+//! * A field named `$value` (or `$text`) becomes the content of its element,
+//!   with no element of its own: text is written as is, and enum variants
+//!   become elements named after the variant. A field named `$tag` replaces the
+//!   name of the element.
+//!
+//! Attributes are always written before child elements, regardless of field order.
+
+use std::borrow::Cow;
+
+use serde::ser::{self, Serialize};
+
+use crate::{SgmlEvent, SgmlFragment};
+
+/// The special field name that is written as the content of an element.
+const VALUE_KEY: &str = "$value";
+/// The special field name that is written as the text of an element.
+const TEXT_KEY: &str = "$text";
+/// The special field name that replaces the name of the element.
+const TAG_KEY: &str = "$tag";
+
+/// Serializes the given value into an [`SgmlFragment`].
+///
+/// See the [module documentation](self) for how values are mapped to SGML.
+///
+/// # Example
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// #[serde(rename = "select")]
+/// struct Select {
+///     name: Option<String>,
+///     #[serde(rename = "option")]
+///     options: Vec<SelectOption>,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct SelectOption {
+///     #[serde(rename = "$value")]
+///     label: String,
+///     #[serde(skip_serializing_if = "Option::is_none")]
+///     selected: Option<bool>,
+/// }
+///
+/// # fn main() -> Result<(), sgmlish::ser::SerializationError> {
+/// let select = Select {
+///     name: Some("color".to_owned()),
+///     options: vec![
+///         SelectOption { label: "Red".to_owned(), selected: Some(true) },
+///         SelectOption { label: "Green".to_owned(), selected: None },
+///     ],
+/// };
+/// let sgml = sgmlish::to_fragment(&select)?;
+/// assert_eq!(
+///     sgml.to_string(),
+///     r#"<select name="color"><option selected="true">Red</option><option>Green</option></select>"#
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_fragment<T>(value: &T) -> Result<SgmlFragment<'static>, SerializationError>
+where
+    T: Serialize + ?Sized,
+{
+    let mut events = Vec::new();
+    match value.serialize(ContentSerializer)? {
+        Content::Element(element) => {
+            let name = element.own_name().ok_or(SerializationError::MissingName)?;
+            write_element(name, Content::Element(element), &mut events);
+        }
+        content => write_content(content, &mut events)?,
+    }
+    Ok(events.into())
+}
+
+/// Serializes the given value into a string of SGML data.
+///
+/// This is a shorthand for [`to_fragment`], followed by [`to_string`](ToString::to_string).
+pub fn to_string<T>(value: &T) -> Result<String, SerializationError>
+where
+    T: Serialize + ?Sized,
+{
+    Ok(to_fragment(value)?.to_string())
+}
+
+/// The error type for serialization problems.
+#[derive(Debug, thiserror::Error)]
+pub enum SerializationError {
+    /// A value that cannot be represented in SGML was found.
+    #[error("serialization of {0} is not supported")]
+    Unsupported(&'static str),
+    /// A map was serialized at the top level, where there is no field to name its element.
+    #[error("cannot serialize a map at the top level without a $tag entry")]
+    MissingName,
+    /// A map key, or a `$tag` field, was not a string.
+    #[error("expected a string for {0}")]
+    ExpectedString(&'static str),
+    /// A map without a `$tag` entry was used as the content of an element (in a `$value` field).
+    #[error("cannot serialize a map as the content of an element without a $tag entry")]
+    UnnamedElement,
+
+    #[error("{0}")]
+    Message(String),
+}
+
+impl ser::Error for SerializationError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        SerializationError::Message(msg.to_string())
+    }
+}
+
+/// The intermediate representation of a serialized value, before knowing
+/// whether it is to be written as an attribute or as elements.
+#[derive(Debug)]
+enum Content {
+    /// `None`, which is not written at all.
+    Absent,
+    /// A unit value, which is written as an empty element.
+    Unit,
+    /// A scalar value.
+    Text(String),
+    /// A struct or map.
+    Element(Element),
+    /// A sequence or tuple.
+    Seq(Vec<Content>),
+    /// An enum variant with data.
+    Variant(&'static str, Box<Content>),
+}
+
+/// A struct or map, with its fields already split into attributes and content.
+#[derive(Debug, Default)]
+struct Element {
+    /// The name of the struct, if not a map.
+    name: Option<&'static str>,
+    /// The value of the `$tag` field, if any.
+    tag: Option<String>,
+    attributes: Vec<SgmlEvent<'static>>,
+    content: Vec<SgmlEvent<'static>>,
+}
+
+impl Element {
+    /// Returns the name of the element when it has no field to be named after.
+    fn own_name(&self) -> Option<Cow<'static, str>> {
+        match (&self.tag, self.name) {
+            (Some(tag), _) => Some(tag.clone().into()),
+            (None, name) => name.map(Cow::Borrowed),
+        }
+    }
+
+    fn add_field(
+        &mut self,
+        key: Cow<'static, str>,
+        value: Content,
+    ) -> Result<(), SerializationError> {
+        match (&*key, value) {
+            (_, Content::Absent) => {}
+            (TAG_KEY, Content::Text(tag)) => self.tag = Some(tag),
+            (TAG_KEY, _) => return Err(SerializationError::ExpectedString(TAG_KEY)),
+            (VALUE_KEY | TEXT_KEY, value) => write_content(value, &mut self.content)?,
+            (_, Content::Text(text)) => self.attributes.push(SgmlEvent::Attribute {
+                name: key,
+                value: Some(text.into()),
+            }),
+            (_, value) => write_element(key, value, &mut self.content),
+        }
+        Ok(())
+    }
+}
+
+/// Writes the given value as elements with the given name.
+fn write_element(name: Cow<'static, str>, value: Content, events: &mut Vec<SgmlEvent<'static>>) {
+    let (name, attributes, content) = match value {
+        Content::Absent => return,
+        Content::Seq(items) => {
+            for item in items {
+                write_element(name.clone(), item, events);
+            }
+            return;
+        }
+        Content::Unit => (name, vec![], vec![]),
+        Content::Text(text) => (name, vec![], vec![SgmlEvent::Character(text.into())]),
+        Content::Element(element) => (
+            element.tag.map_or(name, Cow::Owned),
+            element.attributes,
+            element.content,
+        ),
+        Content::Variant(variant, value) => {
+            let mut content = vec![];
+            write_element(variant.into(), *value, &mut content);
+            (name, vec![], content)
+        }
+    };
+    events.push(SgmlEvent::OpenStartTag { name: name.clone() });
+    events.extend(attributes);
+    events.push(SgmlEvent::CloseStartTag);
+    events.extend(content);
+    events.push(SgmlEvent::EndTag { name });
+}
+
+/// Writes the given value as the content of an element.
+fn write_content(
+    value: Content,
+    events: &mut Vec<SgmlEvent<'static>>,
+) -> Result<(), SerializationError> {
+    match value {
+        Content::Absent | Content::Unit => {}
+        Content::Text(text) => events.push(SgmlEvent::Character(text.into())),
+        Content::Element(element) => match element.own_name() {
+            Some(name) => write_element(name, Content::Element(element), events),
+            None => return Err(SerializationError::UnnamedElement),
+        },
+        Content::Seq(items) => {
+            for item in items {
+                write_content(item, events)?;
+            }
+        }
+        Content::Variant(variant, value) => write_element(variant.into(), *value, events),
+    }
+    Ok(())
+}
+
+/// Serializes values into [`Content`].
+struct ContentSerializer;
+
+macro_rules! serialize_display {
+    ($($serialize:ident: $ty:ty),*) => {
+        $(
+            fn $serialize(self, v: $ty) -> Result<Content, SerializationError> {
+                Ok(Content::Text(v.to_string()))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ContentSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = ElementSerializer;
+    type SerializeStruct = ElementSerializer;
+    type SerializeStructVariant = ElementSerializer;
+
+    serialize_display!(
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str
+    );
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Content, SerializationError> {
+        Err(SerializationError::Unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Content, SerializationError> {
+        Ok(Content::Absent)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Content, SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Content, SerializationError> {
+        Ok(Content::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Content, SerializationError> {
+        Ok(Content::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Content, SerializationError> {
+        Ok(Content::Text(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Content, SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Content, SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(Content::Variant(variant, Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerializationError> {
+        Ok(SeqSerializer::new(None, len))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerializationError> {
+        Ok(SeqSerializer::new(None, Some(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializationError> {
+        Ok(SeqSerializer::new(None, Some(len)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializationError> {
+        Ok(SeqSerializer::new(Some(variant), Some(len)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<ElementSerializer, SerializationError> {
+        Ok(ElementSerializer::new(None, None))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<ElementSerializer, SerializationError> {
+        Ok(ElementSerializer::new(Some(name), None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ElementSerializer, SerializationError> {
+        Ok(ElementSerializer::new(None, Some(variant)))
+    }
+}
+
+/// Wraps the content in a variant, if given.
+fn into_variant(variant: Option<&'static str>, content: Content) -> Content {
+    match variant {
+        Some(variant) => Content::Variant(variant, Box::new(content)),
+        None => content,
+    }
+}
+
+/// Collects the items of sequences and tuples.
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    items: Vec<Content>,
+}
+
+impl SeqSerializer {
+    fn new(variant: Option<&'static str>, len: Option<usize>) -> Self {
+        SeqSerializer {
+            variant,
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        }
+    }
+
+    fn push<T>(&mut self, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(value.serialize(ContentSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Content, SerializationError> {
+        Ok(into_variant(self.variant, Content::Seq(self.items)))
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
+
+/// Collects the fields of structs and the entries of maps.
+struct ElementSerializer {
+    variant: Option<&'static str>,
+    element: Element,
+    key: Option<Cow<'static, str>>,
+}
+
+impl ElementSerializer {
+    fn new(name: Option<&'static str>, variant: Option<&'static str>) -> Self {
+        ElementSerializer {
+            variant,
+            element: Element {
+                name,
+                ..Default::default()
+            },
+            key: None,
+        }
+    }
+
+    fn finish(self) -> Result<Content, SerializationError> {
+        Ok(into_variant(self.variant, Content::Element(self.element)))
+    }
+}
+
+impl ser::SerializeMap for ElementSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        match key.serialize(ContentSerializer)? {
+            Content::Text(key) => self.key = Some(key.into()),
+            _ => return Err(SerializationError::ExpectedString("map key")),
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        self.element
+            .add_field(key, value.serialize(ContentSerializer)?)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for ElementSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.element
+            .add_field(key.into(), value.serialize(ContentSerializer)?)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for ElementSerializer {
+    type Ok = Content;
+    type Error = SerializationError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), SerializationError>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.element
+            .add_field(key.into(), value.serialize(ContentSerializer)?)
+    }
+
+    fn end(self) -> Result<Content, SerializationError> {
+        self.finish()
+    }
+}
@@ -1,7 +1,10 @@
 //! Functions for dealing with textual character data.
 
-use std::fmt::{self, Write};
-use std::iter::FusedIterator;
+use alloc::borrow::Cow;
+use core::fmt::{self, Write};
+use core::iter::FusedIterator;
+
+use crate::prelude::*;
 
 /// Matches the most common definition of whitespace in SGML:
 /// ASCII space, tab, newline, and carriage return. (`" \t\r\n"`)
@@ -27,6 +30,69 @@ pub(crate) fn is_blank(s: &str) -> bool {
     s.chars().all(is_sgml_whitespace)
 }
 
+/// Replaces every run of [SGML whitespace](is_sgml_whitespace) in the given
+/// text with a single space.
+///
+/// Leading and trailing whitespace is collapsed too, but not removed.
+/// If there is nothing to replace, the text is returned as-is, without allocating.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::text::collapse_whitespace;
+/// assert_eq!(collapse_whitespace("\n  Some\ttext\r\n"), " Some text ");
+/// ```
+pub fn collapse_whitespace(text: &str) -> Cow<'_, str> {
+    collapse_whitespace_with(text, is_sgml_whitespace)
+}
+
+/// Replaces every run of characters matched by `is_whitespace` in the given
+/// text with a single space, like [`collapse_whitespace`].
+///
+/// This allows using the same definition of whitespace as a parser configured with
+/// [`ParserBuilder::whitespace`](crate::parser::ParserBuilder::whitespace).
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::text::collapse_whitespace_with;
+/// let collapsed = collapse_whitespace_with("Some\x0c\x0ctext", |c| " \t\r\n\x0c".contains(c));
+/// assert_eq!(collapsed, "Some text");
+/// ```
+pub fn collapse_whitespace_with<F>(text: &str, mut is_whitespace: F) -> Cow<'_, str>
+where
+    F: FnMut(char) -> bool,
+{
+    let mut chars = text.chars().peekable();
+    let mut needs_change = false;
+    while let Some(c) = chars.next() {
+        if is_whitespace(c)
+            && (c != ' ' || matches!(chars.peek(), Some(&next) if is_whitespace(next)))
+        {
+            needs_change = true;
+            break;
+        }
+    }
+    if !needs_change {
+        return text.into();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut last_was_whitespace = false;
+    for c in text.chars() {
+        if is_whitespace(c) {
+            if !last_was_whitespace {
+                out.push(' ');
+            }
+            last_was_whitespace = true;
+        } else {
+            out.push(c);
+            last_was_whitespace = false;
+        }
+    }
+    out.into()
+}
+
 /// Returns an iterator that escapes characters that cannot be represented in
 /// SGML text (`<`, `>`, `&`) using character references (`&#60;`).
 ///
@@ -55,8 +121,8 @@ pub fn escape(text: &str) -> Escape {
 #[derive(Clone, Debug)]
 pub struct Escape<'a> {
     escape_ampersand: bool,
-    chars: std::str::Chars<'a>,
-    escape_buffer: Option<std::slice::Iter<'static, u8>>,
+    chars: core::str::Chars<'a>,
+    escape_buffer: Option<core::slice::Iter<'static, u8>>,
 }
 
 impl<'a> Escape<'a> {
@@ -127,6 +193,58 @@ impl fmt::Display for Escape<'_> {
     }
 }
 
+/// Escapes text for use as element content, following the same rules as [`escape`].
+///
+/// Returns the text unchanged (and borrowed) when there is nothing to escape.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::text::escape_for_text;
+/// assert_eq!(escape_for_text("Sonic & Knuckles"), "Sonic &#38; Knuckles");
+/// assert_eq!(escape_for_text("\"Quoted\""), "\"Quoted\"");
+/// ```
+pub fn escape_for_text(text: &str) -> Cow<'_, str> {
+    if text.contains(['<', '>', '&']) {
+        escape(text).collect::<String>().into()
+    } else {
+        text.into()
+    }
+}
+
+/// Escapes text for use as an attribute value delimited by the given quote character.
+///
+/// Ampersands (`&`) and occurrences of the quote character are replaced with
+/// character references; the delimiting quotes themselves are not added.
+/// Returns the value unchanged (and borrowed) when there is nothing to escape.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::text::escape_for_attribute;
+/// let value = r#"Say "hi" & 'bye'"#;
+/// assert_eq!(escape_for_attribute(value, '"'), "Say &#34;hi&#34; &#38; 'bye'");
+/// assert_eq!(escape_for_attribute(value, '\''), r#"Say "hi" &#38; &#39;bye&#39;"#);
+/// ```
+pub fn escape_for_attribute(value: &str, quote: char) -> Cow<'_, str> {
+    if !value.contains(['&', quote]) {
+        return value.into();
+    }
+    let mut escaped = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&#38;"),
+            c if c == quote => {
+                escaped.push_str("&#");
+                escaped.push_str(&u32::from(c).to_string());
+                escaped.push(';');
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -151,6 +269,36 @@ mod tests {
         assert!(!is_blank("  \u{0c}\n"));
     }
 
+    #[test]
+    fn test_collapse_whitespace() {
+        assert!(matches!(collapse_whitespace(""), Cow::Borrowed("")));
+        assert!(matches!(
+            collapse_whitespace(" hello world "),
+            Cow::Borrowed(" hello world ")
+        ));
+        assert!(matches!(
+            collapse_whitespace("a\u{0c}\u{a0}b"),
+            Cow::Borrowed("a\u{0c}\u{a0}b")
+        ));
+        assert_eq!(collapse_whitespace("hello  world"), "hello world");
+        assert_eq!(collapse_whitespace("hello\nworld"), "hello world");
+        assert_eq!(collapse_whitespace("\r\n\t hello \t\r\n"), " hello ");
+        assert_eq!(collapse_whitespace("a \n b\tc  "), "a b c ");
+    }
+
+    #[test]
+    fn test_collapse_whitespace_with() {
+        let is_whitespace = |c: char| c == ' ' || c == '\u{0c}';
+        assert!(matches!(
+            collapse_whitespace_with("a\n\n b", is_whitespace),
+            Cow::Borrowed("a\n\n b")
+        ));
+        assert_eq!(
+            collapse_whitespace_with("a\u{0c} \u{0c}b\tc", is_whitespace),
+            "a b\tc"
+        );
+    }
+
     #[test]
     fn test_escape_noop() {
         assert_eq!(escape("hello!").to_string(), "hello!");
@@ -179,6 +327,31 @@ mod tests {
         assert_eq!(escape(max).size_hint().1, Some(escape(max).count()));
     }
 
+    #[test]
+    fn test_escape_for_text() {
+        assert!(matches!(escape_for_text("hello!"), Cow::Borrowed("hello!")));
+        assert_eq!(
+            escape_for_text("hello && <world>"),
+            "hello &#38;&#38; &#60;world&#62;"
+        );
+    }
+
+    #[test]
+    fn test_escape_for_attribute() {
+        assert!(matches!(
+            escape_for_attribute("it's <ok>", '"'),
+            Cow::Borrowed("it's <ok>")
+        ));
+        assert_eq!(
+            escape_for_attribute(r#"a&b "c" 'd'"#, '"'),
+            "a&#38;b &#34;c&#34; 'd'"
+        );
+        assert_eq!(
+            escape_for_attribute(r#"a&b "c" 'd'"#, '\''),
+            r#"a&#38;b "c" &#39;d&#39;"#
+        );
+    }
+
     #[test]
     fn test_escape_iter() {
         let mut escape = escape("wo<rld");
@@ -0,0 +1,290 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::prelude::*;
+use crate::transforms::Transform;
+use crate::{SgmlEvent, SgmlFragment};
+
+/// The error type in the event a start tag does not satisfy its declared attributes.
+///
+/// This is returned by [`apply_attribute_defaults`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeDefaultError {
+    /// An attribute declared `#REQUIRED` was missing from a start tag.
+    MissingRequired { element: String, attribute: String },
+    /// An attribute declared `#FIXED` was given a different value.
+    FixedMismatch { element: String, attribute: String },
+}
+
+impl fmt::Display for AttributeDefaultError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeDefaultError::MissingRequired { element, attribute } => write!(
+                f,
+                "missing required attribute in start tag for {}: {}",
+                element, attribute
+            ),
+            AttributeDefaultError::FixedMismatch { element, attribute } => write!(
+                f,
+                "fixed attribute with another value in start tag for {}: {}",
+                element, attribute
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AttributeDefaultError {}
+
+/// A default value, as written in an `<!ATTLIST>` declaration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DefaultValue<'v> {
+    Value(&'v str),
+    Fixed(&'v str),
+    Required,
+    Current,
+    Implied,
+}
+
+impl<'v> DefaultValue<'v> {
+    fn parse(value: &'v str) -> Self {
+        let (keyword, rest) = match value.find(char::is_whitespace) {
+            Some(pos) => (&value[..pos], value[pos..].trim_start()),
+            None => (value, ""),
+        };
+        let is = |expected: &str| keyword.eq_ignore_ascii_case(expected);
+        if is("#FIXED") {
+            DefaultValue::Fixed(rest)
+        } else if !rest.is_empty() {
+            DefaultValue::Value(value)
+        } else if is("#REQUIRED") {
+            DefaultValue::Required
+        } else if is("#CURRENT") {
+            DefaultValue::Current
+        } else if is("#IMPLIED") || is("#CONREF") {
+            DefaultValue::Implied
+        } else {
+            DefaultValue::Value(value)
+        }
+    }
+}
+
+/// Inserts declared default values for attributes missing from start tags.
+///
+/// `attlist` maps element names to a list of attribute names and their
+/// default values, similar to what would be declared in `<!ATTLIST>` declarations.
+/// Attributes already present in a start tag are never overwritten;
+/// injected attributes are placed after all explicit ones.
+///
+/// Besides plain values, defaults may be given as one of these keywords,
+/// matched case-insensitively:
+///
+/// * `#FIXED value`: `value` is inserted when the attribute is missing,
+///   and the attribute may not be given any other value;
+/// * `#REQUIRED`: the attribute must be present in every start tag;
+/// * `#CURRENT`: the last value given to the attribute in a start tag
+///   for the same element is inserted; nothing is inserted before the first one;
+/// * `#IMPLIED` and `#CONREF`: the attribute may be missing, and nothing is inserted.
+///
+/// Attributes written without a value (like `<ul compact>`) are compared with
+/// `#FIXED` values by their name, which is how SGML records the value in this case.
+///
+/// # Notes
+///
+/// * Tag and attribute names are compared in a case-sensitive manner; if your data may mix cases,
+///   you can configure your parser with [`lowercase_names`] or [`uppercase_names`] beforehand.
+/// * Only the first error is reported.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use sgmlish::transforms::apply_attribute_defaults;
+/// # fn main() -> sgmlish::Result<()> {
+/// let mut attlist = HashMap::new();
+/// attlist.insert("img", vec![("src", "#REQUIRED"), ("border", "0"), ("alt", "")]);
+///
+/// let sgml = sgmlish::parse(r#"<p><img src="a.gif" alt="A"></p>"#)?;
+/// let sgml = apply_attribute_defaults(sgml, &attlist)?;
+/// assert_eq!(sgml.to_string(), r#"<p><img src="a.gif" alt="A" border="0"></p>"#);
+///
+/// let sgml = sgmlish::parse(r#"<p><img alt="A"></p>"#)?;
+/// assert!(apply_attribute_defaults(sgml, &attlist).is_err());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`lowercase_names`]: crate::parser::ParserBuilder::lowercase_names
+/// [`uppercase_names`]: crate::parser::ParserBuilder::uppercase_names
+pub fn apply_attribute_defaults<'a, K, N, V>(
+    fragment: SgmlFragment<'a>,
+    attlist: &HashMap<K, Vec<(N, V)>>,
+) -> Result<SgmlFragment<'a>, AttributeDefaultError>
+where
+    K: Borrow<str> + Hash + Eq,
+    N: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut transform = Transform::new();
+    let mut element = "";
+    let mut defaults: Option<&[(N, V)]> = None;
+    let mut present = vec![];
+    // Last values given to `#CURRENT` attributes, by element and attribute name
+    let mut current = HashMap::new();
+
+    for (i, event) in fragment.iter().enumerate() {
+        match event {
+            SgmlEvent::OpenStartTag { name } => {
+                element = name;
+                defaults = attlist.get(name.as_ref()).map(Vec::as_slice);
+                present.clear();
+            }
+            SgmlEvent::Attribute { name, value } if defaults.is_some() => {
+                present.push((name.as_ref(), value.as_deref().unwrap_or(name)));
+            }
+            SgmlEvent::UnquotedAttribute { name, value }
+            | SgmlEvent::QuotedAttribute { name, value, .. }
+                if defaults.is_some() =>
+            {
+                present.push((name.as_ref(), value.as_ref()));
+            }
+            SgmlEvent::CloseStartTag | SgmlEvent::XmlCloseEmptyElement => {
+                for (name, value) in defaults.take().unwrap_or_default() {
+                    let name = name.as_ref();
+                    let found = present
+                        .iter()
+                        .find(|(present, _)| *present == name)
+                        .map(|&(_, value)| value);
+                    let inserted = match (DefaultValue::parse(value.as_ref()), found) {
+                        (DefaultValue::Fixed(expected), Some(found)) if found != expected => {
+                            return Err(AttributeDefaultError::FixedMismatch {
+                                element: element.to_owned(),
+                                attribute: name.to_owned(),
+                            });
+                        }
+                        (DefaultValue::Required, None) => {
+                            return Err(AttributeDefaultError::MissingRequired {
+                                element: element.to_owned(),
+                                attribute: name.to_owned(),
+                            });
+                        }
+                        (DefaultValue::Current, Some(found)) => {
+                            current.insert((element, name), found);
+                            None
+                        }
+                        (DefaultValue::Current, None) => current.get(&(element, name)).copied(),
+                        (DefaultValue::Value(value) | DefaultValue::Fixed(value), None) => {
+                            Some(value)
+                        }
+                        _ => None,
+                    };
+                    if let Some(value) = inserted {
+                        transform.insert_at(
+                            i,
+                            SgmlEvent::Attribute {
+                                name: name.to_owned().into(),
+                                value: Some(value.to_owned().into()),
+                            },
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(transform.apply(fragment))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_attribute_defaults() {
+        let mut attlist = HashMap::new();
+        attlist.insert("img".to_owned(), vec![("border", "0"), ("ismap", "ismap")]);
+        attlist.insert("td".to_owned(), vec![("colspan", "1")]);
+
+        let fragment = parse(
+            r#"<table><tr><td colspan=2><img src="a.gif"><td><img border=1 src="b.gif"/></table>"#,
+        )
+        .unwrap();
+
+        let result = apply_attribute_defaults(fragment, &attlist).unwrap();
+        assert_eq!(
+            result,
+            parse(concat!(
+                r#"<table><tr>"#,
+                r#"<td colspan=2><img src="a.gif" border="0" ismap="ismap">"#,
+                r#"<td colspan="1"><img border=1 src="b.gif" ismap="ismap"/>"#,
+                r#"</table>"#,
+            ))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_apply_attribute_defaults_case_sensitive() {
+        let mut attlist = HashMap::new();
+        attlist.insert("img", vec![("border", "0")]);
+
+        let fragment = parse(r#"<IMG><img BORDER=1>"#).unwrap();
+        let result = apply_attribute_defaults(fragment, &attlist).unwrap();
+        assert_eq!(result, parse(r#"<IMG><img BORDER=1 border="0">"#).unwrap());
+    }
+
+    #[test]
+    fn test_apply_attribute_defaults_keywords() {
+        let mut attlist = HashMap::new();
+        attlist.insert(
+            "input",
+            vec![
+                ("type", "#FIXED checkbox"),
+                ("name", "#REQUIRED"),
+                ("value", "#IMPLIED"),
+                ("class", "#CURRENT"),
+                ("checked", "#fixed checked"),
+            ],
+        );
+
+        let fragment = parse(concat!(
+            "<input name=a><input name=b class=x>",
+            "<input name=c type=checkbox checked><input name=d class=y><input name=e>",
+        ))
+        .unwrap();
+        let result = apply_attribute_defaults(fragment, &attlist).unwrap();
+        assert_eq!(
+            result,
+            parse(concat!(
+                r#"<input name=a type="checkbox" checked="checked">"#,
+                r#"<input name=b class=x type="checkbox" checked="checked">"#,
+                r#"<input name=c type=checkbox checked class="x">"#,
+                r#"<input name=d class=y type="checkbox" checked="checked">"#,
+                r#"<input name=e type="checkbox" class="y" checked="checked">"#,
+            ))
+            .unwrap()
+        );
+
+        let fragment = parse("<input name=a><input type=radio name=b>").unwrap();
+        assert_eq!(
+            apply_attribute_defaults(fragment, &attlist),
+            Err(AttributeDefaultError::FixedMismatch {
+                element: "input".to_owned(),
+                attribute: "type".to_owned(),
+            })
+        );
+
+        let fragment = parse("<input name=a><input value=1>").unwrap();
+        assert_eq!(
+            apply_attribute_defaults(fragment, &attlist),
+            Err(AttributeDefaultError::MissingRequired {
+                element: "input".to_owned(),
+                attribute: "name".to_owned(),
+            })
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::prelude::*;
+use crate::{SgmlEvent, SgmlFragment, UnbalancedError};
+
+/// What to do with an end tag that does not close the innermost open element.
+///
+/// Used by [`balance_end_tags`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MismatchPolicy {
+    /// Fail with an [`UnbalancedError`].
+    Error,
+    /// Drop the end tag, leaving the open elements as they are.
+    Ignore,
+    /// Close open elements, innermost first, until one matching the end tag is found;
+    /// if none matches, all open elements are closed and the end tag is dropped.
+    AutoClose,
+}
+
+/// Resolves end tags that do not close the innermost open element
+/// (as in `<b><i></b></i>`), according to the given policy.
+///
+/// Empty end tags (`</>`) close whichever element is innermost, and XML-style
+/// empty elements (`<br/>`) need no end tag. End tags with no open element at all
+/// are handled the same way as mismatched ones. Elements left open at the end
+/// of the fragment are not closed; see
+/// [`normalize_end_tags`](crate::transforms::normalize_end_tags) for inferring those.
+///
+/// Tag names are compared in a case-sensitive manner; if your data may mix cases,
+/// you can configure your parser with [`lowercase_names`] or [`uppercase_names`] beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::{balance_end_tags, MismatchPolicy};
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse("<p><b><i>Text</b> more</i></p>")?;
+/// let sgml = balance_end_tags(sgml, MismatchPolicy::AutoClose)?;
+/// assert_eq!(sgml.to_string(), "<p><b><i>Text</i></b>more</p>");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`lowercase_names`]: crate::parser::ParserBuilder::lowercase_names
+/// [`uppercase_names`]: crate::parser::ParserBuilder::uppercase_names
+pub fn balance_end_tags(
+    fragment: SgmlFragment,
+    policy: MismatchPolicy,
+) -> Result<SgmlFragment, UnbalancedError> {
+    let mut events = Vec::with_capacity(fragment.len());
+    let mut stack = vec![];
+
+    for (index, event) in fragment.into_iter().enumerate() {
+        match &event {
+            SgmlEvent::OpenStartTag { name } => stack.push(name.clone()),
+            SgmlEvent::XmlCloseEmptyElement => {
+                stack.pop();
+            }
+            SgmlEvent::EndTag { name } => match stack.last() {
+                Some(open_name) if name.is_empty() || open_name == name => {
+                    stack.pop();
+                }
+                open_name => match policy {
+                    MismatchPolicy::Error => {
+                        return Err(match open_name {
+                            Some(open_name) => UnbalancedError::MismatchedEndTag {
+                                index,
+                                expected: open_name.to_string(),
+                                found: name.to_string(),
+                            },
+                            None => UnbalancedError::UnexpectedEndTag {
+                                index,
+                                name: name.to_string(),
+                            },
+                        })
+                    }
+                    MismatchPolicy::Ignore => continue,
+                    MismatchPolicy::AutoClose => {
+                        let matching = stack.iter().rposition(|open_name| open_name == name);
+                        let close_from = matching.map_or(0, |position| position + 1);
+                        events.extend(
+                            stack
+                                .drain(close_from..)
+                                .rev()
+                                .map(|name| SgmlEvent::EndTag { name }),
+                        );
+                        if matching.is_none() {
+                            continue;
+                        }
+                        stack.pop();
+                    }
+                },
+            },
+            _ => {}
+        }
+        events.push(event);
+    }
+
+    Ok(events.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_balance_end_tags_error() {
+        let fragment = parse("<b><i></b></i>").unwrap();
+        assert_eq!(
+            balance_end_tags(fragment, MismatchPolicy::Error),
+            Err(UnbalancedError::MismatchedEndTag {
+                index: 4,
+                expected: "i".to_owned(),
+                found: "b".to_owned(),
+            })
+        );
+
+        let fragment = parse("<a></a></b>").unwrap();
+        assert_eq!(
+            balance_end_tags(fragment, MismatchPolicy::Error),
+            Err(UnbalancedError::UnexpectedEndTag {
+                index: 3,
+                name: "b".to_owned(),
+            })
+        );
+
+        let fragment = parse("<a><b/><c>x</></a><br>").unwrap();
+        assert_eq!(
+            balance_end_tags(fragment.clone(), MismatchPolicy::Error),
+            Ok(fragment)
+        );
+    }
+
+    #[test]
+    fn test_balance_end_tags_ignore() {
+        let fragment = parse("<b><i></b></i>").unwrap();
+        let result = balance_end_tags(fragment, MismatchPolicy::Ignore).unwrap();
+        assert_eq!(result.to_string(), "<b><i></i>");
+
+        let fragment = parse("</x><a>y</z></a>").unwrap();
+        let result = balance_end_tags(fragment, MismatchPolicy::Ignore).unwrap();
+        assert_eq!(result.to_string(), "<a>y</a>");
+    }
+
+    #[test]
+    fn test_balance_end_tags_auto_close() {
+        let fragment = parse("<b><i></b></i>").unwrap();
+        let result = balance_end_tags(fragment, MismatchPolicy::AutoClose).unwrap();
+        assert_eq!(result, parse("<b><i></i></b>").unwrap());
+
+        let fragment = parse("<a><b><c>x</a>y").unwrap();
+        let result = balance_end_tags(fragment, MismatchPolicy::AutoClose).unwrap();
+        assert_eq!(result.to_string(), "<a><b><c>x</c></b></a>y");
+
+        let fragment = parse("<a><b>x</z>y</a>").unwrap();
+        let result = balance_end_tags(fragment, MismatchPolicy::AutoClose).unwrap();
+        assert_eq!(result.to_string(), "<a><b>x</b></a>y");
+    }
+}
@@ -0,0 +1,85 @@
+use crate::prelude::*;
+use crate::{SgmlEvent, SgmlFragment};
+
+/// Merges adjacent [`Character`](SgmlEvent::Character) events into a single one.
+///
+/// The parser produces a single event for each run of text, but transforms
+/// may leave runs split across several events, as when
+/// [`expand_marked_sections`](super::expand_marked_sections) replaces a section
+/// between two pieces of text. Events in between, like comments or
+/// [`Whitespace`](SgmlEvent::Whitespace) events, keep runs apart.
+///
+/// Text that is not merged is kept as is, without copying.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::coalesce_text;
+/// # use sgmlish::{SgmlEvent, SgmlFragment};
+/// let fragment = SgmlFragment::from(vec![
+///     SgmlEvent::Character("Hello, ".into()),
+///     SgmlEvent::Character("world".into()),
+///     SgmlEvent::Character("!".into()),
+/// ]);
+/// let fragment = coalesce_text(fragment);
+/// assert_eq!(fragment.as_slice(), [SgmlEvent::Character("Hello, world!".into())]);
+/// ```
+pub fn coalesce_text(fragment: SgmlFragment) -> SgmlFragment {
+    let mut events: Vec<SgmlEvent> = Vec::with_capacity(fragment.len());
+    for event in fragment {
+        if let SgmlEvent::Character(next) = &event {
+            if let Some(SgmlEvent::Character(text)) = events.last_mut() {
+                text.to_mut().push_str(next);
+                continue;
+            }
+        }
+        events.push(event);
+    }
+    events.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::MarkedSectionHandling;
+    use crate::transforms::expand_marked_sections;
+    use crate::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_coalesce_text() {
+        let fragment = Parser::builder()
+            .trim_whitespace(false)
+            .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+            .parse("<p>Fish <![RCDATA[&amp;]]> chips</p><p>a<br>b</p>")
+            .unwrap();
+        let fragment =
+            expand_marked_sections(fragment, MarkedSectionHandling::ExpandAll, |entity| {
+                if entity == "amp" {
+                    Some("&")
+                } else {
+                    None
+                }
+            })
+            .unwrap();
+        assert_eq!(fragment.iter().filter(|event| event.is_text()).count(), 5);
+
+        let fragment = coalesce_text(fragment);
+        assert_eq!(
+            fragment.as_slice(),
+            [
+                SgmlEvent::OpenStartTag { name: "p".into() },
+                SgmlEvent::CloseStartTag,
+                SgmlEvent::Character("Fish & chips".into()),
+                SgmlEvent::EndTag { name: "p".into() },
+                SgmlEvent::OpenStartTag { name: "p".into() },
+                SgmlEvent::CloseStartTag,
+                SgmlEvent::Character("a".into()),
+                SgmlEvent::OpenStartTag { name: "br".into() },
+                SgmlEvent::CloseStartTag,
+                SgmlEvent::Character("b".into()),
+                SgmlEvent::EndTag { name: "p".into() },
+            ]
+        );
+    }
+}
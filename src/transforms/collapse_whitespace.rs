@@ -0,0 +1,113 @@
+use alloc::borrow::Cow;
+
+use crate::{text, SgmlEvent, SgmlFragment};
+
+/// Replaces runs of whitespace in text content with a single space.
+///
+/// Only [`Character`](SgmlEvent::Character) events are affected; attribute values
+/// and other events are kept unmodified. In particular, if the parser was configured with
+/// [`MarkedSectionHandling::KeepUnmodified`], the content of `CDATA` marked sections
+/// is preserved.
+///
+/// # Limitations
+///
+/// Character events do not record which parts of their text came from entity
+/// or character references, so whitespace introduced by an expansion
+/// (like `&#10;`) is collapsed as well.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::collapse_whitespace;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse("<P>Hello,\n    world!</P>")?;
+/// let sgml = collapse_whitespace(sgml);
+/// assert_eq!(sgml.to_string(), "<P>Hello, world!</P>");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`MarkedSectionHandling::KeepUnmodified`]: crate::parser::MarkedSectionHandling::KeepUnmodified
+pub fn collapse_whitespace(fragment: SgmlFragment) -> SgmlFragment {
+    collapse_whitespace_with(fragment, text::is_sgml_whitespace)
+}
+
+/// Replaces runs of characters matched by `is_whitespace` in text content
+/// with a single space, like [`collapse_whitespace`].
+///
+/// This allows collapsing the same characters a parser configured with
+/// [`ParserBuilder::whitespace`] treats as whitespace.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::collapse_whitespace_with;
+/// # fn main() -> sgmlish::Result<()> {
+/// let is_whitespace = |c| " \t\r\n\x0c".contains(c);
+/// let sgml = sgmlish::Parser::builder()
+///     .whitespace(is_whitespace)
+///     .parse("<P>Hello,\x0c\n    world!</P>")?;
+/// let sgml = collapse_whitespace_with(sgml, is_whitespace);
+/// assert_eq!(sgml.to_string(), "<P>Hello, world!</P>");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ParserBuilder::whitespace`]: crate::parser::ParserBuilder::whitespace
+pub fn collapse_whitespace_with<F>(mut fragment: SgmlFragment, mut is_whitespace: F) -> SgmlFragment
+where
+    F: FnMut(char) -> bool,
+{
+    for event in fragment.iter_mut() {
+        if let SgmlEvent::Character(text) = event {
+            if let Cow::Owned(collapsed) = text::collapse_whitespace_with(text, &mut is_whitespace)
+            {
+                *text = collapsed.into();
+            }
+        }
+    }
+    fragment
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::MarkedSectionHandling;
+    use crate::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_collapse_whitespace() {
+        let parser = Parser::builder()
+            .trim_whitespace(false)
+            .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+            .build();
+        let fragment = parser
+            .parse(
+                "<PRE>\n  one  <B>two\tthree</B><![CDATA[ four\n  five ]]></PRE>\n\n<X Y='a  b'></X>",
+            )
+            .unwrap();
+
+        let result = collapse_whitespace(fragment);
+        assert_eq!(
+            result,
+            parser
+                .parse(
+                    "<PRE> one <B>two three</B><![CDATA[ four\n  five ]]></PRE> <X Y='a  b'></X>"
+                )
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_collapse_whitespace_with() {
+        let parser = Parser::builder()
+            .trim_whitespace(false)
+            .whitespace_chars(" \x0c")
+            .build();
+        let fragment = parser.parse("<P>one\x0c \x0ctwo\n\nthree</P>").unwrap();
+
+        let result = collapse_whitespace_with(fragment, |c| c == ' ' || c == '\x0c');
+        assert_eq!(result, parser.parse("<P>one two\n\nthree</P>").unwrap());
+    }
+}
@@ -0,0 +1,166 @@
+use alloc::borrow::Cow;
+
+use nom::combinator::all_consuming;
+use nom::Finish;
+
+use crate::parser::events::marked_section_declaration;
+use crate::parser::{ContextualizedError, MarkedSectionHandling};
+use crate::prelude::*;
+use crate::{Parser, SgmlEvent, SgmlFragment};
+
+/// Expands [`MarkedSection`](SgmlEvent::MarkedSection) events left by a parser configured with
+/// [`MarkedSectionHandling::KeepUnmodified`], as if the given handling had been used when parsing.
+///
+/// `CDATA` and `RCDATA` sections become [`Character`](SgmlEvent::Character) events,
+/// with entity references in `RCDATA` sections resolved through the given closure
+/// (see [`ParserBuilder::expand_entities`]). When `handling` is
+/// [`ExpandAll`](MarkedSectionHandling::ExpandAll), the content of `INCLUDE` sections
+/// is parsed into events, and `IGNORE` sections are removed; otherwise, their keywords
+/// are rejected, just like the parser would. Passing `KeepUnmodified` returns the fragment as is.
+///
+/// Sections are expanded with the parser's default settings otherwise: in particular,
+/// text is trimmed, and names within `INCLUDE` sections are not normalized.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::parser::MarkedSectionHandling;
+/// # use sgmlish::transforms::expand_marked_sections;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::Parser::builder()
+///     .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+///     .parse("<P><![RCDATA[Fish &amp; chips]]><![IGNORE[<B>draft</B>]]></P>")?;
+///
+/// let expanded = expand_marked_sections(sgml, MarkedSectionHandling::ExpandAll, |entity| {
+///     match entity {
+///         "amp" => Some("&"),
+///         _ => None,
+///     }
+/// })?;
+/// assert_eq!(expanded.to_string(), "<P>Fish &#38; chips</P>");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ParserBuilder::expand_entities`]: crate::parser::ParserBuilder::expand_entities
+pub fn expand_marked_sections<'a, F, T>(
+    fragment: SgmlFragment<'a>,
+    handling: MarkedSectionHandling,
+    expand_entities: F,
+) -> crate::Result<SgmlFragment<'a>>
+where
+    F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+    T: Into<Cow<'static, str>>,
+{
+    if handling == MarkedSectionHandling::KeepUnmodified {
+        return Ok(fragment);
+    }
+
+    let config = Parser::builder()
+        .marked_section_handling(handling)
+        .expand_entities(expand_entities)
+        .into_config();
+
+    let mut events: Vec<SgmlEvent<'a>> = Vec::with_capacity(fragment.len());
+    for event in fragment {
+        if let SgmlEvent::MarkedSection { .. } = event {
+            let declaration = event.to_string();
+            let (_, expanded) = all_consuming(|input| {
+                marked_section_declaration::<ContextualizedError<_>>(input, &config)
+            })(declaration.as_str())
+            .finish()
+            .map_err(|err| crate::Error::ParseError(err.describe(&declaration.as_str())))?;
+            // The events borrow from `declaration`, which is about to be dropped
+            events.extend(expanded.map(|event| event.into_owned()));
+        } else {
+            events.push(event);
+        }
+    }
+    Ok(events.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    fn keep_unmodified(input: &str) -> SgmlFragment<'_> {
+        Parser::builder()
+            .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+            .parse(input)
+            .unwrap()
+    }
+
+    fn no_entities(_: &str) -> Option<&'static str> {
+        None
+    }
+
+    #[test]
+    fn test_expand_marked_sections() {
+        let input =
+            "<A><![CDATA[<x>]]><![RCDATA[&lt;y&#62;]]><![ INCLUDE [<B>b<![IGNORE[c]]></B>]]></A>";
+        let expanded = expand_marked_sections(
+            keep_unmodified(input),
+            MarkedSectionHandling::ExpandAll,
+            |entity| match entity {
+                "lt" => Some("<"),
+                _ => None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            expanded,
+            Parser::builder()
+                .expand_marked_sections()
+                .expand_entities(|entity| match entity {
+                    "lt" => Some("<"),
+                    _ => None,
+                })
+                .parse(input)
+                .unwrap()
+        );
+        assert_eq!(
+            expanded.to_string(),
+            "<A>&#60;x&#62;&#60;y&#62;<B>b</B></A>"
+        );
+    }
+
+    #[test]
+    fn test_expand_marked_sections_keep_unmodified() {
+        let fragment = keep_unmodified("<A><![IGNORE[x]]></A>");
+        assert_eq!(
+            expand_marked_sections(
+                fragment.clone(),
+                MarkedSectionHandling::KeepUnmodified,
+                no_entities
+            )
+            .unwrap(),
+            fragment
+        );
+    }
+
+    #[test]
+    fn test_expand_marked_sections_errors() {
+        let err = expand_marked_sections(
+            keep_unmodified("<A><![IGNORE[x]]></A>"),
+            MarkedSectionHandling::AcceptOnlyCharacterData,
+            no_entities,
+        )
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("invalid marked section keyword: IGNORE"),
+            "{}",
+            err
+        );
+
+        let err = expand_marked_sections(
+            keep_unmodified("<A><![RCDATA[&unknown;]]></A>"),
+            MarkedSectionHandling::ExpandAll,
+            no_entities,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("&unknown;"), "{}", err);
+    }
+}
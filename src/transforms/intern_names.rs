@@ -0,0 +1,162 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+
+use crate::prelude::*;
+use crate::{SgmlEvent, SgmlFragment};
+
+/// A set of element and attribute names, used by [`intern_names`].
+#[derive(Clone, Debug, Default)]
+pub struct NameTable {
+    names: BTreeSet<String>,
+}
+
+impl NameTable {
+    /// Creates a table with one copy of every element and attribute name in the fragment
+    /// that is not already borrowed from the input.
+    pub fn collect(fragment: &SgmlFragment) -> Self {
+        let names = fragment
+            .iter()
+            .filter_map(|event| match name_of(event) {
+                Some(Cow::Owned(name)) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        NameTable { names }
+    }
+
+    /// Returns the number of distinct names in the table.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns `true` if the table contains no names.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Replaces owned element and attribute names with references to the copies
+/// held by the given table, so that repeated names share the same storage.
+///
+/// Names are only owned when they differ from the input, such as when
+/// the parser normalizes their case; names borrowed from the input, or missing
+/// from the table, are kept unchanged. Equality and display are unaffected.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::borrow::Cow;
+/// # use sgmlish::transforms::{intern_names, NameTable};
+/// # use sgmlish::SgmlEvent;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::Parser::builder()
+///     .lowercase_names()
+///     .parse("<UL><LI>One</LI><LI>Two</LI></UL>")?;
+///
+/// let names = NameTable::collect(&sgml);
+/// assert_eq!(names.len(), 2);
+///
+/// let interned = intern_names(sgml.clone(), &names);
+/// assert_eq!(interned, sgml);
+/// assert!(matches!(
+///     &interned.as_slice()[2],
+///     SgmlEvent::OpenStartTag { name: Cow::Borrowed("li") },
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+pub fn intern_names<'a, 'n>(fragment: SgmlFragment<'a>, names: &'n NameTable) -> SgmlFragment<'n>
+where
+    'a: 'n,
+{
+    let mut fragment: SgmlFragment<'n> = fragment;
+    for event in fragment.iter_mut() {
+        if let Some(name) = name_of_mut(event) {
+            if let Cow::Owned(owned) = name {
+                if let Some(interned) = names.names.get(owned.as_str()) {
+                    *name = Cow::Borrowed(interned.as_str());
+                }
+            }
+        }
+    }
+    fragment
+}
+
+fn name_of<'e, 'a>(event: &'e SgmlEvent<'a>) -> Option<&'e Cow<'a, str>> {
+    match event {
+        SgmlEvent::OpenStartTag { name }
+        | SgmlEvent::EndTag { name }
+        | SgmlEvent::Attribute { name, .. }
+        | SgmlEvent::UnquotedAttribute { name, .. }
+        | SgmlEvent::QuotedAttribute { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+fn name_of_mut<'e, 'a>(event: &'e mut SgmlEvent<'a>) -> Option<&'e mut Cow<'a, str>> {
+    match event {
+        SgmlEvent::OpenStartTag { name }
+        | SgmlEvent::EndTag { name }
+        | SgmlEvent::Attribute { name, .. }
+        | SgmlEvent::UnquotedAttribute { name, .. }
+        | SgmlEvent::QuotedAttribute { name, .. } => Some(name),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_intern_names() {
+        let sgml = Parser::builder()
+            .lowercase_names()
+            .unquoted_attribute_events(true)
+            .quoted_attribute_events(true)
+            .parse("<TABLE><TR CLASS=a><TD ID='1'>x</TD></TR><TR class=b><td id=\"2\">y</td></tr></table>")
+            .unwrap();
+        let names = NameTable::collect(&sgml);
+        assert_eq!(names.len(), 5);
+
+        let interned = intern_names(sgml.clone(), &names);
+        assert_eq!(interned, sgml);
+        assert_eq!(interned.to_string(), sgml.to_string());
+
+        let mut interned_count = 0;
+        for (before, after) in sgml.iter().zip(interned.iter()) {
+            if let (Some(Cow::Owned(_)), Some(after)) = (name_of(before), name_of(after)) {
+                let entry = names.names.get(after.as_ref()).unwrap();
+                assert!(
+                    matches!(after, Cow::Borrowed(name) if core::ptr::eq(*name, entry.as_str()))
+                );
+                interned_count += 1;
+            }
+        }
+        assert_eq!(interned_count, 8);
+    }
+
+    #[test]
+    fn test_intern_names_keeps_unknown_names() {
+        let sgml = Parser::builder()
+            .lowercase_names()
+            .parse("<A><B></B></A>")
+            .unwrap();
+        let names = NameTable::collect(
+            &Parser::builder()
+                .lowercase_names()
+                .parse("<A></A>")
+                .unwrap(),
+        );
+        let interned = intern_names(sgml.clone(), &names);
+        assert_eq!(interned, sgml);
+        assert!(matches!(
+            &interned.as_slice()[2],
+            SgmlEvent::OpenStartTag {
+                name: Cow::Owned(_)
+            }
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use alloc::borrow::Cow;
+use core::mem;
+
+use crate::{SgmlEvent, SgmlFragment};
+
+/// Rewrites attribute values with the given closure.
+///
+/// The closure receives the name of each attribute and its current value,
+/// and returns the new value. Attributes without a value (like `HIDDEN`)
+/// are left untouched, as are attribute names.
+///
+/// Values are handed over as is, so returning the given `Cow` unchanged allocates nothing.
+///
+/// # Notes
+///
+/// * Attribute names are passed after any normalization performed by the parser;
+///   if your data may mix cases, you can configure your parser with
+///   [`lowercase_names`] or [`uppercase_names`] beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::map_attribute_values;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse(r#"<input type=CHECKBOX checked=TRUE>"#)?;
+/// let sgml = map_attribute_values(sgml, |name, value| match name {
+///     "checked" => value.to_lowercase().into(),
+///     _ => value,
+/// });
+/// assert_eq!(sgml.to_string(), r#"<input type="CHECKBOX" checked="true">"#);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`lowercase_names`]: crate::parser::ParserBuilder::lowercase_names
+/// [`uppercase_names`]: crate::parser::ParserBuilder::uppercase_names
+pub fn map_attribute_values<'a, F>(mut fragment: SgmlFragment<'a>, mut f: F) -> SgmlFragment<'a>
+where
+    F: FnMut(&str, Cow<'a, str>) -> Cow<'a, str>,
+{
+    for event in fragment.iter_mut() {
+        match event {
+            SgmlEvent::Attribute {
+                name,
+                value: Some(value),
+            }
+            | SgmlEvent::UnquotedAttribute { name, value }
+            | SgmlEvent::QuotedAttribute { name, value, .. } => {
+                *value = f(name, mem::take(value));
+            }
+            _ => {}
+        }
+    }
+    fragment
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{parse, Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_map_attribute_values() {
+        let fragment =
+            parse(r#"<td align=LEFT nowrap valign="TOP"><a align=RIGHT>x</a></td>"#).unwrap();
+        let mut seen = Vec::new();
+        let result = map_attribute_values(fragment, |name, value| {
+            seen.push(name.to_owned());
+            if name == "align" {
+                value.to_lowercase().into()
+            } else {
+                value
+            }
+        });
+        assert_eq!(
+            result,
+            parse(r#"<td align="left" nowrap valign="TOP"><a align="right">x</a></td>"#).unwrap()
+        );
+        assert_eq!(seen, ["align", "valign", "align"]);
+    }
+
+    #[test]
+    fn test_map_attribute_values_event_kinds() {
+        let parser = Parser::builder()
+            .unquoted_attribute_events(true)
+            .quoted_attribute_events(true)
+            .build();
+        let fragment = parser.parse(r#"<p a=ONE b='TWO' c>"#).unwrap();
+        let result = map_attribute_values(fragment, |_, value| value.to_lowercase().into());
+        assert_eq!(result.to_string(), r#"<p a=one b='two' c>"#);
+    }
+}
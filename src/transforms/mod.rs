@@ -4,8 +4,38 @@
 //!
 //! [`SgmlFragment`]: crate::SgmlFragment
 
+#[cfg(feature = "std")]
+pub use self::attribute_defaults::*;
+pub use self::balance_end_tags::*;
+pub use self::coalesce_text::*;
+pub use self::collapse_whitespace::*;
+pub use self::expand_marked_sections::*;
+#[cfg(feature = "intern")]
+pub use self::intern_names::*;
+pub use self::map_attribute_values::*;
+pub use self::normalize_empty_elements::*;
 pub use self::normalize_end_tags::*;
+pub use self::remove_empty_elements::*;
+#[cfg(feature = "std")]
+pub use self::rename_elements::*;
+pub use self::resolve_empty_tags::*;
+pub use self::strip_name_prefixes::*;
 pub use self::transform::*;
 
+#[cfg(feature = "std")]
+mod attribute_defaults;
+mod balance_end_tags;
+mod coalesce_text;
+mod collapse_whitespace;
+mod expand_marked_sections;
+#[cfg(feature = "intern")]
+mod intern_names;
+mod map_attribute_values;
+mod normalize_empty_elements;
 mod normalize_end_tags;
+mod remove_empty_elements;
+#[cfg(feature = "std")]
+mod rename_elements;
+mod resolve_empty_tags;
+mod strip_name_prefixes;
 mod transform;
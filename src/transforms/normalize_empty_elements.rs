@@ -0,0 +1,103 @@
+use crate::{SgmlEvent, SgmlFragment};
+
+/// How elements without content should be written.
+///
+/// Used by [`normalize_empty_elements`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EmptyElementStyle {
+    /// As XML-style empty elements, like `<br/>`, ending in an
+    /// [`XmlCloseEmptyElement`](SgmlEvent::XmlCloseEmptyElement) event.
+    Xml,
+    /// As a start tag with no end tag, like `<br>`, ending in a
+    /// [`CloseStartTag`](SgmlEvent::CloseStartTag) event.
+    Sgml,
+}
+
+/// Rewrites the elements with the given names, which are known to be empty
+/// (like HTML's `br` and `img`), in the given style.
+///
+/// End tags for those elements, as in `<br></br>`, are removed, since they
+/// cannot close anything. Other elements are left alone, even if they are
+/// written as XML-style empty elements.
+///
+/// Tag names are compared in a case-sensitive manner; if your data may mix cases,
+/// you can configure your parser with [`lowercase_names`] or [`uppercase_names`] beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::{normalize_empty_elements, EmptyElementStyle};
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse(r#"<p>One<br>Two<br/><img src="x"></img></p>"#)?;
+/// let xml = normalize_empty_elements(sgml.clone(), &["br", "img"], EmptyElementStyle::Xml);
+/// assert_eq!(xml.to_string(), r#"<p>One<br/>Two<br/><img src="x"/></p>"#);
+///
+/// let sgml = normalize_empty_elements(sgml, &["br", "img"], EmptyElementStyle::Sgml);
+/// assert_eq!(sgml.to_string(), r#"<p>One<br>Two<br><img src="x"></p>"#);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`lowercase_names`]: crate::parser::ParserBuilder::lowercase_names
+/// [`uppercase_names`]: crate::parser::ParserBuilder::uppercase_names
+pub fn normalize_empty_elements<'a>(
+    fragment: SgmlFragment<'a>,
+    empty_elements: &[&str],
+    style: EmptyElementStyle,
+) -> SgmlFragment<'a> {
+    let is_empty_element = |name: &str| empty_elements.contains(&name);
+    let mut in_empty_element = false;
+
+    fragment
+        .into_iter()
+        .filter_map(|event| match event {
+            SgmlEvent::OpenStartTag { ref name } => {
+                in_empty_element = is_empty_element(name);
+                Some(event)
+            }
+            SgmlEvent::CloseStartTag | SgmlEvent::XmlCloseEmptyElement if in_empty_element => {
+                in_empty_element = false;
+                Some(match style {
+                    EmptyElementStyle::Xml => SgmlEvent::XmlCloseEmptyElement,
+                    EmptyElementStyle::Sgml => SgmlEvent::CloseStartTag,
+                })
+            }
+            SgmlEvent::EndTag { ref name } if is_empty_element(name) => None,
+            event => Some(event),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_normalize_empty_elements() {
+        let input = r#"<p>a<br>b<br/>c<img src=x></img><span/><b>d</b></p>"#;
+        let empty_elements = ["br", "img"];
+
+        let fragment = normalize_empty_elements(
+            parse(input).unwrap(),
+            &empty_elements,
+            EmptyElementStyle::Xml,
+        );
+        assert_eq!(
+            fragment.to_string(),
+            r#"<p>a<br/>b<br/>c<img src="x"/><span/><b>d</b></p>"#
+        );
+        fragment.validate_balanced().unwrap();
+
+        let fragment = normalize_empty_elements(
+            parse(input).unwrap(),
+            &empty_elements,
+            EmptyElementStyle::Sgml,
+        );
+        assert_eq!(
+            fragment.to_string(),
+            r#"<p>a<br>b<br>c<img src="x"><span/><b>d</b></p>"#
+        );
+    }
+}
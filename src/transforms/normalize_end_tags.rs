@@ -1,17 +1,32 @@
+use core::fmt;
+
+use crate::prelude::*;
 use crate::transforms::Transform;
 use crate::{text, SgmlEvent, SgmlFragment};
 
 /// The error type in the event tag normalization fails.
 ///
 /// This is returned by [`normalize_end_tags`].
-#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum NormalizationError {
-    #[error("unpaired end tag: </{0}>")]
     UnpairedEndTag(String),
-    #[error("empty tags (<> and </>) are not supported")]
     EmptyTagNotSupported,
 }
 
+impl fmt::Display for NormalizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NormalizationError::UnpairedEndTag(name) => write!(f, "unpaired end tag: </{}>", name),
+            NormalizationError::EmptyTagNotSupported => {
+                f.write_str("empty tags (<> and </>) are not supported")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NormalizationError {}
+
 /// Inserts omitted end tags, assuming they are only implied for text-only content.
 ///
 /// This is good enough for certain formats, like [OFX] 1.x, but not for others, e.g. [HTML].
@@ -20,7 +35,8 @@ pub enum NormalizationError {
 ///
 /// * Tag names are compared in a case-sensitive manner; if your data may mix cases,
 ///   you can configure your parser with [`lowercase_names`] or [`uppercase_names`] beforehand.
-/// * This transform does not support empty start tags (`<>`) or empty end tags (`</>`).
+/// * This transform does not support empty start tags (`<>`) or empty end tags (`</>`);
+///   use [`resolve_empty_tags`](crate::transforms::resolve_empty_tags) beforehand to name them.
 ///
 /// # Example
 ///
@@ -98,7 +114,7 @@ pub fn normalize_end_tags(mut fragment: SgmlFragment) -> Result<SgmlFragment, No
                 stack.push(name);
                 next_insertion_point = i;
             }
-            SgmlEvent::Character(text) => {
+            SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) => {
                 if next_insertion_point == i + 1 && text::is_blank(text) {
                     next_insertion_point = i;
                 }
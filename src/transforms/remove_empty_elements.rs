@@ -0,0 +1,210 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{text, SgmlEvent, SgmlFragment};
+
+/// Options for [`remove_empty_elements_with`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EmptyElementOptions {
+    /// Also remove empty elements that have attributes, like `<td class="pad"></td>`.
+    pub ignore_attributes: bool,
+    /// Also remove XML-style empty elements, like `<br/>`.
+    pub remove_xml_empty_elements: bool,
+}
+
+/// Removes elements without attributes whose content is empty or whitespace-only.
+///
+/// Removal applies bottom-up, so an element containing only empty elements is
+/// removed as well. XML-style empty elements (`<br/>`) are kept, and count as
+/// content of their parent; see [`remove_empty_elements_with`] for other options.
+///
+/// Any event other than whitespace-only text, such as a comment or a processing
+/// instruction, counts as content. Elements whose end tag is omitted, or that
+/// are closed by an end tag for an enclosing element, are always kept.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::remove_empty_elements;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse("<body><div><p> </p></div><p>Hello</p><hr/></body>")?;
+/// let sgml = remove_empty_elements(sgml);
+/// assert_eq!(sgml.to_string(), "<body><p>Hello</p><hr/></body>");
+/// # Ok(())
+/// # }
+/// ```
+pub fn remove_empty_elements(fragment: SgmlFragment) -> SgmlFragment {
+    remove_empty_elements_with(fragment, EmptyElementOptions::default())
+}
+
+/// Removes empty elements, as in [`remove_empty_elements`], with the given options.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::{remove_empty_elements_with, EmptyElementOptions};
+/// # fn main() -> sgmlish::Result<()> {
+/// let options = EmptyElementOptions {
+///     ignore_attributes: true,
+///     remove_xml_empty_elements: true,
+/// };
+/// let sgml = sgmlish::parse(r#"<tr><td class="pad"><br/></td><td>1</td></tr>"#)?;
+/// let sgml = remove_empty_elements_with(sgml, options);
+/// assert_eq!(sgml.to_string(), "<tr><td>1</td></tr>");
+/// # Ok(())
+/// # }
+/// ```
+pub fn remove_empty_elements_with(
+    fragment: SgmlFragment,
+    options: EmptyElementOptions,
+) -> SgmlFragment {
+    struct OpenElement<'a> {
+        name: Cow<'a, str>,
+        start: usize,
+        removable: bool,
+        has_content: bool,
+    }
+
+    fn mark_content(stack: &mut [OpenElement]) {
+        if let Some(parent) = stack.last_mut() {
+            parent.has_content = true;
+        }
+    }
+
+    let mut events = Vec::with_capacity(fragment.len());
+    let mut stack: Vec<OpenElement> = vec![];
+    let mut in_start_tag = false;
+
+    for event in fragment {
+        match &event {
+            SgmlEvent::OpenStartTag { name } => {
+                stack.push(OpenElement {
+                    name: name.clone(),
+                    start: events.len(),
+                    removable: true,
+                    has_content: false,
+                });
+                in_start_tag = true;
+            }
+            SgmlEvent::Attribute { .. }
+            | SgmlEvent::UnquotedAttribute { .. }
+            | SgmlEvent::QuotedAttribute { .. }
+                if in_start_tag =>
+            {
+                if !options.ignore_attributes {
+                    stack.last_mut().unwrap().removable = false;
+                }
+            }
+            SgmlEvent::CloseStartTag => in_start_tag = false,
+            SgmlEvent::XmlCloseEmptyElement => {
+                in_start_tag = false;
+                let element = stack.pop();
+                events.push(event);
+                match element {
+                    Some(element) if options.remove_xml_empty_elements && element.removable => {
+                        events.truncate(element.start);
+                    }
+                    _ => mark_content(&mut stack),
+                }
+                continue;
+            }
+            SgmlEvent::EndTag { name } => {
+                let position = stack
+                    .iter()
+                    .rposition(|element| name.is_empty() || element.name == *name);
+                match position {
+                    Some(position) => {
+                        let closes_others = position + 1 < stack.len();
+                        stack.truncate(position + 1);
+                        let element = stack.pop().unwrap();
+                        events.push(event);
+                        if element.removable && !element.has_content && !closes_others {
+                            events.truncate(element.start);
+                        } else {
+                            mark_content(&mut stack);
+                        }
+                        continue;
+                    }
+                    None => mark_content(&mut stack),
+                }
+            }
+            SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) => {
+                if !text::is_blank(text) {
+                    mark_content(&mut stack);
+                }
+            }
+            _ => mark_content(&mut stack),
+        }
+        events.push(event);
+    }
+
+    events.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_remove_empty_elements() {
+        let parser = Parser::builder().trim_whitespace(false).build();
+
+        let fragment = parser.parse("<div><p> </p></div>").unwrap();
+        assert_eq!(remove_empty_elements(fragment).len(), 0);
+
+        let fragment = parser
+            .parse("<body>\n<div><p> </p>\n<p></p></div>\n<p>text</p>\n</body>")
+            .unwrap();
+        assert_eq!(
+            remove_empty_elements(fragment).to_string(),
+            "<body>\n\n<p>text</p>\n</body>"
+        );
+    }
+
+    #[test]
+    fn test_remove_empty_elements_keeps_content() {
+        let input = concat!(
+            "<div class=x><p></p></div>",
+            "<div><?pi></div>",
+            "<div><br/></div>",
+            "<ul><li>one<li></ul>",
+            "<p>unclosed",
+        );
+        let fragment = parse(input).unwrap();
+        assert_eq!(
+            remove_empty_elements(fragment).to_string(),
+            concat!(
+                r#"<div class="x"></div>"#,
+                "<div><?pi></div>",
+                "<div><br/></div>",
+                "<ul><li>one<li></ul>",
+                "<p>unclosed",
+            )
+        );
+    }
+
+    #[test]
+    fn test_remove_empty_elements_with_options() {
+        let input = "<div class=x><p></p></div><div><br/></div><p>a<br/>b</p>";
+
+        let options = EmptyElementOptions {
+            ignore_attributes: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            remove_empty_elements_with(parse(input).unwrap(), options).to_string(),
+            "<div><br/></div><p>a<br/>b</p>"
+        );
+
+        let options = EmptyElementOptions {
+            remove_xml_empty_elements: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            remove_empty_elements_with(parse(input).unwrap(), options).to_string(),
+            r#"<div class="x"></div><p>ab</p>"#
+        );
+    }
+}
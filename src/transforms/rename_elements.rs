@@ -0,0 +1,81 @@
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::prelude::*;
+use crate::{SgmlEvent, SgmlFragment};
+
+/// Renames elements according to the given mapping of old names to new names.
+///
+/// Both start tags and end tags are renamed; elements missing from the map
+/// and attribute names are kept unchanged.
+///
+/// # Notes
+///
+/// * Tag names are compared in a case-sensitive manner, after any normalization
+///   performed by the parser; if your data may mix cases, you can configure your parser
+///   with [`lowercase_names`] or [`uppercase_names`] beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use sgmlish::transforms::rename_elements;
+/// # fn main() -> sgmlish::Result<()> {
+/// let mut map = HashMap::new();
+/// map.insert("b", "strong");
+///
+/// let sgml = sgmlish::parse("<p><b>Bold</b> text</p>")?;
+/// let sgml = rename_elements(sgml, &map);
+/// assert_eq!(sgml.to_string(), "<p><strong>Bold</strong>text</p>");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`lowercase_names`]: crate::parser::ParserBuilder::lowercase_names
+/// [`uppercase_names`]: crate::parser::ParserBuilder::uppercase_names
+pub fn rename_elements<'a, K, V>(
+    mut fragment: SgmlFragment<'a>,
+    map: &HashMap<K, V>,
+) -> SgmlFragment<'a>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: AsRef<str>,
+{
+    for event in fragment.iter_mut() {
+        if let SgmlEvent::OpenStartTag { name } | SgmlEvent::EndTag { name } = event {
+            if let Some(new_name) = map.get(name.as_ref()) {
+                *name = new_name.as_ref().to_owned().into();
+            }
+        }
+    }
+    fragment
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_rename_elements() {
+        let mut map = HashMap::new();
+        map.insert("b".to_owned(), "strong".to_owned());
+        map.insert("i".to_owned(), "em".to_owned());
+
+        let fragment =
+            parse(r#"<p b="b"><b>one <i>two <b>three</b></i></b><B>four</B><b/><bb>five</bb></p>"#)
+                .unwrap();
+
+        let result = rename_elements(fragment, &map);
+        assert_eq!(
+            result,
+            parse(concat!(
+                r#"<p b="b"><strong>one <em>two <strong>three</strong></em></strong>"#,
+                r#"<B>four</B><strong/><bb>five</bb></p>"#,
+            ))
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use alloc::borrow::Cow;
+
+use crate::prelude::*;
+use crate::{SgmlEvent, SgmlFragment};
+
+/// Gives empty start tags (`<>`) and empty end tags (`</>`) the names they stand for.
+///
+/// Following the SGML rules for documents with omitted tags:
+///
+/// * an empty end tag closes the innermost open element;
+/// * an empty start tag opens another element of the same type as the one
+///   most recently ended, or, if no element has ended yet, as the innermost open element.
+///
+/// Empty tags with nothing to refer to are left as they are. An end tag for
+/// an enclosing element (as in `<a><b></a>`) closes the elements it encloses,
+/// and only the element it names counts as the most recently ended.
+///
+/// Tag names are compared in a case-sensitive manner; if your data may mix cases,
+/// you can configure your parser with [`lowercase_names`] or [`uppercase_names`] beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::resolve_empty_tags;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse("<ul><li>One</><>Two</></>")?;
+/// let sgml = resolve_empty_tags(sgml);
+/// assert_eq!(sgml.to_string(), "<ul><li>One</li><li>Two</li></ul>");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`lowercase_names`]: crate::parser::ParserBuilder::lowercase_names
+/// [`uppercase_names`]: crate::parser::ParserBuilder::uppercase_names
+pub fn resolve_empty_tags(mut fragment: SgmlFragment) -> SgmlFragment {
+    let mut open_elements: Vec<Cow<str>> = vec![];
+    let mut last_ended: Option<Cow<str>> = None;
+
+    for event in fragment.iter_mut() {
+        match event {
+            SgmlEvent::OpenStartTag { name } => {
+                if name.is_empty() {
+                    if let Some(previous) = last_ended.as_ref().or_else(|| open_elements.last()) {
+                        *name = previous.clone();
+                    }
+                }
+                open_elements.push(name.clone());
+            }
+            SgmlEvent::XmlCloseEmptyElement => last_ended = open_elements.pop(),
+            SgmlEvent::EndTag { name } => {
+                if name.is_empty() {
+                    if let Some(innermost) = open_elements.last() {
+                        *name = innermost.clone();
+                    }
+                }
+                if let Some(position) = open_elements.iter().rposition(|open| open == name) {
+                    open_elements.truncate(position);
+                    last_ended = Some(name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fragment
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_empty_tags() {
+        let resolve = |input| resolve_empty_tags(parse(input).unwrap()).to_string();
+
+        assert_eq!(resolve("<p>text</><p>more</>"), "<p>text</p><p>more</p>");
+        assert_eq!(resolve("<p>one</p><>two</>"), "<p>one</p><p>two</p>");
+        assert_eq!(
+            resolve("<doc><sec><>x</></>"),
+            "<doc><sec><sec>x</sec></sec>"
+        );
+        assert_eq!(
+            resolve("<doc><sec><p>a</doc><>b</>"),
+            "<doc><sec><p>a</doc><doc>b</doc>"
+        );
+        assert_eq!(
+            resolve("<doc><br/><>x</></doc>"),
+            "<doc><br/><br>x</br></doc>"
+        );
+    }
+
+    #[test]
+    fn test_resolve_empty_tags_without_reference() {
+        let fragment = resolve_empty_tags(parse("<>x</>").unwrap());
+        assert_eq!(
+            fragment.as_slice(),
+            [
+                SgmlEvent::OpenStartTag { name: "".into() },
+                SgmlEvent::CloseStartTag,
+                SgmlEvent::Character("x".into()),
+                SgmlEvent::EndTag { name: "".into() },
+            ]
+        );
+
+        let fragment = resolve_empty_tags(parse("<p>x</p></>").unwrap());
+        assert_eq!(fragment.to_string(), "<p>x</p></>");
+    }
+}
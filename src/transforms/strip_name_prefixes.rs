@@ -0,0 +1,76 @@
+use alloc::borrow::Cow;
+use core::mem;
+
+use crate::{SgmlEvent, SgmlFragment};
+
+/// Removes namespace prefixes, like the `html:` in `<html:body>`, from element names.
+///
+/// Everything up to and including the first colon is removed from the names in
+/// start and end tags, so `<a:b:c>` becomes `<b:c>`; names without a colon are kept unchanged.
+/// This allows deserializing documents that use XML namespace prefixes
+/// into types whose fields and variants are named without them.
+///
+/// Attribute names are left untouched, so that namespace declarations
+/// (`xmlns:html="..."`) do not collide with one another.
+///
+/// # Notes
+///
+/// * Prefixes are removed without regard to the namespace they stand for,
+///   so elements that only differ by prefix, like `<a:item>` and `<b:item>`,
+///   become indistinguishable: both will be deserialized into an `item` field,
+///   which fails with a duplicate field error unless the field accepts a sequence.
+///   Use [`rename_elements`] first if such elements must be kept apart.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::transforms::strip_name_prefixes;
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse(r#"<html:p xmlns:html="http://www.w3.org/1999/xhtml">Hi</html:p>"#)?;
+/// let sgml = strip_name_prefixes(sgml);
+/// assert_eq!(sgml.to_string(), r#"<p xmlns:html="http://www.w3.org/1999/xhtml">Hi</p>"#);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`rename_elements`]: crate::transforms::rename_elements
+pub fn strip_name_prefixes(mut fragment: SgmlFragment) -> SgmlFragment {
+    for event in fragment.iter_mut() {
+        if let SgmlEvent::OpenStartTag { name } | SgmlEvent::EndTag { name } = event {
+            if let Some(colon) = name.find(':') {
+                *name = match mem::take(name) {
+                    Cow::Borrowed(name) => Cow::Borrowed(&name[colon + 1..]),
+                    Cow::Owned(mut name) => {
+                        name.drain(..=colon);
+                        Cow::Owned(name)
+                    }
+                };
+            }
+        }
+    }
+    fragment
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Parser};
+
+    use super::*;
+
+    #[test]
+    fn test_strip_name_prefixes() {
+        let fragment = parse(r#"<x:doc y:id=1><a:b:c>one</a:b:c><plain/></></x:doc>"#).unwrap();
+        let result = strip_name_prefixes(fragment);
+        assert_eq!(
+            result,
+            parse(r#"<doc y:id=1><b:c>one</b:c><plain/></></doc>"#).unwrap()
+        );
+
+        let fragment = Parser::builder()
+            .lowercase_names()
+            .parse("<X:DOC>two</X:DOC>")
+            .unwrap();
+        let result = strip_name_prefixes(fragment);
+        assert_eq!(result, parse("<doc>two</doc>").unwrap());
+    }
+}
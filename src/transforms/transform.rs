@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::{SgmlEvent, SgmlFragment};
 
 /// A convenience helper to insert and remove events from a [`SgmlFragment`].
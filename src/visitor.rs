@@ -0,0 +1,215 @@
+use crate::prelude::*;
+use crate::{SgmlEvent, SgmlFragment};
+
+/// A depth-first walk over the elements of a fragment.
+///
+/// Used by [`SgmlFragment::walk`], which calls these methods in document order.
+/// All methods do nothing by default, so implementations only need to override
+/// the ones they care about.
+///
+/// The `depth` of an element is the number of elements enclosing it, so top-level
+/// elements have depth 0; text and other events get the depth of an element that
+/// would appear in their place.
+///
+/// # Example
+///
+/// ```rust
+/// # use sgmlish::Visitor;
+/// #[derive(Default)]
+/// struct TableOfContents(Vec<String>);
+///
+/// impl Visitor for TableOfContents {
+///     fn text(&mut self, text: &str, depth: usize) {
+///         self.0.push(format!("{}{}", "  ".repeat(depth - 1), text));
+///     }
+/// }
+///
+/// # fn main() -> sgmlish::Result<()> {
+/// let sgml = sgmlish::parse("<h1>Intro</h1><section><h2>Usage</h2></section>")?;
+/// let mut toc = TableOfContents::default();
+/// sgml.walk(&mut toc);
+/// assert_eq!(toc.0, ["Intro", "  Usage"]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Visitor {
+    /// Called once a start tag is complete, with its attributes in order.
+    fn enter_element(&mut self, name: &str, attributes: &[(&str, Option<&str>)], depth: usize) {
+        let _ = (name, attributes, depth);
+    }
+
+    /// Called when an element ends, with the same name and depth as
+    /// [`enter_element`](Visitor::enter_element).
+    ///
+    /// This is called for every element entered, innermost first, even when its
+    /// end tag was omitted: an end tag for an enclosing element (or an empty end tag,
+    /// `</>`) closes it, as does the end of the fragment. XML-style empty elements
+    /// (`<br/>`) are left immediately after being entered.
+    fn leave_element(&mut self, name: &str, depth: usize) {
+        let _ = (name, depth);
+    }
+
+    /// Called for text content, including whitespace-only text.
+    fn text(&mut self, text: &str, depth: usize) {
+        let _ = (text, depth);
+    }
+
+    /// Called for any other event, such as processing instructions,
+    /// markup declarations and marked sections that were kept unmodified.
+    fn other(&mut self, event: &SgmlEvent, depth: usize) {
+        let _ = (event, depth);
+    }
+}
+
+impl<'a> SgmlFragment<'a> {
+    /// Walks the elements of the fragment depth-first, reporting them to the given [`Visitor`].
+    ///
+    /// Tag names are compared in a case-sensitive manner when matching end tags;
+    /// end tags that match no open element are ignored.
+    pub fn walk<V: Visitor>(&self, visitor: &mut V) {
+        let mut open_elements: Vec<&str> = Vec::new();
+        let mut start_tag: Option<StartTag> = None;
+
+        for event in self.iter() {
+            match event {
+                SgmlEvent::OpenStartTag { name } => start_tag = Some((name, Vec::new())),
+                SgmlEvent::Attribute { .. }
+                | SgmlEvent::UnquotedAttribute { .. }
+                | SgmlEvent::QuotedAttribute { .. } => {
+                    if let (Some((_, attributes)), Some(attribute)) =
+                        (&mut start_tag, event.as_attribute())
+                    {
+                        attributes.push(attribute);
+                    }
+                }
+                SgmlEvent::CloseStartTag => {
+                    if let Some((name, attributes)) = start_tag.take() {
+                        visitor.enter_element(name, &attributes, open_elements.len());
+                        open_elements.push(name);
+                    }
+                }
+                SgmlEvent::XmlCloseEmptyElement => {
+                    if let Some((name, attributes)) = start_tag.take() {
+                        visitor.enter_element(name, &attributes, open_elements.len());
+                        visitor.leave_element(name, open_elements.len());
+                    }
+                }
+                SgmlEvent::EndTag { name } => {
+                    let position = open_elements
+                        .iter()
+                        .rposition(|open| name.is_empty() || open == name);
+                    if let Some(position) = position {
+                        leave_elements(visitor, &mut open_elements, position);
+                    }
+                }
+                SgmlEvent::Character(text) | SgmlEvent::Whitespace(text) => {
+                    visitor.text(text, open_elements.len())
+                }
+                event => visitor.other(event, open_elements.len()),
+            }
+        }
+
+        leave_elements(visitor, &mut open_elements, 0);
+    }
+}
+
+/// A start tag whose attributes are still being gathered.
+type StartTag<'e> = (&'e str, Vec<(&'e str, Option<&'e str>)>);
+
+/// Leaves open elements, innermost first, until only `depth` elements remain open.
+fn leave_elements<V: Visitor>(visitor: &mut V, open_elements: &mut Vec<&str>, depth: usize) {
+    while open_elements.len() > depth {
+        let name = open_elements.pop().unwrap();
+        visitor.leave_element(name, open_elements.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Parser};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Visitor for Recorder {
+        fn enter_element(&mut self, name: &str, attributes: &[(&str, Option<&str>)], depth: usize) {
+            self.0.push(format!(
+                "{}enter {} {:?}",
+                "  ".repeat(depth),
+                name,
+                attributes
+            ));
+        }
+
+        fn leave_element(&mut self, name: &str, depth: usize) {
+            self.0.push(format!("{}leave {}", "  ".repeat(depth), name));
+        }
+
+        fn text(&mut self, text: &str, depth: usize) {
+            self.0.push(format!("{}text {}", "  ".repeat(depth), text));
+        }
+
+        fn other(&mut self, event: &SgmlEvent, depth: usize) {
+            self.0
+                .push(format!("{}other {}", "  ".repeat(depth), event));
+        }
+    }
+
+    #[test]
+    fn test_walk() {
+        let fragment = Parser::builder()
+            .unquoted_attribute_events(true)
+            .build()
+            .parse("<?pi><doc id=1><p class='x'>one<br/>two</p><hr></doc>")
+            .unwrap();
+        let mut recorder = Recorder::default();
+        fragment.walk(&mut recorder);
+        assert_eq!(
+            recorder.0,
+            [
+                "other <?pi>",
+                r#"enter doc [("id", Some("1"))]"#,
+                r#"  enter p [("class", Some("x"))]"#,
+                "    text one",
+                "    enter br []",
+                "    leave br",
+                "    text two",
+                "  leave p",
+                "  enter hr []",
+                "  leave hr",
+                "leave doc",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_walk_omitted_end_tags() {
+        let fragment = parse("<ul><li>one<li>two</ul></x><p>a<b>b</><i>unclosed").unwrap();
+        let mut recorder = Recorder::default();
+        fragment.walk(&mut recorder);
+        assert_eq!(
+            recorder.0,
+            [
+                "enter ul []",
+                "  enter li []",
+                "    text one",
+                "    enter li []",
+                "      text two",
+                "    leave li",
+                "  leave li",
+                "leave ul",
+                "enter p []",
+                "  text a",
+                "  enter b []",
+                "    text b",
+                "  leave b",
+                "  enter i []",
+                "    text unclosed",
+                "  leave i",
+                "leave p",
+            ]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 #![cfg(feature = "serde")]
 
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 use rust_decimal::Decimal;
@@ -257,6 +258,52 @@ fn test_html_style_boolean() -> sgmlish::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_boolean_attribute_values() -> sgmlish::Result<()> {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Form {
+        #[serde(rename = "option")]
+        options: Vec<SelectOption>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct SelectOption {
+        #[serde(default)]
+        selected: bool,
+    }
+
+    let input = r##"
+        <form>
+            <option selected></option>
+            <option selected=""></option>
+            <option selected="selected"></option>
+            <option selected="true"></option>
+            <option selected="YES"></option>
+            <option selected="1"></option>
+            <option></option>
+            <option selected="false"></option>
+            <option selected="no"></option>
+            <option selected="0"></option>
+            <option><selected>yes</selected></option>
+            <option><selected>No</selected></option>
+        </form>
+    "##;
+
+    let form = sgmlish::from_fragment::<Form>(sgmlish::parse(input)?)?;
+    let selected = form.options.iter().map(|o| o.selected).collect::<Vec<_>>();
+    assert_eq!(
+        selected,
+        [true, true, true, true, true, true, false, false, false, false, true, false]
+    );
+
+    let invalid = sgmlish::parse(r#"<form><option selected="maybe"></option></form>"#)?;
+    assert!(sgmlish::from_fragment::<Form>(invalid).is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_complex_enum() {
     init_logger();
@@ -397,9 +444,7 @@ fn test_reject_markup_declarations() {
     init_logger();
 
     #[derive(Debug, Deserialize)]
-    struct Test {
-        name: String,
-    }
+    struct Test {}
 
     let input = r##"
         <!DOCTYPE test>
@@ -447,9 +492,7 @@ fn test_reject_processing_instructions() {
     init_logger();
 
     #[derive(Debug, Deserialize)]
-    struct Test {
-        name: String,
-    }
+    struct Test {}
 
     let input = r##"
         <test>
@@ -462,7 +505,7 @@ fn test_reject_processing_instructions() {
 
     let err = sgmlish::from_fragment::<Test>(sgml).unwrap_err();
     assert!(matches!(
-        err,
+        err.inner(),
         DeserializationError::Unsupported(SgmlEvent::ProcessingInstruction(pi)) if pi == "<?experiment>"
     ));
 }
@@ -609,3 +652,841 @@ fn test_enum_untagged() {
         }
     );
 }
+
+#[test]
+fn test_deserialize_any() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(untagged)]
+    enum Value {
+        Unit,
+        String(String),
+        Seq(Vec<Value>),
+        Map(BTreeMap<String, Value>),
+    }
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_owned())
+    }
+
+    let input = r##"
+        <post id="1">
+            <title>Hello</title>
+            <tag>a</tag>
+            <tag>b</tag>
+            <note lang="en">Hi</note>
+            <author><name>Jane</name></author>
+            <author><name>John</name></author>
+            <body>Some <b>bold</b></body>
+            <draft></draft>
+        </post>
+    "##;
+    let sgml = sgmlish::parse(input).unwrap();
+    let value = sgml.deserialize::<Value>().unwrap();
+    assert_eq!(
+        value,
+        map(vec![
+            ("id", string("1")),
+            ("title", string("Hello")),
+            ("tag", Value::Seq(vec![string("a"), string("b")])),
+            (
+                "note",
+                map(vec![("lang", string("en")), ("$value", string("Hi"))])
+            ),
+            (
+                "author",
+                Value::Seq(vec![
+                    map(vec![("name", string("Jane"))]),
+                    map(vec![("name", string("John"))]),
+                ])
+            ),
+            (
+                "body",
+                map(vec![("b", string("bold")), ("$value", string("Some"))])
+            ),
+            ("draft", Value::Unit),
+        ])
+    );
+
+    // Repeated elements are grouped even when not adjacent
+    let input = r##"
+        <post>
+            <tag>a</tag>
+            <note>Hi</note>
+            <tag>b</tag>
+            Text
+            <list><tag>c</tag><tag>d</tag></list>
+            <tag>e</tag>
+        </post>
+    "##;
+    let sgml = sgmlish::parse(input).unwrap();
+    let value = sgml.deserialize::<Value>().unwrap();
+    assert_eq!(
+        value,
+        map(vec![
+            (
+                "tag",
+                Value::Seq(vec![string("a"), string("b"), string("e")])
+            ),
+            ("note", string("Hi")),
+            (
+                "list",
+                map(vec![("tag", Value::Seq(vec![string("c"), string("d")]))])
+            ),
+            ("$value", string("Text")),
+        ])
+    );
+}
+
+#[test]
+fn test_text_field() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Paragraph {
+        class: String,
+        #[serde(rename = "$text")]
+        body: String,
+    }
+
+    let input = r##"<p class="intro">Some <b>bold</b> and <i><b>nested</b></i> text</p>"##;
+    let sgml = Parser::builder()
+        .trim_whitespace(false)
+        .parse(input)
+        .unwrap();
+    let paragraph = sgml.deserialize::<Paragraph>().unwrap();
+    assert_eq!(
+        paragraph,
+        Paragraph {
+            class: "intro".into(),
+            body: "Some bold and nested text".into(),
+        }
+    );
+}
+
+#[test]
+fn test_tag_field() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Section {
+        title: String,
+        #[serde(rename = "$value")]
+        admonitions: Vec<Admonition>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Admonition {
+        #[serde(rename = "$tag")]
+        kind: String,
+        #[serde(rename = "$value")]
+        text: String,
+        id: Option<String>,
+    }
+
+    let input = r##"
+        <SECTION TITLE="Setup">
+            <NOTE>Read this first</NOTE>
+            <WARNING ID="w1">Do not unplug</WARNING>
+            <NOTE>Almost done</NOTE>
+        </SECTION>
+    "##;
+    let sgml = Parser::builder().lowercase_names().parse(input).unwrap();
+    let section = sgml.deserialize::<Section>().unwrap();
+    assert_eq!(
+        section,
+        Section {
+            title: "Setup".into(),
+            admonitions: vec![
+                Admonition {
+                    kind: "note".into(),
+                    text: "Read this first".into(),
+                    id: None,
+                },
+                Admonition {
+                    kind: "warning".into(),
+                    text: "Do not unplug".into(),
+                    id: Some("w1".into()),
+                },
+                Admonition {
+                    kind: "note".into(),
+                    text: "Almost done".into(),
+                    id: None,
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn test_unquoted_attribute_events() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Cell {
+        colspan: u32,
+        class: String,
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let sgml = Parser::builder()
+        .unquoted_attribute_events(true)
+        .quoted_attribute_events(true)
+        .parse(r#"<td colspan=2 class="wide">text</td>"#)
+        .unwrap();
+    assert!(sgml
+        .iter()
+        .any(|event| matches!(event, SgmlEvent::UnquotedAttribute { .. })));
+    assert!(sgml
+        .iter()
+        .any(|event| matches!(event, SgmlEvent::QuotedAttribute { .. })));
+    let cell = sgml.deserialize::<Cell>().unwrap();
+    assert_eq!(
+        cell,
+        Cell {
+            colspan: 2,
+            class: "wide".into(),
+            text: "text".into(),
+        }
+    );
+}
+
+#[test]
+fn test_option_fields() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Item {
+        id: Option<u32>,
+        label: Option<String>,
+        note: Option<String>,
+        price: Option<Price>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Price {
+        currency: Option<String>,
+        #[serde(rename = "$value")]
+        amount: String,
+    }
+
+    let sgml = sgmlish::parse("<item></item>").unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Item>(sgml).unwrap(),
+        Item {
+            id: None,
+            label: None,
+            note: None,
+            price: None,
+        }
+    );
+
+    let sgml = sgmlish::parse(
+        r#"<item id="7" label=""><note></note><price currency="EUR">1.50</price></item>"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Item>(sgml).unwrap(),
+        Item {
+            id: Some(7),
+            label: Some("".to_owned()),
+            note: Some("".to_owned()),
+            price: Some(Price {
+                currency: Some("EUR".to_owned()),
+                amount: "1.50".to_owned(),
+            }),
+        }
+    );
+
+    let sgml = sgmlish::parse("<item><note>a</note><note>b</note></item>").unwrap();
+    let err = sgmlish::from_fragment::<Item>(sgml).unwrap_err();
+    assert!(
+        err.to_string().contains("duplicate field `note`"),
+        "{}",
+        err
+    );
+
+    let sgml = sgmlish::parse(r#"<item id="1"><id>2</id></item>"#).unwrap();
+    let err = sgmlish::from_fragment::<Item>(sgml).unwrap_err();
+    assert!(err.to_string().contains("duplicate field `id`"), "{}", err);
+}
+
+#[test]
+fn test_error_path() {
+    init_logger();
+
+    #[derive(Debug, Deserialize)]
+    struct Root {
+        #[serde(rename = "item")]
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Item {
+        id: Option<u32>,
+        price: Option<u32>,
+    }
+
+    let sgml =
+        sgmlish::parse("<root><item id=1><price>5</price></item><item></item></root>").unwrap();
+    let root = sgmlish::from_fragment::<Root>(sgml).unwrap();
+    assert_eq!(root.items.len(), 2);
+    assert_eq!((root.items[0].id, root.items[0].price), (Some(1), Some(5)));
+    assert_eq!((root.items[1].id, root.items[1].price), (None, None));
+
+    let sgml = sgmlish::parse(
+        "<root><item><price>1</price></item><item id=2></item><item><price>x</price></item></root>",
+    )
+    .unwrap();
+    let err = sgmlish::from_fragment::<Root>(sgml).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "error parsing integer value \"x\" as u32: invalid digit found in string at root > item[2] > price"
+    );
+    assert_eq!(
+        err.path().collect::<Vec<_>>(),
+        ["root", "item", "[2]", "price"]
+    );
+    assert!(matches!(
+        err.inner(),
+        DeserializationError::ParseIntError { .. }
+    ));
+
+    let sgml = sgmlish::parse("<root><item id=z></item></root>").unwrap();
+    let err = sgmlish::from_fragment::<Root>(sgml).unwrap_err();
+    assert!(
+        err.to_string().ends_with(" at root > item[0] > @id"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_scalars() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Reading {
+        id: i64,
+        count: u64,
+        ratio: f64,
+        valid: bool,
+        offset: i32,
+        total: u64,
+        value: f64,
+        stale: bool,
+    }
+
+    let sgml = sgmlish::parse(
+        "<reading id=' -42 ' count=7 ratio='\t0.5\n' valid=' yes '>
+            <offset> -3 </offset>
+            <total>18446744073709551615</total>
+            <value>\n  1e3\n</value>
+            <stale> false </stale>
+        </reading>",
+    )
+    .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Reading>(sgml).unwrap(),
+        Reading {
+            id: -42,
+            count: 7,
+            ratio: 0.5,
+            valid: true,
+            offset: -3,
+            total: u64::MAX,
+            value: 1000.0,
+            stale: false,
+        }
+    );
+}
+
+#[test]
+fn test_scalar_errors() {
+    init_logger();
+
+    #[derive(Debug, Deserialize)]
+    struct Attribute<T> {
+        #[allow(dead_code)]
+        n: T,
+    }
+
+    fn error<T: serde::de::DeserializeOwned + std::fmt::Debug>(input: &str) -> String {
+        let sgml = sgmlish::parse(input).unwrap();
+        sgmlish::from_fragment::<Attribute<T>>(sgml)
+            .unwrap_err()
+            .to_string()
+    }
+
+    assert_eq!(
+        error::<u8>("<x n=' 300 '></x>"),
+        "error parsing integer value \"300\" as u8: number too large to fit in target type at x > @n"
+    );
+    assert_eq!(
+        error::<f32>("<x><n>one</n></x>"),
+        "error parsing float value \"one\" as f32: invalid float literal at x > n"
+    );
+    assert_eq!(
+        error::<bool>("<x><n> maybe </n></x>"),
+        "invalid value: string \"maybe\", expected a boolean at x > n"
+    );
+}
+
+#[test]
+fn test_bytes() {
+    init_logger();
+
+    /// Collects bytes, recording whether they were borrowed from the input
+    #[derive(Debug, PartialEq)]
+    struct Bytes {
+        bytes: Vec<u8>,
+        borrowed: bool,
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+        type Value = Bytes;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("bytes")
+        }
+
+        fn visit_borrowed_bytes<E>(self, bytes: &'de [u8]) -> Result<Bytes, E> {
+            Ok(Bytes {
+                bytes: bytes.to_vec(),
+                borrowed: true,
+            })
+        }
+
+        fn visit_byte_buf<E>(self, bytes: Vec<u8>) -> Result<Bytes, E> {
+            Ok(Bytes {
+                bytes,
+                borrowed: false,
+            })
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bytes {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_bytes(BytesVisitor)
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Attachment {
+        name: Bytes,
+        data: Bytes,
+        #[serde(deserialize_with = "byte_buf")]
+        checksum: Vec<u8>,
+    }
+
+    fn byte_buf<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer
+            .deserialize_byte_buf(BytesVisitor)
+            .map(|bytes| bytes.bytes)
+    }
+
+    let sgml = sgmlish::parse(
+        "<attachment name='caf&#233;'><data>aGVsbG8=</data><checksum>ab12</checksum></attachment>",
+    )
+    .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Attachment>(sgml).unwrap(),
+        Attachment {
+            name: Bytes {
+                bytes: "café".as_bytes().to_vec(),
+                borrowed: false,
+            },
+            data: Bytes {
+                bytes: b"aGVsbG8=".to_vec(),
+                borrowed: true,
+            },
+            checksum: b"ab12".to_vec(),
+        }
+    );
+}
+
+#[test]
+fn test_flatten() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Link {
+        #[serde(flatten)]
+        target: Target,
+        label: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Target {
+        href: String,
+        rel: Option<String>,
+    }
+
+    let sgml =
+        sgmlish::parse(r#"<link href="/home" rel="start"><label>Home</label></link>"#).unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Link>(sgml).unwrap(),
+        Link {
+            target: Target {
+                href: "/home".to_owned(),
+                rel: Some("start".to_owned()),
+            },
+            label: "Home".to_owned(),
+        }
+    );
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Cell {
+        #[serde(flatten)]
+        span: Span,
+        #[serde(flatten)]
+        extra: std::collections::BTreeMap<String, String>,
+        note: Note,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Span {
+        #[serde(deserialize_with = "sgmlish::de::deserialize_from_str")]
+        colspan: u32,
+        #[serde(deserialize_with = "sgmlish::de::deserialize_from_str")]
+        wide: bool,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Note {
+        lang: String,
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let sgml = sgmlish::parse(
+        r#"<td colspan="2" wide="true" class="x" id="c1"><note lang="en">hi</note></td>"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Cell>(sgml).unwrap(),
+        Cell {
+            span: Span {
+                colspan: 2,
+                wide: true,
+            },
+            extra: vec![
+                ("class".to_owned(), "x".to_owned()),
+                ("id".to_owned(), "c1".to_owned()),
+            ]
+            .into_iter()
+            .collect(),
+            note: Note {
+                lang: "en".to_owned(),
+                text: "hi".to_owned(),
+            },
+        }
+    );
+}
+
+#[test]
+fn test_newtype_and_tuple_structs() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Celsius(f64);
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Reading {
+        temperature: Celsius,
+        range: Range,
+        point: (i32, i32),
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Range(u32, u32);
+
+    let sgml = sgmlish::parse(
+        r#"<reading temperature="21.5"><range><v>10</v><v>20</v></range><point><x>-1</x><y>2</y></point></reading>"#,
+    )
+    .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Reading>(sgml).unwrap(),
+        Reading {
+            temperature: Celsius(21.5),
+            range: Range(10, 20),
+            point: (-1, 2),
+        }
+    );
+
+    let sgml = sgmlish::parse("<t>-4</t>").unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Celsius>(sgml).unwrap(),
+        Celsius(-4.0)
+    );
+}
+
+#[test]
+fn test_map_from_child_elements() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Product {
+        name: String,
+        prices: std::collections::HashMap<String, f64>,
+    }
+
+    let sgml = sgmlish::parse(
+        "<product><name>Widget</name><prices><usd>5</usd><eur>4.5</eur></prices></product>",
+    )
+    .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Product>(sgml).unwrap(),
+        Product {
+            name: "Widget".to_owned(),
+            prices: vec![("usd".to_owned(), 5.0), ("eur".to_owned(), 4.5)]
+                .into_iter()
+                .collect(),
+        }
+    );
+
+    /// A map that remembers insertion order, like `IndexMap`
+    #[derive(Debug, PartialEq)]
+    struct Ordered(Vec<(String, String)>);
+
+    impl<'de> Deserialize<'de> for Ordered {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct OrderedVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for OrderedVisitor {
+                type Value = Ordered;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a map")
+                }
+
+                fn visit_map<A: serde::de::MapAccess<'de>>(
+                    self,
+                    mut map: A,
+                ) -> Result<Ordered, A::Error> {
+                    let mut entries = vec![];
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(Ordered(entries))
+                }
+            }
+
+            deserializer.deserialize_map(OrderedVisitor)
+        }
+    }
+
+    let sgml =
+        sgmlish::parse(r#"<prices currency="all"><usd>5</usd><eur>4</eur><brl>25</brl></prices>"#)
+            .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Ordered>(sgml).unwrap(),
+        Ordered(vec![
+            ("currency".to_owned(), "all".to_owned()),
+            ("usd".to_owned(), "5".to_owned()),
+            ("eur".to_owned(), "4".to_owned()),
+            ("brl".to_owned(), "25".to_owned()),
+        ])
+    );
+}
+
+#[test]
+fn test_separate_whitespace() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Poem {
+        title: String,
+        line: Vec<String>,
+    }
+
+    let parser = Parser::builder()
+        .whitespace_handling(sgmlish::parser::WhitespaceHandling::Separate)
+        .build();
+    let sgml = parser
+        .parse(
+            "<poem>\n  <title>Untitled</title>\n  <line> one </line>\n  <line>two</line>\n</poem>",
+        )
+        .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Poem>(sgml).unwrap(),
+        Poem {
+            title: "Untitled".to_owned(),
+            line: vec![" one ".to_owned(), "two".to_owned()],
+        }
+    );
+}
+
+#[test]
+fn test_char_and_unit() {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Draft;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Grade {
+        letter: char,
+        sign: char,
+        draft: Draft,
+        reviewed: Option<()>,
+    }
+
+    let sgml = sgmlish::parse("<grade letter=B><sign>+</sign><draft></draft></grade>").unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Grade>(sgml).unwrap(),
+        Grade {
+            letter: 'B',
+            sign: '+',
+            draft: Draft,
+            reviewed: None,
+        }
+    );
+
+    let sgml =
+        sgmlish::parse("<grade letter=\u{e9} sign='-'><draft/><reviewed></reviewed></grade>")
+            .unwrap();
+    assert_eq!(
+        sgmlish::from_fragment::<Grade>(sgml).unwrap(),
+        Grade {
+            letter: '\u{e9}',
+            sign: '-',
+            draft: Draft,
+            reviewed: Some(()),
+        }
+    );
+
+    for input in &[
+        "<grade letter=AB sign='-'><draft></draft></grade>",
+        "<grade letter='' sign='-'><draft></draft></grade>",
+    ] {
+        let sgml = sgmlish::parse(input).unwrap();
+        let err = sgmlish::from_fragment::<Grade>(sgml).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected a single character at grade > @letter"),
+            "{}",
+            err
+        );
+    }
+}
+
+#[test]
+fn test_strip_name_prefixes() -> sgmlish::Result<()> {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Feed {
+        title: String,
+        #[serde(rename = "entry")]
+        entries: Vec<Entry>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Entry {
+        id: u32,
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let input = r##"
+        <atom:feed xmlns:atom="http://www.w3.org/2005/Atom">
+            <atom:title>News</atom:title>
+            <atom:entry id="1">First</atom:entry>
+            <other:entry id="2">Second</other:entry>
+        </atom:feed>
+    "##;
+
+    let sgml = sgmlish::parse(input)?;
+    let sgml = sgmlish::transforms::strip_name_prefixes(sgml);
+    let feed = sgmlish::from_fragment::<Feed>(sgml)?;
+    assert_eq!(
+        feed,
+        Feed {
+            title: "News".to_owned(),
+            entries: vec![
+                Entry {
+                    id: 1,
+                    text: "First".to_owned(),
+                },
+                Entry {
+                    id: 2,
+                    text: "Second".to_owned(),
+                },
+            ],
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_path() -> sgmlish::Result<()> {
+    init_logger();
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Record {
+        #[serde(rename = "meta/id")]
+        id: u32,
+        #[serde(rename = "meta/source/name")]
+        source: Option<String>,
+        #[serde(rename = "meta/created")]
+        created: Option<String>,
+        title: String,
+    }
+
+    let input = r##"
+        <record>
+            <meta>
+                <created>2021-07-01</created>
+                <id>7</id>
+                <id>8</id>
+                <source><name>Archive</name></source>
+            </meta>
+            <title>Example</title>
+        </record>
+    "##;
+    let sgml = sgmlish::parse(input)?;
+    let record = sgmlish::from_fragment::<Record>(sgml)?;
+    assert_eq!(
+        record,
+        Record {
+            id: 7,
+            source: Some("Archive".to_owned()),
+            created: Some("2021-07-01".to_owned()),
+            title: "Example".to_owned(),
+        }
+    );
+
+    let input = "<record><meta><id>7</id></meta><title>Example</title></record>";
+    let sgml = sgmlish::parse(input)?;
+    let record = sgmlish::from_fragment::<Record>(sgml)?;
+    assert_eq!(record.source, None);
+    assert_eq!(record.created, None);
+    assert_eq!(record.title, "Example");
+
+    let input = "<record><meta><id>x</id></meta><title>Example</title></record>";
+    let sgml = sgmlish::parse(input)?;
+    let err = sgmlish::from_fragment::<Record>(sgml).unwrap_err();
+    assert!(err.to_string().contains("at record > meta > id"), "{}", err);
+
+    let input = "<record><meta><id>7</id></meta><meta><id>8</id></meta></record>";
+    let sgml = sgmlish::parse(input)?;
+    let err = sgmlish::from_fragment::<Record>(sgml).unwrap_err();
+    assert!(err.to_string().contains("duplicate field"), "{}", err);
+
+    Ok(())
+}
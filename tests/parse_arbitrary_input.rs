@@ -0,0 +1,118 @@
+//! A lightweight, deterministic counterpart to the fuzz target in `fuzz/`,
+//! checking that the parser never panics on inputs made of markup fragments.
+
+use sgmlish::parser::{MarkedSectionHandling, WhitespaceHandling};
+use sgmlish::Parser;
+
+const PIECES: &[&str] = &[
+    "<",
+    ">",
+    "</",
+    "/>",
+    "<>",
+    "</>",
+    "<!",
+    "<!>",
+    "<?",
+    "?>",
+    "<!--",
+    "--",
+    "-->",
+    "<![",
+    "[",
+    "]",
+    "]]>",
+    "CDATA",
+    "RCDATA",
+    "IGNORE",
+    "INCLUDE",
+    "TEMP",
+    "%",
+    "%e;",
+    "&",
+    "&e;",
+    "&#",
+    "&#x",
+    "&#1114112;",
+    "&#xD800;",
+    "&#RE;",
+    ";",
+    "#",
+    "=",
+    "'",
+    "\"",
+    " ",
+    "\t",
+    "\n",
+    "\r",
+    "\u{feff}",
+    "é",
+    "a",
+    "b",
+    "x",
+    "1",
+    "<!DOCTYPE a [",
+    "<!ENTITY e \"",
+    "<!ENTITY % e '",
+    "SYSTEM",
+    "SDATA",
+    "xml",
+    "version=\"1.0\"",
+    "<a>",
+    "</a>",
+    "<b c=d>",
+];
+
+/// A xorshift generator, so that failures are reproducible without extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+#[test]
+fn test_parse_does_not_panic() {
+    let parsers = [
+        Parser::new(),
+        Parser::builder()
+            .marked_section_handling(MarkedSectionHandling::ExpandAll)
+            .expand_entities(|_| Some("&e;<a>"))
+            .expand_parameter_entities(|_| Some("%e; IGNORE"))
+            .resolve_internal_entities(true)
+            .predefined_xml_entities(true)
+            .resolve_function_chars(true)
+            .require_balanced(true)
+            .build(),
+        Parser::builder()
+            .marked_section_handling(MarkedSectionHandling::KeepUnmodified)
+            .whitespace_handling(WhitespaceHandling::Separate)
+            .normalize_newlines(true)
+            .split_internal_subset(true)
+            .structured_pis(true)
+            .recognize_xml_declaration(true)
+            .strip_bom(true)
+            .allow_trailing_content(true)
+            .build(),
+    ];
+
+    let mut rng = Rng(0x5eed);
+    for _ in 0..5_000 {
+        let len = rng.next(24);
+        let input = (0..len)
+            .map(|_| PIECES[rng.next(PIECES.len())])
+            .collect::<String>();
+        for parser in &parsers {
+            if let Ok(fragment) = parser.parse(&input) {
+                let _ = fragment.to_string();
+                let _ = sgmlish::transforms::normalize_end_tags(fragment);
+            }
+            let _ = parser.parse_one(&input);
+            let _ = parser.parse_preserving_layout(&input);
+        }
+    }
+}
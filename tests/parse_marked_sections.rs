@@ -459,3 +459,64 @@ fn test_keep_unmodified_ignore_trim_whitespace() {
     );
     assert_eq!(events.next(), None);
 }
+
+#[test]
+fn test_parameter_entity_status_keyword() {
+    let parser = Parser::builder()
+        .expand_marked_sections()
+        .expand_parameter_entities(|entity| match entity {
+            "HTML.Reserved" => Some("IGNORE"),
+            "HTML.Recommended" => Some("INCLUDE"),
+            _ => None,
+        })
+        .build();
+    let fragment = parser
+        .parse(
+            "<P><![ %HTML.Reserved; [ <SPAN>reserved</SPAN> ]]><![ %HTML.Recommended; [ok]]></P>",
+        )
+        .unwrap();
+    assert_eq!(
+        fragment.as_slice(),
+        [
+            SgmlEvent::OpenStartTag { name: "P".into() },
+            SgmlEvent::CloseStartTag,
+            SgmlEvent::Character("ok".into()),
+            SgmlEvent::EndTag { name: "P".into() },
+        ]
+    );
+
+    assert!(parser.parse("<P><![ %unknown; [x]]></P>").is_err());
+}
+
+#[test]
+fn test_parameter_entity_status_keyword_requires_resolver() {
+    let result = Parser::builder()
+        .expand_marked_sections()
+        .parse("<P><![ %HTML.Reserved; [x]]></P>");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_nested_include_ignore() {
+    let parser = Parser::builder().expand_marked_sections().build();
+    let fragment = parser
+        .parse(concat!(
+            "<A>",
+            "<![INCLUDE[ one",
+            "<![IGNORE[ two <![INCLUDE[ three ]]> four <![IGNORE[ five ]]> ]]>",
+            "<![INCLUDE[ six <![IGNORE[ seven ]]> eight <![INCLUDE[ nine ]]> ]]>",
+            " ten ]]>",
+            "<![IGNORE[ <![IGNORE[ eleven ]]> <![INCLUDE[ twelve ]]> ]]>",
+            "thirteen</A>",
+        ))
+        .unwrap();
+    let text = fragment
+        .iter()
+        .filter_map(|event| match event {
+            SgmlEvent::Character(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(text, ["one", "six", "eight", "nine", "ten", "thirteen"]);
+    assert_eq!(fragment.len(), 9);
+}
@@ -0,0 +1,216 @@
+#![cfg(feature = "serde")]
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use sgmlish::ser::SerializationError;
+
+#[test]
+fn test_struct() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename = "item")]
+    struct Item {
+        id: u32,
+        label: Option<String>,
+        price: Price,
+        #[serde(rename = "tag")]
+        tags: Vec<String>,
+        note: Option<Note>,
+        draft: Option<()>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Price {
+        #[serde(rename = "$value")]
+        amount: f64,
+        currency: char,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Note {
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let item = Item {
+        id: 7,
+        label: Some("<Fish & chips>".to_owned()),
+        price: Price {
+            amount: 4.5,
+            currency: '€',
+        },
+        tags: vec!["food".to_owned(), "hot".to_owned()],
+        note: None,
+        draft: Some(()),
+    };
+    let sgml = sgmlish::to_string(&item).unwrap();
+    assert_eq!(
+        sgml,
+        concat!(
+            r#"<item id="7" label="<Fish &#38; chips>">"#,
+            r#"<price currency="€">4.5</price><tag>food</tag><tag>hot</tag><draft></draft>"#,
+            "</item>",
+        )
+    );
+
+    let fragment = sgmlish::parse(&sgml).unwrap();
+    assert_eq!(sgmlish::from_fragment::<Item>(fragment).unwrap(), item);
+    assert_eq!(
+        sgmlish::from_fragment::<Item>(sgmlish::to_fragment(&item).unwrap()).unwrap(),
+        item
+    );
+}
+
+#[test]
+fn test_enum() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename = "example")]
+    struct Example {
+        background: Background,
+        #[serde(rename = "$value")]
+        blocks: Vec<Block>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Background {
+        Color(String),
+        Gradient { from: String, to: String },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Block {
+        P(String),
+        Hr,
+    }
+
+    let example = Example {
+        background: Background::Gradient {
+            from: "blue".to_owned(),
+            to: "navy".to_owned(),
+        },
+        blocks: vec![Block::P("one".to_owned()), Block::P("two".to_owned())],
+    };
+    assert_eq!(
+        sgmlish::to_string(&example).unwrap(),
+        concat!(
+            r#"<example><background><gradient from="blue" to="navy"></gradient></background>"#,
+            "<p>one</p><p>two</p></example>",
+        )
+    );
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename = "theme")]
+    struct Theme {
+        background: Background,
+    }
+
+    let theme = Theme {
+        background: Background::Color("red".to_owned()),
+    };
+    let fragment = sgmlish::to_fragment(&theme).unwrap();
+    assert_eq!(
+        fragment.to_string(),
+        "<theme><background><color>red</color></background></theme>"
+    );
+    assert_eq!(sgmlish::from_fragment::<Theme>(fragment).unwrap(), theme);
+
+    let theme = Theme {
+        background: Background::Gradient {
+            from: "black".to_owned(),
+            to: "gold".to_owned(),
+        },
+    };
+    let fragment = sgmlish::to_fragment(&theme).unwrap();
+    assert_eq!(sgmlish::from_fragment::<Theme>(fragment).unwrap(), theme);
+
+    assert_eq!(sgmlish::to_string(&Block::Hr).unwrap(), "hr");
+}
+
+#[test]
+fn test_tag_field() {
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Section {
+        #[serde(rename = "$value")]
+        admonitions: Vec<Admonition>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Admonition {
+        #[serde(rename = "$tag")]
+        kind: String,
+        #[serde(rename = "$value")]
+        text: String,
+    }
+
+    let section = Section {
+        admonitions: vec![
+            Admonition {
+                kind: "note".to_owned(),
+                text: "Read this first".to_owned(),
+            },
+            Admonition {
+                kind: "warning".to_owned(),
+                text: "Do not unplug".to_owned(),
+            },
+        ],
+    };
+    let fragment = sgmlish::to_fragment(&section).unwrap();
+    assert_eq!(
+        fragment.to_string(),
+        "<Section><note>Read this first</note><warning>Do not unplug</warning></Section>"
+    );
+    assert_eq!(
+        sgmlish::from_fragment::<Section>(fragment).unwrap(),
+        section
+    );
+}
+
+#[test]
+fn test_errors() {
+    let mut map = BTreeMap::new();
+    map.insert("a", 1);
+    assert!(matches!(
+        sgmlish::to_fragment(&map),
+        Err(SerializationError::MissingName)
+    ));
+
+    assert!(matches!(
+        sgmlish::to_fragment(&vec![map]),
+        Err(SerializationError::UnnamedElement)
+    ));
+
+    #[derive(Serialize)]
+    struct Grid {
+        cells: BTreeMap<(u8, u8), u8>,
+    }
+
+    let mut cells = BTreeMap::new();
+    cells.insert((1, 2), 3);
+    assert!(matches!(
+        sgmlish::to_fragment(&Grid { cells }),
+        Err(SerializationError::ExpectedString("map key"))
+    ));
+
+    #[derive(Serialize)]
+    struct Blob<'a> {
+        #[serde(with = "serde_bytes_like")]
+        data: &'a [u8],
+    }
+
+    mod serde_bytes_like {
+        pub fn serialize<S: serde::Serializer>(
+            data: &[u8],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(data)
+        }
+    }
+
+    let err = sgmlish::to_string(&Blob { data: b"x" }).unwrap_err();
+    assert_eq!(err.to_string(), "serialization of bytes is not supported");
+
+    let err: sgmlish::Error = err.into();
+    assert!(matches!(err, sgmlish::Error::SerializationError(_)));
+}